@@ -0,0 +1,273 @@
+use std::{fs, path::{Path, PathBuf}, process::ExitCode};
+use clap::{Parser, Subcommand, ValueEnum};
+use odsr_engine::{
+    Characteristic, Content, ContentError, ContentLibrary, ContentSet, EntityDefinition,
+    content::{CONFIG_FILE, CONTENT_PATH_VAR, resolve_roots},
+    fs::{parse_json, parse_yaml},
+    schema::write_schemas,
+};
+use serde_json::Value;
+
+/// Lint, list and convert Draw Steel content packs
+#[derive(Parser)]
+#[command(name = "odsr-content")]
+struct Cli {
+    /// A content pack to load. Repeat for layered packs, later ones overriding earlier ones.
+    /// Defaults to `ODSR_CONTENT`, then `odsr.json`, then `content`.
+    #[arg(long = "content", global = true)]
+    roots: Vec<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check the content for parse errors, schema mismatches, dangling ability references and duplicate names
+    Lint,
+    /// List definitions with their key stats
+    List {
+        /// Only list this set
+        #[arg(value_enum)]
+        set: Option<SetArg>,
+    },
+    /// Print the stat block of a hero or monster, by ID or name
+    Show { id: String },
+    /// Convert a content file between JSON and YAML
+    Convert {
+        input: PathBuf,
+        /// Where to write the result. Printed if omitted.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Output format. Defaults to the output file's extension, or the other format.
+        #[arg(long, value_enum)]
+        to: Option<Format>,
+    },
+    /// Write the JSON Schema of every content set
+    Schema {
+        #[arg(default_value = "schemas")]
+        dir: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SetArg {
+    Heroes,
+    Monsters,
+    Abilities,
+    Maps,
+}
+
+impl From<SetArg> for ContentSet {
+    fn from(set: SetArg) -> Self {
+        match set {
+            SetArg::Heroes => ContentSet::Heroes,
+            SetArg::Monsters => ContentSet::Monsters,
+            SetArg::Abilities => ContentSet::Abilities,
+            SetArg::Maps => ContentSet::Maps,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Json,
+    Yaml,
+}
+
+impl Format {
+    fn of(path: &Path) -> Option<Format> {
+        match path.extension().and_then(|s| s.to_str()) {
+            Some("json") => Some(Format::Json),
+            Some("yaml") | Some("yml") => Some(Format::Yaml),
+            _ => None,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Lint => lint(cli.roots),
+        Command::List { set } => load(cli.roots).map(|content| list(&content, set.map(ContentSet::from))),
+        Command::Show { id } => load(cli.roots).and_then(|content| show(&content, &id)),
+        Command::Convert { input, output, to } => convert(&input, output.as_deref(), to),
+        Command::Schema { dir } => schema(&dir),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn content_roots(args: Vec<PathBuf>) -> Result<Vec<PathBuf>, ContentError> {
+    let roots = resolve_roots(args, std::env::var_os(CONTENT_PATH_VAR), Path::new(CONFIG_FILE))?;
+    if roots.is_empty() {
+        return Ok(vec![PathBuf::from("content")]);
+    }
+    Ok(roots)
+}
+
+fn load(args: Vec<PathBuf>) -> Result<Content, String> {
+    let roots = content_roots(args).map_err(|e| e.to_string())?;
+    ContentLibrary::load(&roots).map(ContentLibrary::into_content).map_err(|e| e.to_string())
+}
+
+fn lint(args: Vec<PathBuf>) -> Result<(), String> {
+    let roots = content_roots(args).map_err(|e| e.to_string())?;
+    let library = match ContentLibrary::load(&roots) {
+        Ok(library) => library,
+        Err(e) => {
+            let problems = e.problems();
+            for problem in &problems {
+                println!("error: {}", problem);
+            }
+            return Err(format!("{} problem(s) found", problems.len()));
+        }
+    };
+    let warnings = library.content().duplicate_names();
+    for warning in &warnings {
+        println!("warning: {}", warning);
+    }
+    for pack in library.packs() {
+        let content = &pack.content;
+        println!(
+            "{} {}: {} heroes, {} monsters, {} abilities, {} maps",
+            pack.manifest.name,
+            pack.manifest.version,
+            content.heroes.len(),
+            content.monsters.len(),
+            content.abilities.len(),
+            content.maps.len()
+        );
+    }
+    println!("{} warning(s)", warnings.len());
+    Ok(())
+}
+
+fn list(content: &Content, only: Option<ContentSet>) {
+    let sets = match only {
+        Some(set) => vec![set],
+        None => ContentSet::ALL.to_vec(),
+    };
+    for (i, set) in sets.into_iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{}:", set.dir_name());
+        match set {
+            ContentSet::Heroes | ContentSet::Monsters => {
+                let definitions = if set == ContentSet::Heroes { &content.heroes } else { &content.monsters };
+                for (id, definition) in definitions {
+                    println!(
+                        "  {:<24} {:<20} stamina {:>3}  size {:<3} speed {}",
+                        id, definition.name, definition.max_stamina, definition.size, definition.speed
+                    );
+                }
+            }
+            ContentSet::Abilities => {
+                for (id, ability) in &content.abilities {
+                    let damage: Vec<String> = ability.power_rolls().iter().map(|roll| roll.damage.to_string()).collect();
+                    println!("  {:<24} {:<20} range {:<2} damage {}", id, ability.name, ability.range, damage.join(" / "));
+                }
+            }
+            ContentSet::Maps => {
+                for (id, map) in &content.maps {
+                    println!("  {:<24} {:<20} {}x{}", id, map.name, map.width, map.height);
+                }
+            }
+        }
+    }
+}
+
+fn find_creature<'a>(content: &'a Content, id: &str) -> Option<(ContentSet, &'a String, &'a EntityDefinition)> {
+    let sets = [(ContentSet::Heroes, &content.heroes), (ContentSet::Monsters, &content.monsters)];
+    for (set, definitions) in sets {
+        let local = id.strip_prefix(&format!("{}/", set.dir_name())).unwrap_or(id);
+        if let Some((id, definition)) = definitions.get_key_value(local) {
+            return Some((set, id, definition));
+        }
+    }
+    for (set, definitions) in sets {
+        if let Some((id, definition)) = definitions.iter().find(|(_, definition)| definition.name.eq_ignore_ascii_case(id)) {
+            return Some((set, id, definition));
+        }
+    }
+    None
+}
+
+fn show(content: &Content, id: &str) -> Result<(), String> {
+    let (set, id, definition) = match find_creature(content, id) {
+        Some(found) => found,
+        None => return Err(format!("No hero or monster '{}'", id)),
+    };
+    println!("{} ({}/{})", definition.name, set.dir_name(), id);
+    println!("Size {}  Speed {}  Stamina {}", definition.size, definition.speed, definition.max_stamina);
+    if let Some(role) = definition.role {
+        println!("Role: {}", format!("{:?}", role).to_lowercase());
+    }
+    if !definition.characteristics.is_zero() {
+        let scores: Vec<String> = Characteristic::ALL
+            .iter()
+            .map(|characteristic| format!("{} {:+}", characteristic.letter(), definition.characteristics.get(*characteristic)))
+            .collect();
+        println!("Characteristics: {}", scores.join("  "));
+    }
+    if !definition.skills.is_empty() {
+        println!("Skills: {}", definition.skills.join(", "));
+    }
+    if !definition.movement.is_empty() {
+        let modes: Vec<String> = definition.movement.iter().map(|mode| format!("{:?}", mode).to_lowercase()).collect();
+        println!("Movement: {}", modes.join(", "));
+    }
+    println!("Abilities:");
+    for ability_id in &definition.abilities {
+        match content.abilities.get(ability_id) {
+            Some(ability) => {
+                let [tier1, tier2, tier3] = ability.power_rolls();
+                println!("  {} ({}), range {}", ability.name, ability_id, ability.range);
+                println!("    11 or lower: {} damage", tier1.damage);
+                println!("    12-16: {} damage", tier2.damage);
+                println!("    17+: {} damage", tier3.damage);
+            }
+            None => println!("  {} (not defined)", ability_id),
+        }
+    }
+    Ok(())
+}
+
+fn convert(input: &Path, output: Option<&Path>, to: Option<Format>) -> Result<(), String> {
+    let from = Format::of(input).ok_or_else(|| format!("{} is not a JSON or YAML file", input.display()))?;
+    let to = to.or_else(|| output.and_then(Format::of)).unwrap_or(match from {
+        Format::Json => Format::Yaml,
+        Format::Yaml => Format::Json,
+    });
+
+    let text = fs::read_to_string(input).map_err(|e| format!("Failed to read {}: {}", input.display(), e))?;
+    let value: Value = match from {
+        Format::Json => parse_json(&text, input),
+        Format::Yaml => parse_yaml(&text, input),
+    }
+    .map_err(|e| e.to_string())?;
+    let converted = match to {
+        Format::Json => serde_json::to_string_pretty(&value).map_err(|e| e.to_string())? + "\n",
+        Format::Yaml => serde_yaml::to_string(&value).map_err(|e| e.to_string())?,
+    };
+
+    match output {
+        Some(path) => fs::write(path, converted).map_err(|e| format!("Failed to write {}: {}", path.display(), e)),
+        None => {
+            print!("{}", converted);
+            Ok(())
+        }
+    }
+}
+
+fn schema(dir: &Path) -> Result<(), String> {
+    for path in write_schemas(dir).map_err(|e| e.to_string())? {
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
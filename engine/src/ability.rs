@@ -1,8 +1,32 @@
+use schemars::JsonSchema;
 use serde::Deserialize;
+use crate::combat::ActionType;
+use crate::dice::{BaneState, Dice, DiceExpression, EdgeState, PowerRollResultTier, RollBreakdown, RollContext, SURGES, TierOdds, power_roll_odds};
+use crate::entity::{Characteristic, Characteristics};
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+/// ID of the ability every creature can use for a melee free strike
+pub const MELEE_FREE_STRIKE: &str = "melee_free_strike";
+
+/// Most surges a creature can spend on one strike
+pub const MAX_SURGES: i32 = 3;
+
+fn default_range() -> i32 {
+    1
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, PartialEq, Eq)]
+#[schemars(deny_unknown_fields)]
 pub struct Ability {
     pub name: String,
+    /// How far away the target can be, in squares. 1 for a melee ability.
+    #[serde(default = "default_range")]
+    pub range: i32,
+    /// What using it costs from the turn. A main action unless stated.
+    #[serde(default)]
+    pub action: ActionType,
+    /// The characteristic added to the power roll. Left out for a roll with no bonus.
+    #[serde(default)]
+    pub characteristic: Option<Characteristic>,
     #[serde(rename = "power_roll_1")]
     power_roll_1: PowerRoll,
     #[serde(rename = "power_roll_2")]
@@ -11,9 +35,23 @@ pub struct Ability {
     power_roll_3: PowerRoll,
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+/// The effect of one power roll tier
+#[derive(Debug, Clone, Deserialize, JsonSchema, PartialEq, Eq)]
+#[schemars(deny_unknown_fields)]
 pub struct PowerRoll {
+    /// A number, or a formula such as `2d6 + M`
+    pub damage: DiceExpression,
+}
+
+/// The outcome of using an ability against a single target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbilityResolution {
+    pub tier: PowerRollResultTier,
     pub damage: i32,
+    /// How the damage was rolled
+    pub roll: RollBreakdown,
+    /// A critical hit with a main action grants another main action
+    pub extra_main_action: bool,
 }
 
 impl Ability {
@@ -25,4 +63,53 @@ impl Ability {
             &self.power_roll_3,
         ]
     }
-}
\ No newline at end of file
+
+    /// The power roll entry used for a tier. A critical uses the tier 3 entry.
+    pub fn power_roll_for(&self, tier: PowerRollResultTier) -> &PowerRoll {
+        match tier {
+            PowerRollResultTier::Tier1 => &self.power_roll_1,
+            PowerRollResultTier::Tier2 => &self.power_roll_2,
+            PowerRollResultTier::Tier3 | PowerRollResultTier::Tier3Critical => &self.power_roll_3,
+        }
+    }
+
+    /// What the user adds to the power roll
+    pub fn roll_bonus(&self, characteristics: &Characteristics) -> i32 {
+        self.characteristic.map_or(0, |characteristic| characteristics.get(characteristic))
+    }
+
+    /// The odds of each tier when the roller with this context uses the ability
+    pub fn odds(&self, context: &RollContext, edge: EdgeState, bane: BaneState) -> TierOdds {
+        power_roll_odds(self.roll_bonus(&context.characteristics), edge, bane)
+    }
+
+    /// Average damage over the outcomes of a power roll with these odds
+    pub fn expected_damage(&self, odds: &TierOdds, context: &RollContext) -> f64 {
+        [PowerRollResultTier::Tier1, PowerRollResultTier::Tier2, PowerRollResultTier::Tier3, PowerRollResultTier::Tier3Critical]
+            .into_iter()
+            .map(|tier| odds.of(tier) * self.power_roll_for(tier).damage.average(context).max(0.0))
+            .sum()
+    }
+
+    /// Whether spending surges changes the damage, through a formula such as `1d3 per surge`
+    pub fn uses_surges(&self) -> bool {
+        self.power_rolls().iter().any(|roll| roll.damage.per() == Some(SURGES))
+    }
+
+    pub fn is_melee(&self) -> bool {
+        self.range <= 1
+    }
+
+    /// Make the power roll for this ability with the roller's characteristic, then roll the damage for
+    /// the tier it lands on
+    pub fn resolve(&self, dice: &mut Dice, context: &RollContext, edge: EdgeState, bane: BaneState) -> AbilityResolution {
+        let tier = dice.power_roll(self.roll_bonus(&context.characteristics), edge, bane);
+        let roll = self.power_roll_for(tier).damage.roll(dice, context);
+        AbilityResolution {
+            tier,
+            damage: roll.total.max(0),
+            roll,
+            extra_main_action: tier == PowerRollResultTier::Tier3Critical && self.action == ActionType::Main,
+        }
+    }
+}
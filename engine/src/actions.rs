@@ -0,0 +1,506 @@
+use std::collections::VecDeque;
+use indexmap::IndexMap;
+use crate::ability::{Ability, AbilityResolution, MAX_SURGES, MELEE_FREE_STRIKE};
+use crate::combat::ActionType;
+use crate::dice::{BaneState, Dice, EdgeState, RollContext, SURGES, TierOdds};
+use crate::edges::compute_edges;
+use crate::encounter::Encounter;
+use crate::entity::EntityId;
+use crate::error::{CombatError, ResolutionError};
+use crate::events::CombatEvent;
+use crate::grid::{Battlefield, FallOutcome, Position};
+use crate::movement::{Movement, MovementKind, MovementProgress, OpportunityAttack, movement_allowance, resolve_opportunity_attack};
+
+/// A free strike made against a creature as it moved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreeStrike {
+    pub attacker: EntityId,
+    pub target: EntityId,
+    pub resolution: AbilityResolution,
+}
+
+/// What happened while a creature moved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveOutcome {
+    /// Where the creature ended up
+    pub position: Position,
+    pub free_strikes: Vec<FreeStrike>,
+    /// Free strikes the enemies provoked let pass
+    pub declined: Vec<OpportunityAttack>,
+    /// Falls taken stepping off ledges, whose damage has been applied
+    pub falls: Vec<FallOutcome>,
+    /// The creature dropped to a free strike or a fall and stopped short
+    pub dropped: bool,
+}
+
+/**
+ * The creature taking its turn moving along a path. Before it leaves an enemy's reach, that enemy is
+ * offered a free strike, and the creature goes no further until the offer is taken or declined. Start it,
+ * `advance` until nothing is on offer, `answer` each offer, then `finish` it.
+ */
+#[derive(Debug, Clone)]
+pub struct CreatureMove {
+    movement: Movement,
+    offers: VecDeque<OpportunityAttack>,
+    free_strikes: Vec<FreeStrike>,
+    declined: Vec<OpportunityAttack>,
+    falls: Vec<FallOutcome>,
+    dropped: bool,
+}
+
+impl CreatureMove {
+    /// Start moving along a path the creature's speed covers, spending the turn's move action, or a main
+    /// action once that is gone
+    pub fn start(encounter: &mut Encounter, mover: EntityId, path: Vec<Position>, kind: MovementKind) -> Result<Self, ResolutionError> {
+        let speed = match encounter.entity(mover) {
+            Some(entity) => entity.definition().speed,
+            None => return Err(CombatError::UnknownEntity { entity: mover }.into()),
+        };
+        let movement = Movement::start(battlefield_of(encounter, mover)?, mover, path, kind, movement_allowance(speed, kind))?;
+        encounter.update(|state| state.use_action(ActionType::Move))?;
+        Ok(Self { movement, offers: VecDeque::new(), free_strikes: vec![], declined: vec![], falls: vec![], dropped: false })
+    }
+
+    pub fn mover(&self) -> EntityId {
+        self.movement.mover()
+    }
+
+    /// The free strike waiting to be taken or declined, if any
+    pub fn offer(&self) -> Option<&OpportunityAttack> {
+        self.offers.front()
+    }
+
+    /// Step along the path until an enemy is offered a free strike or the creature gets where it is going.
+    /// Falling off a ledge deals its damage, and a creature it drops stops where it lands. Returns the free
+    /// strike on offer.
+    pub fn advance(&mut self, encounter: &mut Encounter) -> Result<Option<&OpportunityAttack>, ResolutionError> {
+        let mover = self.mover();
+        while self.offers.is_empty() {
+            let battlefield = encounter.battlefield_mut().ok_or(ResolutionError::NotOnBattlefield { entity: mover })?;
+            match self.movement.advance(battlefield)? {
+                MovementProgress::OpportunityAttacks(attacks) => self.offers.extend(attacks),
+                MovementProgress::Moved(_) => {}
+                MovementProgress::Fell(_, fall) => {
+                    self.falls.push(fall);
+                    if encounter.damage(mover, fall.damage)? <= 0 {
+                        self.movement.stop();
+                        self.dropped = true;
+                    }
+                }
+                MovementProgress::Finished => break,
+            }
+        }
+        Ok(self.offers.front())
+    }
+
+    /// Take or decline the free strike on offer. A strike taken is rolled and its damage applied. If it
+    /// drops the creature, it stops where it stands and any other offers lapse.
+    pub fn answer(
+        &mut self,
+        encounter: &mut Encounter,
+        abilities: &IndexMap<String, Ability>,
+        take: bool,
+        dice: &mut Dice,
+    ) -> Result<Option<&FreeStrike>, ResolutionError> {
+        let attack = match self.offers.pop_front() {
+            Some(attack) => attack,
+            None => return Ok(None),
+        };
+        if !take {
+            self.declined.push(attack);
+            return Ok(None);
+        }
+        let context = encounter.roll_context(attack.attacker);
+        let resolution = resolve_opportunity_attack(battlefield_of(encounter, attack.target)?, abilities, &attack, &context, dice)?;
+        encounter.record(CombatEvent::RollMade { actor: attack.attacker, ability: MELEE_FREE_STRIKE.to_string(), tier: resolution.tier });
+        if encounter.damage(attack.target, resolution.damage)? <= 0 {
+            self.movement.stop();
+            self.offers.clear();
+            self.dropped = true;
+        }
+        self.free_strikes.push(FreeStrike { attacker: attack.attacker, target: attack.target, resolution });
+        Ok(self.free_strikes.last())
+    }
+
+    /// Move along the whole path, asking `take_free_strike` whether each enemy provoked takes its free strike
+    pub fn run(
+        mut self,
+        encounter: &mut Encounter,
+        abilities: &IndexMap<String, Ability>,
+        dice: &mut Dice,
+        take_free_strike: &mut dyn FnMut(&Encounter, &OpportunityAttack) -> bool,
+    ) -> Result<MoveOutcome, ResolutionError> {
+        while let Some(attack) = self.advance(encounter)?.cloned() {
+            let take = take_free_strike(encounter, &attack);
+            self.answer(encounter, abilities, take, dice)?;
+        }
+        self.finish(encounter)
+    }
+
+    /// Stop moving and commit the turn, like using an ability. Offers not yet answered count as declined,
+    /// and the rest of the path is given up.
+    pub fn finish(mut self, encounter: &mut Encounter) -> Result<MoveOutcome, ResolutionError> {
+        let mover = self.mover();
+        self.declined.extend(self.offers.drain(..));
+        encounter.update(|state| state.commit_turn())?;
+        let position = battlefield_of(encounter, mover)?.position(mover).ok_or(ResolutionError::NotOnBattlefield { entity: mover })?;
+        Ok(MoveOutcome {
+            position,
+            free_strikes: self.free_strikes,
+            declined: self.declined,
+            falls: self.falls,
+            dropped: self.dropped,
+        })
+    }
+}
+
+/**
+ * Move the creature taking its turn along a path within its speed. Each enemy it provokes is asked through
+ * `take_free_strike` whether to make its free strike, and the damage of those taken is applied. The creature
+ * stops early if it drops. Moving spends the turn's move action, or a main action once that is gone, and
+ * commits the turn like using an ability.
+ */
+pub fn move_creature(
+    encounter: &mut Encounter,
+    abilities: &IndexMap<String, Ability>,
+    mover: EntityId,
+    path: Vec<Position>,
+    kind: MovementKind,
+    dice: &mut Dice,
+    take_free_strike: &mut dyn FnMut(&Encounter, &OpportunityAttack) -> bool,
+) -> Result<MoveOutcome, ResolutionError> {
+    CreatureMove::start(encounter, mover, path, kind)?.run(encounter, abilities, dice, take_free_strike)
+}
+
+/// The battlefield a creature is fighting on
+fn battlefield_of(encounter: &Encounter, entity: EntityId) -> Result<&Battlefield, ResolutionError> {
+    encounter.battlefield().ok_or(ResolutionError::NotOnBattlefield { entity })
+}
+
+/**
+ * Teleport the creature taking its turn up to its speed, as creatures with a teleport speed can instead of
+ * moving. Teleporting provokes no free strikes. It spends the move action like moving, and commits the turn.
+ */
+pub fn teleport_creature(encounter: &mut Encounter, mover: EntityId, destination: Position) -> Result<(), ResolutionError> {
+    let battlefield = battlefield_of(encounter, mover)?;
+    let placement = battlefield.placement(mover).ok_or(ResolutionError::NotOnBattlefield { entity: mover })?;
+    if !placement.modes.teleport {
+        return Err(ResolutionError::CannotTeleport { entity: mover });
+    }
+    let speed = match encounter.entity(mover) {
+        Some(entity) => entity.definition().speed,
+        None => return Err(CombatError::UnknownEntity { entity: mover }.into()),
+    };
+    let mut teleported = battlefield.clone();
+    teleported.teleport(mover, destination, speed)?;
+    encounter.update(|state| state.use_action(ActionType::Move)?.commit_turn())?;
+    encounter.set_battlefield(teleported);
+    Ok(())
+}
+
+/// A creature using an ability on a target, and the surges it spends on the damage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AbilityUse<'a> {
+    pub actor: EntityId,
+    pub ability: &'a str,
+    pub target: EntityId,
+    pub surges: i32,
+}
+
+impl<'a> AbilityUse<'a> {
+    pub fn new(actor: EntityId, ability: &'a str, target: EntityId) -> Self {
+        Self { actor, ability, target, surges: 0 }
+    }
+
+    pub fn with_surges(mut self, surges: i32) -> Self {
+        self.surges = surges;
+        self
+    }
+}
+
+/**
+ * Use an ability against a target in range, with any edges the battlefield gives, and apply the damage.
+ * Damage formulas use the actor's characteristics and the surges spent, up to `MAX_SURGES`. The ability's
+ * action is spent from the turn, and a critical hit with a main action grants another. This commits the turn.
+ */
+pub fn use_ability(
+    encounter: &mut Encounter,
+    abilities: &IndexMap<String, Ability>,
+    used: AbilityUse,
+    dice: &mut Dice,
+) -> Result<AbilityResolution, ResolutionError> {
+    let AbilityUse { actor, ability: ability_id, target, surges } = used;
+    let ability = abilities.get(ability_id).ok_or_else(|| ResolutionError::AbilityNotFound { name: ability_id.to_string() })?;
+    let battlefield = battlefield_of(encounter, actor)?;
+    let user = battlefield.placement(actor).ok_or(ResolutionError::NotOnBattlefield { entity: actor })?;
+    let victim = battlefield.placement(target).ok_or(ResolutionError::NotOnBattlefield { entity: target })?;
+    if user.distance_to(victim) > ability.range {
+        return Err(ResolutionError::OutOfRange { position: victim.position, range: ability.range });
+    }
+
+    let edges = compute_edges(battlefield, actor, target, ability.is_melee(), EdgeState::None)?;
+    encounter.update(|state| state.use_action(ability.action))?;
+    let context = encounter.roll_context(actor).with_count(SURGES, surges.clamp(0, MAX_SURGES));
+    let resolution = ability.resolve(dice, &context, edges.edge, BaneState::None);
+    encounter.record(CombatEvent::RollMade { actor, ability: ability_id.to_string(), tier: resolution.tier });
+    encounter.damage(target, resolution.damage)?;
+    if resolution.extra_main_action {
+        encounter.update(|state| state.grant_main_action())?;
+    }
+    encounter.update(|state| state.commit_turn())?;
+    Ok(resolution)
+}
+
+/// The odds of each tier when the actor, rolling with this context, uses an ability against a target
+/// with any edges the battlefield gives
+pub fn strike_odds(
+    battlefield: &Battlefield,
+    ability: &Ability,
+    actor: EntityId,
+    target: EntityId,
+    context: &RollContext,
+) -> Result<TierOdds, ResolutionError> {
+    let edges = compute_edges(battlefield, actor, target, ability.is_melee(), EdgeState::None)?;
+    Ok(ability.odds(context, edges.edge, BaneState::None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::TurnSide;
+    use crate::dice::{PowerRollResultTier, power_roll_odds};
+    use crate::entity::{Entity, EntityDefinition, MovementMode};
+    use crate::grid::Grid;
+    use crate::testing::{self, entity, strike};
+
+    /// A hero at (0, 0) taking their turn, and a goblin at (3, 0)
+    fn setup() -> (Encounter, IndexMap<String, Ability>, EntityId, EntityId) {
+        let mut encounter = Encounter::new();
+        let hero = encounter.add(TurnSide::PC, entity("Hero", 5)).unwrap();
+        let goblin = encounter.add(TurnSide::NPC, entity("Goblin", 5)).unwrap();
+        encounter.start(TurnSide::PC).unwrap();
+        encounter.update(|state| state.start_turn(TurnSide::PC, hero)).unwrap();
+
+        encounter.set_battlefield(Battlefield::new(Grid::new(8, 8)));
+        encounter.place(hero, Position::new(0, 0)).unwrap();
+        encounter.place(goblin, Position::new(3, 0)).unwrap();
+
+        let mut abilities = IndexMap::new();
+        abilities.insert(MELEE_FREE_STRIKE.to_string(), strike(1, [3; 3]));
+        abilities.insert("bolt".to_string(), strike(5, [3; 3]));
+        (encounter, abilities, hero, goblin)
+    }
+
+    #[test]
+    fn test_abilities_need_the_target_in_range() {
+        let (mut encounter, abilities, hero, goblin) = setup();
+        let result = use_ability(&mut encounter, &abilities, AbilityUse::new(hero, MELEE_FREE_STRIKE, goblin), &mut Dice::seeded(0));
+        assert_eq!(result, Err(ResolutionError::OutOfRange { position: Position::new(3, 0), range: 1 }));
+
+        let resolution = use_ability(&mut encounter, &abilities, AbilityUse::new(hero, "bolt", goblin), &mut Dice::seeded(0)).unwrap();
+        assert_eq!(resolution.damage, 3);
+        assert_eq!(encounter.entity(goblin).unwrap().current_health(), 2);
+        assert!(encounter.state().unwrap().is_turn_committed());
+    }
+
+    #[test]
+    fn test_critical_hits_grant_another_main_action() {
+        let (mut encounter, abilities, hero, goblin) = setup();
+        let result = use_ability(&mut encounter, &abilities, AbilityUse::new(hero, "bolt", goblin), &mut Dice::seeded(0)).unwrap();
+        assert!(!result.extra_main_action);
+        let again = use_ability(&mut encounter, &abilities, AbilityUse::new(hero, "bolt", goblin), &mut Dice::seeded(0));
+        assert_eq!(again, Err(ResolutionError::Combat(CombatError::NoActionLeft { action: ActionType::Main })));
+
+        let critical = (0..)
+            .find(|seed| Dice::seeded(*seed).power_roll(0, EdgeState::None, BaneState::None) == PowerRollResultTier::Tier3Critical)
+            .unwrap();
+        let (mut encounter, abilities, hero, goblin) = setup();
+        let result = use_ability(&mut encounter, &abilities, AbilityUse::new(hero, "bolt", goblin), &mut Dice::seeded(critical)).unwrap();
+        assert!(result.extra_main_action);
+        assert_eq!(encounter.state().unwrap().current_turn().unwrap().main_actions, 1);
+        assert!(use_ability(&mut encounter, &abilities, AbilityUse::new(hero, "bolt", goblin), &mut Dice::seeded(0)).is_ok());
+    }
+
+    #[test]
+    fn test_power_rolls_and_damage_use_the_actor_characteristics() {
+        let mut definition = entity("Brute", 5).definition().clone();
+        definition.characteristics.might = 2;
+        let mut encounter = Encounter::new();
+        let brute = encounter.add(TurnSide::PC, Entity::new("Brute".to_string(), definition)).unwrap();
+        let goblin = encounter.add(TurnSide::NPC, entity("Goblin", 5)).unwrap();
+        encounter.start(TurnSide::PC).unwrap();
+        encounter.update(|state| state.start_turn(TurnSide::PC, brute)).unwrap();
+        encounter.set_battlefield(Battlefield::new(Grid::new(4, 4)));
+        encounter.place(brute, Position::new(0, 0)).unwrap();
+        encounter.place(goblin, Position::new(1, 0)).unwrap();
+        let mut abilities = IndexMap::new();
+        let formula = |damage: &str| serde_json::json!({ "damage": damage });
+        abilities.insert(
+            "smash".to_string(),
+            serde_json::from_value(serde_json::json!({
+                "name": "Smash",
+                "characteristic": "might",
+                "power_roll_1": formula("1 + M"),
+                "power_roll_2": formula("1 + M"),
+                "power_roll_3": formula("1 + M"),
+            }))
+            .unwrap(),
+        );
+
+        let context = encounter.roll_context(brute);
+        let odds = strike_odds(encounter.battlefield().unwrap(), &abilities["smash"], brute, goblin, &context).unwrap();
+        assert_eq!(odds, power_roll_odds(2, EdgeState::None, BaneState::None));
+
+        let resolution = use_ability(&mut encounter, &abilities, AbilityUse::new(brute, "smash", goblin), &mut Dice::seeded(0)).unwrap();
+        assert_eq!(resolution.tier, Dice::seeded(0).power_roll(2, EdgeState::None, BaneState::None));
+        assert_eq!(resolution.damage, 3);
+        assert_eq!(resolution.roll.to_string(), "1 + M (2) = 3");
+    }
+
+    #[test]
+    fn test_surges_spent_multiply_per_surge_damage() {
+        let formula = serde_json::json!({ "damage": "1 per surge" });
+        let surge: Ability = serde_json::from_value(serde_json::json!({
+            "name": "Surge",
+            "range": 5,
+            "power_roll_1": formula,
+            "power_roll_2": formula,
+            "power_roll_3": formula,
+        }))
+        .unwrap();
+        assert!(surge.uses_surges());
+
+        for (spent, damage) in [(2, 2), (5, MAX_SURGES)] {
+            let (mut encounter, mut abilities, hero, goblin) = setup();
+            abilities.insert("surge".to_string(), surge.clone());
+            let used = AbilityUse::new(hero, "surge", goblin).with_surges(spent);
+            let resolution = use_ability(&mut encounter, &abilities, used, &mut Dice::seeded(0)).unwrap();
+            assert_eq!(resolution.damage, damage);
+            assert_eq!(resolution.roll.to_string(), format!("1 per surge ({}) = {}", damage, damage));
+        }
+    }
+
+    #[test]
+    fn test_flanking_raises_the_expected_damage() {
+        let (mut encounter, _, hero, goblin) = setup();
+        let fighter = encounter.add(TurnSide::PC, entity("Fighter", 5)).unwrap();
+        encounter.battlefield_mut().unwrap().move_along(hero, &[Position::new(1, 0), Position::new(2, 0)]).unwrap();
+        let strike = strike(1, [2, 5, 7]);
+
+        let alone = strike_odds(encounter.battlefield().unwrap(), &strike, hero, goblin, &RollContext::default()).unwrap();
+        assert!((strike.expected_damage(&alone, &RollContext::default()) - (0.55 * 2.0 + 0.35 * 5.0 + 0.1 * 7.0)).abs() < 1e-9);
+
+        encounter.place(fighter, Position::new(4, 0)).unwrap();
+        let flanking = strike_odds(encounter.battlefield().unwrap(), &strike, hero, goblin, &RollContext::default()).unwrap();
+        assert_eq!(flanking, power_roll_odds(0, EdgeState::Single, BaneState::None));
+        assert!(strike.expected_damage(&flanking, &RollContext::default()) > strike.expected_damage(&alone, &RollContext::default()));
+    }
+
+    #[test]
+    fn test_moving_spends_the_move_action_then_the_main_action() {
+        let (mut encounter, abilities, hero, _) = setup();
+        let step = |encounter: &mut Encounter, to: Position| {
+            move_creature(encounter, &abilities, hero, vec![to], MovementKind::Move, &mut Dice::seeded(0), &mut |_, _| true)
+        };
+        assert!(step(&mut encounter, Position::new(0, 1)).is_ok());
+        assert_eq!(encounter.state().unwrap().current_turn().unwrap().move_actions, 0);
+        assert!(step(&mut encounter, Position::new(0, 2)).is_ok());
+        assert_eq!(encounter.state().unwrap().current_turn().unwrap().main_actions, 0);
+        assert_eq!(
+            step(&mut encounter, Position::new(0, 3)),
+            Err(ResolutionError::Combat(CombatError::NoActionLeft { action: ActionType::Move }))
+        );
+    }
+
+    #[test]
+    fn test_only_creatures_with_a_teleport_speed_teleport_as_their_move() {
+        let (mut encounter, _, hero, _) = setup();
+        assert_eq!(
+            teleport_creature(&mut encounter, hero, Position::new(0, 4)),
+            Err(ResolutionError::CannotTeleport { entity: hero })
+        );
+
+        let blinker = testing::creature("Blinker", 5, &[]);
+        let blinker = EntityDefinition { movement: vec![MovementMode::Teleport], ..blinker };
+        let battlefield = encounter.battlefield_mut().unwrap();
+        battlefield.remove(hero);
+        battlefield.place_entity(hero, TurnSide::PC, &blinker, Position::new(0, 0)).unwrap();
+        assert!(matches!(
+            teleport_creature(&mut encounter, hero, Position::new(6, 0)),
+            Err(ResolutionError::OutOfRange { range: 5, .. })
+        ));
+        // Past the goblin without provoking anything
+        teleport_creature(&mut encounter, hero, Position::new(5, 0)).unwrap();
+        assert_eq!(encounter.battlefield().unwrap().position(hero), Some(Position::new(5, 0)));
+        let turn = encounter.state().unwrap().current_turn().unwrap();
+        assert_eq!(turn.move_actions, 0);
+        assert!(turn.committed);
+    }
+
+    #[test]
+    fn test_moving_away_takes_free_strikes_until_dropping() {
+        let (mut encounter, abilities, hero, goblin) = setup();
+        encounter.battlefield_mut().unwrap().move_along(hero, &[Position::new(1, 0), Position::new(2, 0)]).unwrap();
+        encounter.damage(hero, 3).unwrap();
+
+        let path = vec![Position::new(1, 0), Position::new(0, 0)];
+        let outcome = move_creature(&mut encounter, &abilities, hero, path, MovementKind::Move, &mut Dice::seeded(0), &mut |_, _| true).unwrap();
+        assert_eq!(outcome.free_strikes.len(), 1);
+        assert_eq!(outcome.free_strikes[0].attacker, goblin);
+        assert!(outcome.dropped);
+        assert_eq!(outcome.position, Position::new(2, 0));
+    }
+
+    #[test]
+    fn test_free_strikes_can_be_declined() {
+        let (mut encounter, abilities, hero, goblin) = setup();
+        encounter.battlefield_mut().unwrap().move_along(hero, &[Position::new(1, 0), Position::new(2, 0)]).unwrap();
+
+        let path = vec![Position::new(1, 0), Position::new(0, 0)];
+        let mut movement = CreatureMove::start(&mut encounter, hero, path, MovementKind::Move).unwrap();
+        assert_eq!(movement.advance(&mut encounter).unwrap(), Some(&OpportunityAttack { attacker: goblin, target: hero }));
+        // Nothing moves until the offer is answered
+        assert_eq!(encounter.battlefield().unwrap().position(hero), Some(Position::new(2, 0)));
+        assert!(movement.answer(&mut encounter, &abilities, false, &mut Dice::seeded(0)).unwrap().is_none());
+        assert_eq!(movement.advance(&mut encounter).unwrap(), None);
+
+        let outcome = movement.finish(&mut encounter).unwrap();
+        assert_eq!(outcome.position, Position::new(0, 0));
+        assert!(outcome.free_strikes.is_empty());
+        assert_eq!(outcome.declined, vec![OpportunityAttack { attacker: goblin, target: hero }]);
+        assert_eq!(encounter.entity(hero).unwrap().current_health(), 5);
+    }
+
+    #[test]
+    fn test_paths_beyond_the_creature_speed_are_refused() {
+        let (mut encounter, abilities, hero, _) = setup();
+        let path: Vec<Position> = (1..=6).map(|y| Position::new(0, y)).collect();
+        assert_eq!(
+            move_creature(&mut encounter, &abilities, hero, path, MovementKind::Move, &mut Dice::seeded(0), &mut |_, _| true),
+            Err(ResolutionError::TooFar { entity: hero, cost: 6, allowance: 5 })
+        );
+        let shift = vec![Position::new(0, 1), Position::new(0, 2)];
+        assert!(matches!(
+            CreatureMove::start(&mut encounter, hero, shift, MovementKind::Shift),
+            Err(ResolutionError::TooFar { allowance: 1, .. })
+        ));
+        assert_eq!(encounter.state().unwrap().current_turn().unwrap().move_actions, 1);
+    }
+
+    #[test]
+    fn test_walking_off_a_ledge_deals_falling_damage() {
+        let (mut encounter, abilities, hero, _) = setup();
+        let mut grid = Grid::new(8, 8);
+        grid.set_elevation(Position::new(0, 0), 3).unwrap();
+        encounter.set_battlefield(Battlefield::new(grid));
+        encounter.place(hero, Position::new(0, 0)).unwrap();
+
+        let path = vec![Position::new(0, 1), Position::new(0, 2)];
+        let outcome = move_creature(&mut encounter, &abilities, hero, path, MovementKind::Move, &mut Dice::seeded(0), &mut |_, _| true).unwrap();
+        assert_eq!(outcome.falls, vec![FallOutcome { distance: 3, damage: 6 }]);
+        // The fall drops the hero, who stops where they land
+        assert!(outcome.dropped);
+        assert_eq!(outcome.position, Position::new(0, 1));
+        assert_eq!(encounter.battlefield().unwrap().elevation_of(hero), Some(0));
+    }
+}
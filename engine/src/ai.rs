@@ -0,0 +1,438 @@
+use std::cmp::Reverse;
+use indexmap::IndexMap;
+use crate::ability::{Ability, AbilityResolution};
+use crate::actions::{AbilityUse, CreatureMove, MoveOutcome, use_ability};
+use crate::dice::Dice;
+use crate::encounter::Encounter;
+use crate::entity::{EntityId, Role};
+use crate::error::{CombatError, ResolutionError};
+use crate::grid::{Battlefield, Placement, Position};
+use crate::movement::{MovementKind, OpportunityAttack};
+
+/// Names accepted by `policy`, in the order they are offered
+pub const POLICY_NAMES: [&str; 3] = ["nearest", "lowest-stamina", "role-aware"];
+
+/// An ability to use on a target once the creature has moved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedStrike {
+    pub ability: String,
+    pub target: EntityId,
+}
+
+/// What a creature means to do with its turn. An empty plan holds position and does nothing.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TurnPlan {
+    /// Where to move first, if anywhere
+    pub destination: Option<Position>,
+    pub strike: Option<PlannedStrike>,
+}
+
+/// Everything a policy can look at when planning a turn
+pub struct TurnContext<'a> {
+    pub encounter: &'a Encounter,
+    pub battlefield: &'a Battlefield,
+    pub abilities: &'a IndexMap<String, Ability>,
+}
+
+impl<'a> TurnContext<'a> {
+    /// What a creature can look at on its turn. The encounter needs a battlefield.
+    pub fn new(encounter: &'a Encounter, abilities: &'a IndexMap<String, Ability>, creature: EntityId) -> Result<Self, ResolutionError> {
+        match encounter.battlefield() {
+            Some(battlefield) => Ok(Self { encounter, battlefield, abilities }),
+            None => Err(ResolutionError::NotOnBattlefield { entity: creature }),
+        }
+    }
+}
+
+/// What happened when a turn was played out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurnReport {
+    pub plan: TurnPlan,
+    pub movement: Option<MoveOutcome>,
+    /// The strike, if the target was still in range and the creature still standing after moving
+    pub strike: Option<AbilityResolution>,
+    /// Strikes made with main actions that critical hits granted, and who they were against
+    pub extra_strikes: Vec<(EntityId, AbilityResolution)>,
+}
+
+/**
+ * Decides how an automatically controlled creature spends its turn. Policies only plan; `play_turn`
+ * carries the plan out with the same resolution as a turn played by hand.
+ */
+pub trait Policy {
+    fn name(&self) -> &'static str;
+
+    fn plan(&self, context: &TurnContext, creature: EntityId) -> Result<TurnPlan, ResolutionError>;
+}
+
+/// Close with the nearest enemy and hit it as hard as possible
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NearestTarget;
+
+impl Policy for NearestTarget {
+    fn name(&self) -> &'static str {
+        "nearest"
+    }
+
+    fn plan(&self, context: &TurnContext, creature: EntityId) -> Result<TurnPlan, ResolutionError> {
+        match nearest_enemy(context, creature)? {
+            Some(target) => plan_attack(context, creature, target, false),
+            None => Ok(TurnPlan::default()),
+        }
+    }
+}
+
+/// Go after the enemy with the least stamina left, the nearest of them on a tie
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LowestStamina;
+
+impl Policy for LowestStamina {
+    fn name(&self) -> &'static str {
+        "lowest-stamina"
+    }
+
+    fn plan(&self, context: &TurnContext, creature: EntityId) -> Result<TurnPlan, ResolutionError> {
+        match weakest_enemy(context, creature)? {
+            Some(target) => plan_attack(context, creature, target, false),
+            None => Ok(TurnPlan::default()),
+        }
+    }
+}
+
+/**
+ * Plays each creature to its role. Artillery picks off the weakest enemy from as far away as its
+ * abilities reach. Controllers keep their distance too, going after whoever is closest to their allies.
+ * Brutes and every other role close with the nearest enemy.
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RoleAware;
+
+impl Policy for RoleAware {
+    fn name(&self) -> &'static str {
+        "role-aware"
+    }
+
+    fn plan(&self, context: &TurnContext, creature: EntityId) -> Result<TurnPlan, ResolutionError> {
+        let role = context.encounter.entity(creature).and_then(|entity| entity.definition().role);
+        let (target, keep_away) = match role {
+            Some(Role::Artillery) => (weakest_enemy(context, creature)?, true),
+            Some(Role::Controller) => (closest_to_allies(context, creature)?, true),
+            _ => (nearest_enemy(context, creature)?, false),
+        };
+        match target {
+            Some(target) => plan_attack(context, creature, target, keep_away),
+            None => Ok(TurnPlan::default()),
+        }
+    }
+}
+
+/// A built-in policy by name
+pub fn policy(name: &str) -> Option<Box<dyn Policy>> {
+    match name {
+        "nearest" => Some(Box::new(NearestTarget)),
+        "lowest-stamina" => Some(Box::new(LowestStamina)),
+        "role-aware" => Some(Box::new(RoleAware)),
+        _ => None,
+    }
+}
+
+/**
+ * Plan the creature's turn with a policy and carry it out: move along the cheapest path to the chosen
+ * square, asking `take_free_strike` whether each enemy provoked on the way makes its free strike, then use
+ * the ability if the creature is still standing and the target still in range. A critical hit's extra main
+ * action strikes again, at the same target while it stands or else the nearest enemy in range. The turn must
+ * already have been started, and is left for the caller to end.
+ */
+pub fn play_turn(
+    policy: &dyn Policy,
+    encounter: &mut Encounter,
+    abilities: &IndexMap<String, Ability>,
+    creature: EntityId,
+    dice: &mut Dice,
+    take_free_strike: &mut dyn FnMut(&Encounter, &OpportunityAttack) -> bool,
+) -> Result<TurnReport, ResolutionError> {
+    let plan = policy.plan(&TurnContext::new(encounter, abilities, creature)?, creature)?;
+    let movement = match start_planned_move(encounter, creature, &plan)? {
+        Some(movement) => Some(movement.run(encounter, abilities, dice, take_free_strike)?),
+        None => None,
+    };
+    finish_planned_turn(encounter, abilities, creature, plan, movement, dice)
+}
+
+/// Start the creature along the cheapest path to the plan's destination, if it has one. For playing a turn
+/// a step at a time, answering the free strikes offered on the way before `finish_planned_turn`.
+pub fn start_planned_move(encounter: &mut Encounter, creature: EntityId, plan: &TurnPlan) -> Result<Option<CreatureMove>, ResolutionError> {
+    let destination = match plan.destination {
+        Some(destination) => destination,
+        None => return Ok(None),
+    };
+    let speed = speed_of(encounter, creature)?;
+    let battlefield = encounter.battlefield().ok_or(ResolutionError::NotOnBattlefield { entity: creature })?;
+    let map = battlefield.movement_map(creature, Some(speed), MovementKind::Move)?;
+    let path = map.path_to(destination).ok_or(ResolutionError::OutOfRange { position: destination, range: speed })?;
+    CreatureMove::start(encounter, creature, path, MovementKind::Move).map(Some)
+}
+
+/// Carry out the rest of a plan once the creature has moved, if it had to, and report the whole turn
+pub fn finish_planned_turn(
+    encounter: &mut Encounter,
+    abilities: &IndexMap<String, Ability>,
+    creature: EntityId,
+    plan: TurnPlan,
+    movement: Option<MoveOutcome>,
+    dice: &mut Dice,
+) -> Result<TurnReport, ResolutionError> {
+    let mut strike = None;
+    let mut extra_strikes = vec![];
+    if let Some(planned) = &plan.strike {
+        let standing = encounter.entity(creature).is_some_and(|entity| entity.current_health() > 0);
+        if standing && in_range(&TurnContext::new(encounter, abilities, creature)?, creature, planned) {
+            let resolution = use_ability(encounter, abilities, AbilityUse::new(creature, &planned.ability, planned.target), dice)?;
+            let mut extra_main_action = resolution.extra_main_action;
+            strike = Some(resolution);
+            while extra_main_action {
+                let target = match follow_up_target(&TurnContext::new(encounter, abilities, creature)?, creature, planned)? {
+                    Some(target) => target,
+                    None => break,
+                };
+                let resolution = use_ability(encounter, abilities, AbilityUse::new(creature, &planned.ability, target), dice)?;
+                extra_main_action = resolution.extra_main_action;
+                extra_strikes.push((target, resolution));
+            }
+        }
+    }
+    Ok(TurnReport { plan, movement, strike, extra_strikes })
+}
+
+fn in_range(context: &TurnContext, creature: EntityId, planned: &PlannedStrike) -> bool {
+    let battlefield = context.battlefield;
+    match (battlefield.placement(creature), battlefield.placement(planned.target), context.abilities.get(&planned.ability)) {
+        (Some(placement), Some(target), Some(ability)) => placement.distance_to(target) <= ability.range,
+        _ => false,
+    }
+}
+
+/// Who to strike with an extra main action: the planned target while it stands, else the nearest enemy in range
+fn follow_up_target(context: &TurnContext, creature: EntityId, planned: &PlannedStrike) -> Result<Option<EntityId>, ResolutionError> {
+    let standing = |id: EntityId| context.encounter.entity(id).is_some_and(|entity| entity.current_health() > 0);
+    if standing(planned.target) && in_range(context, creature, planned) {
+        return Ok(Some(planned.target));
+    }
+    let range = context.abilities.get(&planned.ability).map_or(0, |ability| ability.range);
+    let placement = placement_of(context, creature)?;
+    Ok(standing_enemies(context, creature)?
+        .into_iter()
+        .filter(|(_, other)| placement.distance_to(other) <= range)
+        .min_by_key(|(_, other)| placement.distance_to(other))
+        .map(|(id, _)| id))
+}
+
+fn speed_of(encounter: &Encounter, creature: EntityId) -> Result<i32, ResolutionError> {
+    match encounter.entity(creature) {
+        Some(entity) => Ok(entity.definition().speed),
+        None => Err(CombatError::UnknownEntity { entity: creature }.into()),
+    }
+}
+
+fn placement_of<'a>(context: &TurnContext<'a>, creature: EntityId) -> Result<&'a Placement, ResolutionError> {
+    context.battlefield.placement(creature).ok_or(ResolutionError::NotOnBattlefield { entity: creature })
+}
+
+/// Enemies of the creature still standing on the battlefield, with their placements
+fn standing_enemies<'a>(context: &TurnContext<'a>, creature: EntityId) -> Result<Vec<(EntityId, &'a Placement)>, ResolutionError> {
+    let side = placement_of(context, creature)?.side;
+    Ok(context
+        .battlefield
+        .placements()
+        .iter()
+        .filter(|(id, other)| {
+            other.side != side && context.encounter.entity(**id).is_some_and(|entity| entity.current_health() > 0)
+        })
+        .map(|(id, other)| (*id, other))
+        .collect())
+}
+
+/// The closest enemy still standing. Ties go to whoever joined first.
+fn nearest_enemy(context: &TurnContext, creature: EntityId) -> Result<Option<EntityId>, ResolutionError> {
+    let placement = placement_of(context, creature)?;
+    Ok(standing_enemies(context, creature)?
+        .into_iter()
+        .min_by_key(|(_, other)| placement.distance_to(other))
+        .map(|(id, _)| id))
+}
+
+fn weakest_enemy(context: &TurnContext, creature: EntityId) -> Result<Option<EntityId>, ResolutionError> {
+    let placement = placement_of(context, creature)?;
+    let stamina = |id: EntityId| context.encounter.entity(id).map_or(0, |entity| entity.current_health());
+    Ok(standing_enemies(context, creature)?
+        .into_iter()
+        .min_by_key(|(id, other)| (stamina(*id), placement.distance_to(other)))
+        .map(|(id, _)| id))
+}
+
+/// The enemy standing closest to any of the creature's allies, or to the creature itself if it has none
+fn closest_to_allies(context: &TurnContext, creature: EntityId) -> Result<Option<EntityId>, ResolutionError> {
+    let mut allies: Vec<&Placement> = context
+        .battlefield
+        .allies_of(creature)
+        .into_iter()
+        .filter_map(|ally| context.battlefield.placement(ally))
+        .collect();
+    if allies.is_empty() {
+        allies.push(placement_of(context, creature)?);
+    }
+    Ok(standing_enemies(context, creature)?
+        .into_iter()
+        .min_by_key(|(_, other)| allies.iter().map(|ally| ally.distance_to(other)).min())
+        .map(|(id, _)| id))
+}
+
+/// The creature's hardest-hitting ability. When keeping away, reach counts for more than damage.
+fn best_ability<'a>(context: &TurnContext<'a>, creature: EntityId, keep_away: bool) -> Option<(&'a String, &'a Ability)> {
+    let entity = context.encounter.entity(creature)?;
+    let roll_context = context.encounter.roll_context(creature);
+    let damage = |ability: &Ability| ability.power_rolls().iter().map(|roll| roll.damage.average(&roll_context)).sum::<f64>();
+    let known = entity.definition().abilities.iter().filter_map(|id| context.abilities.get_key_value(id));
+    if keep_away {
+        known.max_by(|(_, a), (_, b)| a.range.cmp(&b.range).then(damage(a).total_cmp(&damage(b))))
+    } else {
+        known.max_by(|(_, a), (_, b)| damage(a).total_cmp(&damage(b)))
+    }
+}
+
+/**
+ * Get within range of the target with the creature's best ability and use it. Of the squares in range,
+ * those provoking the fewest free strikes are preferred, then the furthest from the target when keeping
+ * away, then the cheapest to reach. Out of reach, the creature gets as close as it can this turn.
+ */
+fn plan_attack(context: &TurnContext, creature: EntityId, target: EntityId, keep_away: bool) -> Result<TurnPlan, ResolutionError> {
+    let (ability_id, ability) = match best_ability(context, creature, keep_away) {
+        Some(best) => best,
+        None => return Ok(TurnPlan::default()),
+    };
+    let strike = Some(PlannedStrike { ability: ability_id.clone(), target });
+    let here = placement_of(context, creature)?;
+    let there = placement_of(context, target)?;
+    if here.distance_to(there) <= ability.range {
+        return Ok(TurnPlan { destination: None, strike });
+    }
+
+    let speed = speed_of(context.encounter, creature)?;
+    let map = context.battlefield.movement_map(creature, Some(speed), MovementKind::Move)?;
+    let distance = |position: Position| here.at(position).distance_to(there);
+    let destinations = map.destinations();
+    let in_range = destinations
+        .iter()
+        .filter(|square| distance(square.position) <= ability.range)
+        .min_by_key(|square| {
+            let away = if keep_away { distance(square.position) } else { 0 };
+            (square.provokes.len(), Reverse(away), square.cost)
+        });
+    let destination = match in_range {
+        Some(square) => Some(square.position),
+        None => destinations
+            .iter()
+            .min_by_key(|square| (distance(square.position), square.provokes.len(), square.cost))
+            .filter(|square| distance(square.position) < here.distance_to(there))
+            .map(|square| square.position),
+    };
+    Ok(TurnPlan { destination, strike })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ability::MELEE_FREE_STRIKE;
+    use crate::combat::TurnSide;
+    use crate::entity::{Entity, EntityDefinition};
+    use crate::grid::Grid;
+    use crate::testing::{self, strike};
+
+    fn abilities() -> IndexMap<String, Ability> {
+        let mut abilities = IndexMap::new();
+        abilities.insert(MELEE_FREE_STRIKE.to_string(), strike(1, [2; 3]));
+        abilities.insert("bow".to_string(), strike(5, [1; 3]));
+        abilities
+    }
+
+    fn creature(name: &str, role: Option<Role>, abilities: &[&str]) -> Entity {
+        Entity::new(name.to_string(), EntityDefinition { role, ..testing::creature(name, 10, abilities) })
+    }
+
+    /// Two heroes, the second one wounded, and a monster starting its turn at (7, 0)
+    fn setup(role: Option<Role>, abilities: &[&str]) -> (Encounter, [EntityId; 3]) {
+        let mut encounter = Encounter::new();
+        let fighter = encounter.add(TurnSide::PC, creature("Fighter", None, &[])).unwrap();
+        let rogue = encounter.add(TurnSide::PC, creature("Rogue", None, &[])).unwrap();
+        let monster = encounter.add(TurnSide::NPC, creature("Monster", role, abilities)).unwrap();
+        encounter.damage(rogue, 6).unwrap();
+        encounter.start(TurnSide::NPC).unwrap();
+        encounter.update(|state| state.start_turn(TurnSide::NPC, monster)).unwrap();
+
+        encounter.set_battlefield(Battlefield::new(Grid::new(12, 8)));
+        encounter.place(fighter, Position::new(4, 0)).unwrap();
+        encounter.place(rogue, Position::new(0, 0)).unwrap();
+        encounter.place(monster, Position::new(7, 0)).unwrap();
+        (encounter, [fighter, rogue, monster])
+    }
+
+    #[test]
+    fn test_nearest_target_closes_in() {
+        let abilities = abilities();
+        let (encounter, [fighter, _, monster]) = setup(None, &[MELEE_FREE_STRIKE]);
+        let context = TurnContext::new(&encounter, &abilities, monster).unwrap();
+        let battlefield = context.battlefield;
+
+        let plan = NearestTarget.plan(&context, monster).unwrap();
+        assert_eq!(plan.strike, Some(PlannedStrike { ability: MELEE_FREE_STRIKE.to_string(), target: fighter }));
+        let destination = plan.destination.unwrap();
+        assert_eq!(battlefield.placement(monster).unwrap().at(destination).distance_to(battlefield.placement(fighter).unwrap()), 1);
+    }
+
+    #[test]
+    fn test_lowest_stamina_goes_for_the_wounded() {
+        let abilities = abilities();
+        let (encounter, [_, rogue, monster]) = setup(None, &[MELEE_FREE_STRIKE]);
+        let context = TurnContext::new(&encounter, &abilities, monster).unwrap();
+        let battlefield = context.battlefield;
+
+        let plan = LowestStamina.plan(&context, monster).unwrap();
+        assert_eq!(plan.strike.unwrap().target, rogue);
+        // The rogue is 7 squares away, beyond a speed of 5, and the way past the fighter costs nothing extra
+        let destination = plan.destination.unwrap();
+        assert_eq!(battlefield.placement(monster).unwrap().at(destination).distance_to(battlefield.placement(rogue).unwrap()), 2);
+    }
+
+    #[test]
+    fn test_artillery_keeps_its_distance() {
+        let abilities = abilities();
+        let (encounter, [_, rogue, monster]) = setup(Some(Role::Artillery), &[MELEE_FREE_STRIKE, "bow"]);
+        let context = TurnContext::new(&encounter, &abilities, monster).unwrap();
+        let battlefield = context.battlefield;
+
+        let plan = RoleAware.plan(&context, monster).unwrap();
+        assert_eq!(plan.strike, Some(PlannedStrike { ability: "bow".to_string(), target: rogue }));
+        let destination = plan.destination.unwrap();
+        assert_eq!(battlefield.placement(monster).unwrap().at(destination).distance_to(battlefield.placement(rogue).unwrap()), 5);
+    }
+
+    #[test]
+    fn test_play_turn_moves_and_strikes() {
+        let abilities = abilities();
+        let (mut encounter, [fighter, _, monster]) = setup(Some(Role::Brute), &[MELEE_FREE_STRIKE]);
+
+        let report = play_turn(&RoleAware, &mut encounter, &abilities, monster, &mut Dice::seeded(3), &mut |_, _| true).unwrap();
+        assert!(report.movement.is_some_and(|movement| movement.free_strikes.is_empty()));
+        assert_eq!(report.strike.map(|strike| strike.damage), Some(2));
+        assert_eq!(encounter.entity(fighter).unwrap().current_health(), 8);
+        assert!(encounter.state().unwrap().is_turn_committed());
+    }
+
+    #[test]
+    fn test_policies_by_name() {
+        for name in POLICY_NAMES {
+            assert_eq!(policy(name).unwrap().name(), name);
+        }
+        assert!(policy("random").is_none());
+    }
+}
@@ -1,31 +1,65 @@
 use std::collections::HashSet;
 use indexmap::IndexSet;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use crate::entity::EntityId;
+use crate::error::CombatError;
+use crate::events::{CombatEvent, EventLog};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TurnSide {
     PC,
     NPC,
 }
 
-#[derive(Debug, Clone)]
+/// What using an ability costs from a creature's turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionType {
+    #[default]
+    Main,
+    Maneuver,
+    /// Moving, such as up to the creature's speed or shifting away
+    Move,
+    /// Used in response to a trigger, taking nothing from the turn
+    Triggered,
+    /// Costs nothing, like a free strike
+    Free,
+}
+
+fn one_action() -> u8 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CurrentTurn {
     pub side: TurnSide,
-    pub entity_name: String,
+    pub entity: EntityId,
     pub committed: bool, // True if an ability has been executed (prevents cancellation)
+    /// Main actions left this turn. A critical hit with a main action grants another.
+    #[serde(default = "one_action")]
+    pub main_actions: u8,
+    #[serde(default = "one_action")]
+    pub maneuvers: u8,
+    #[serde(default = "one_action")]
+    pub move_actions: u8,
+    /// Main actions critical hits have granted this turn, whether or not they have been spent
+    #[serde(default)]
+    pub granted_main_actions: u8,
 }
 
 /**
  * Parameters for a combat. These cannot mutate once the combat is started.
  */
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CombatParameters {
-    pcs: IndexSet<String>,
-    npcs: IndexSet<String>,
+    pcs: IndexSet<EntityId>,
+    npcs: IndexSet<EntityId>,
     starting_side: TurnSide,
 }
 
 impl CombatParameters {
-    pub fn new(pcs: impl IntoIterator<Item = String>, npcs: impl IntoIterator<Item = String>, starting_side: TurnSide) -> Self {
+    pub fn new(pcs: impl IntoIterator<Item = EntityId>, npcs: impl IntoIterator<Item = EntityId>, starting_side: TurnSide) -> Self {
         Self {
             pcs: pcs.into_iter().collect(),
             npcs: npcs.into_iter().collect(),
@@ -33,18 +67,18 @@ impl CombatParameters {
         }
     }
     
-    pub fn players(&self, turn: TurnSide) -> &IndexSet<String> {
+    pub fn players(&self, turn: TurnSide) -> &IndexSet<EntityId> {
         match turn {
             TurnSide::NPC => &self.npcs,
             TurnSide::PC => &self.pcs,
         }
     }
 
-    pub fn pcs(&self) -> &IndexSet<String> {
+    pub fn pcs(&self) -> &IndexSet<EntityId> {
         &self.pcs
     }
     
-    pub fn npcs(&self) -> &IndexSet<String> {
+    pub fn npcs(&self) -> &IndexSet<EntityId> {
         &self.npcs
     }
     
@@ -52,35 +86,44 @@ impl CombatParameters {
         self.starting_side
     }
     
-    pub fn add_pc(&mut self, pc: String) {
+    pub fn add_pc(&mut self, pc: EntityId) {
         self.pcs.insert(pc);
     }
     
-    pub fn add_npc(&mut self, npc: String) {
+    pub fn add_npc(&mut self, npc: EntityId) {
         self.npcs.insert(npc);
     }
     
-    pub fn remove_pc(&mut self, pc: &String) -> bool {
-        self.pcs.shift_remove(pc)
+    pub fn remove_pc(&mut self, pc: EntityId) -> bool {
+        self.pcs.shift_remove(&pc)
     }
     
-    pub fn remove_npc(&mut self, npc: &String) -> bool {
-        self.npcs.shift_remove(npc)
+    pub fn remove_npc(&mut self, npc: EntityId) -> bool {
+        self.npcs.shift_remove(&npc)
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CombatState {
     starting_parameters:CombatParameters,
     current_side: TurnSide,
     current_turn: Option<CurrentTurn>, // The entity currently taking their turn
-    pc_taken_turns: HashSet<String>,
-    npc_taken_turns: HashSet<String>,
+    pc_taken_turns: HashSet<EntityId>,
+    npc_taken_turns: HashSet<EntityId>,
     round:i16,
+    /// Everything that has happened in this combat, oldest first. Shared between states, so copying
+    /// the state for a transition doesn't copy the log.
+    #[serde(default)]
+    events: EventLog,
 }
 
 impl CombatState {
     pub fn new(parameters:CombatParameters) -> Self {
+        let started = CombatEvent::CombatStarted {
+            pcs: parameters.pcs.iter().copied().collect(),
+            npcs: parameters.npcs.iter().copied().collect(),
+            starting_side: parameters.starting_side,
+        };
         Self {
             pc_taken_turns: HashSet::with_capacity(parameters.pcs.len()),
             npc_taken_turns: HashSet::with_capacity(parameters.npcs.len()),
@@ -88,7 +131,42 @@ impl CombatState {
             current_turn: None,
             starting_parameters:parameters,
             round: 1,
+            events: [started].into_iter().collect(),
+        }
+    }
+
+    fn with_event(mut self, event: CombatEvent) -> Self {
+        self.push_event(event);
+        self
+    }
+
+    /// Hand the round to the other side when nobody on the current side is left waiting, such as after
+    /// the last creature yet to act is removed between turns
+    fn with_side_settled(mut self) -> Self {
+        if self.current_turn.is_some() || !self.available().is_empty() {
+            return self;
         }
+        let other = match self.current_side {
+            TurnSide::PC => TurnSide::NPC,
+            TurnSide::NPC => TurnSide::PC,
+        };
+        let waiting = match other {
+            TurnSide::PC => self.starting_parameters.pcs().iter().any(|pc| !self.pc_taken_turns.contains(pc)),
+            TurnSide::NPC => self.starting_parameters.npcs().iter().any(|npc| !self.npc_taken_turns.contains(npc)),
+        };
+        if waiting {
+            self.current_side = other;
+        }
+        self
+    }
+
+    pub(crate) fn push_event(&mut self, event: CombatEvent) {
+        self.events.push(event);
+    }
+
+    /// The event log of this combat, oldest first
+    pub fn events(&self) -> &EventLog {
+        &self.events
     }
 
     pub fn current_side(&self) -> TurnSide {
@@ -99,19 +177,19 @@ impl CombatState {
         self.round
     }
 
-    pub fn all_pcs(&self) -> &IndexSet<String> {
+    pub fn all_pcs(&self) -> &IndexSet<EntityId> {
         self.starting_parameters.pcs()
     }
 
-    pub fn all_npcs(&self) -> &IndexSet<String> {
+    pub fn all_npcs(&self) -> &IndexSet<EntityId> {
         self.starting_parameters.npcs()
     }
 
-    pub fn pc_taken_turns(&self) -> &HashSet<String> {
+    pub fn pc_taken_turns(&self) -> &HashSet<EntityId> {
         &self.pc_taken_turns
     }
 
-    pub fn npc_taken_turns(&self) -> &HashSet<String> {
+    pub fn npc_taken_turns(&self) -> &HashSet<EntityId> {
         &self.npc_taken_turns
     }
 
@@ -125,32 +203,31 @@ impl CombatState {
     }
     
     /// Mark the current turn as committed
-    pub fn commit_turn(&self) -> Result<Self, String> {
+    pub fn commit_turn(&self) -> Result<Self, CombatError> {
         let mut new_state = self.clone();
         if let Some(ref mut turn) = new_state.current_turn {
             turn.committed = true;
-            Ok(new_state)
+            Ok(new_state.with_event(CombatEvent::TurnCommitted))
         } else {
-            Err("No turn in progress to commit.".to_string())
+            Err(CombatError::NoTurnInProgress)
         }
     }
 
-    pub fn available(&self) -> IndexSet<String> {
+    pub fn available(&self) -> IndexSet<EntityId> {
         match self.current_side {
             TurnSide::PC => {
                 // PCs can act, exclude those who already took their turn or are currently taking it
                 // Preserve insertion order from IndexSet
-                let mut available: IndexSet<String> = self.starting_parameters.pcs()
+                let mut available: IndexSet<EntityId> = self.starting_parameters.pcs()
                     .iter()
                     .filter(|pc| !self.pc_taken_turns.contains(*pc))
-                    .cloned()
+                    .copied()
                     .collect();
                 
                 // Remove the entity currently taking their turn
-                if let Some(ref turn) = self.current_turn {
-                    if turn.side == TurnSide::PC {
-                        available.shift_remove(&turn.entity_name);
-                    }
+                if let Some(ref turn) = self.current_turn
+                    && turn.side == TurnSide::PC {
+                    available.shift_remove(&turn.entity);
                 }
                 
                 available
@@ -158,17 +235,16 @@ impl CombatState {
             TurnSide::NPC => {
                 // NPCs can act, exclude those who already took their turn or are currently taking it
                 // Preserve insertion order from IndexSet
-                let mut available: IndexSet<String> = self.starting_parameters.npcs()
+                let mut available: IndexSet<EntityId> = self.starting_parameters.npcs()
                     .iter()
                     .filter(|npc| !self.npc_taken_turns.contains(*npc))
-                    .cloned()
+                    .copied()
                     .collect();
                 
                 // Remove the entity currently taking their turn
-                if let Some(ref turn) = self.current_turn {
-                    if turn.side == TurnSide::NPC {
-                        available.shift_remove(&turn.entity_name);
-                    }
+                if let Some(ref turn) = self.current_turn
+                    && turn.side == TurnSide::NPC {
+                    available.shift_remove(&turn.entity);
                 }
                 
                 available
@@ -176,29 +252,29 @@ impl CombatState {
         }
     }
 
-    pub fn start_turn(&self, side: TurnSide, entity_name: String) -> Result<Self, String> {
+    pub fn start_turn(&self, side: TurnSide, entity: EntityId) -> Result<Self, CombatError> {
         // 1. Check if there's already a turn in progress
         if self.current_turn.is_some() {
-            return Err("A turn is already in progress. End the current turn first.".to_string());
+            return Err(CombatError::TurnInProgress);
         }
 
         // 2. Check correct side
         if self.current_side != side {
-            return Err(format!("Not {:?}'s turn, current side is {:?}", side, self.current_side));
+            return Err(CombatError::WrongSide { attempted: side, current: self.current_side });
         }
 
-        // 3. Check name is in CombatParameters
-        if !self.starting_parameters.players(side).contains(&entity_name) {
-            return Err(format!("{:?} '{}' is not in the combat", side, entity_name));
+        // 3. Check the entity is in CombatParameters
+        if !self.starting_parameters.players(side).contains(&entity) {
+            return Err(CombatError::NotInCombat { side, entity });
         }
 
-        // 4. Check name hasn't taken turn yet
+        // 4. Check the entity hasn't taken turn yet
         let taken_turns = match side {  
             TurnSide::PC => &self.pc_taken_turns,
             TurnSide::NPC => &self.npc_taken_turns,
         };
-        if taken_turns.contains(&entity_name) {
-            return Err(format!("{:?} '{}' has already taken their turn this round", side, entity_name));
+        if taken_turns.contains(&entity) {
+            return Err(CombatError::AlreadyActed { side, entity });
         }
         
         // Start the turn - set current_turn but don't mark as taken yet
@@ -207,23 +283,65 @@ impl CombatState {
             current_side: self.current_side,
             current_turn: Some(CurrentTurn {
                 side,
-                entity_name,
+                entity,
                 committed: false,
+                main_actions: 1,
+                maneuvers: 1,
+                move_actions: 1,
+                granted_main_actions: 0,
             }),
             pc_taken_turns: self.pc_taken_turns.clone(),
             npc_taken_turns: self.npc_taken_turns.clone(),
             round: self.round,
-        })
+            events: self.events.clone(),
+        }.with_event(CombatEvent::TurnStarted { side, entity }))
     }
 
-    pub fn cancel_turn(&self) -> Result<Self, String> {
-        if self.current_turn.is_none() {
-            return Err("No turn in progress to cancel.".to_string());
+    /**
+     * Spend an action from the current turn. A maneuver or a move can be taken in place of a main action
+     * once the turn's own is gone. Triggered and free actions cost nothing.
+     */
+    pub fn use_action(&self, action: ActionType) -> Result<Self, CombatError> {
+        let mut new_state = self.clone();
+        let turn = match new_state.current_turn.as_mut() {
+            Some(turn) => turn,
+            None => return Err(CombatError::NoTurnInProgress),
+        };
+        match action {
+            ActionType::Main if turn.main_actions > 0 => turn.main_actions -= 1,
+            ActionType::Maneuver if turn.maneuvers > 0 => turn.maneuvers -= 1,
+            ActionType::Maneuver if turn.main_actions > 0 => turn.main_actions -= 1,
+            ActionType::Move if turn.move_actions > 0 => turn.move_actions -= 1,
+            ActionType::Move if turn.main_actions > 0 => turn.main_actions -= 1,
+            ActionType::Main | ActionType::Maneuver | ActionType::Move => return Err(CombatError::NoActionLeft { action }),
+            ActionType::Triggered | ActionType::Free => {}
         }
+        let entity = turn.entity;
+        Ok(new_state.with_event(CombatEvent::ActionUsed { entity, action }))
+    }
+
+    /// Give the creature taking its turn another main action, as a critical hit does
+    pub fn grant_main_action(&self) -> Result<Self, CombatError> {
+        let mut new_state = self.clone();
+        let turn = match new_state.current_turn.as_mut() {
+            Some(turn) => turn,
+            None => return Err(CombatError::NoTurnInProgress),
+        };
+        turn.main_actions += 1;
+        turn.granted_main_actions += 1;
+        let entity = turn.entity;
+        Ok(new_state.with_event(CombatEvent::MainActionGranted { entity }))
+    }
+
+    pub fn cancel_turn(&self) -> Result<Self, CombatError> {
+        let cancelled = match &self.current_turn {
+            Some(turn) => turn,
+            None => return Err(CombatError::NoTurnInProgress),
+        };
         
         // Check if turn is committed
         if self.is_turn_committed() {
-            return Err("Cannot cancel turn after using an ability.".to_string());
+            return Err(CombatError::TurnCommitted);
         }
 
         Ok(Self {
@@ -233,26 +351,27 @@ impl CombatState {
             pc_taken_turns: self.pc_taken_turns.clone(),
             npc_taken_turns: self.npc_taken_turns.clone(),
             round: self.round,
-        })
+            events: self.events.clone(),
+        }.with_event(CombatEvent::TurnCancelled { side: cancelled.side, entity: cancelled.entity }))
     }
 
-    pub fn end_turn(&self) -> Result<Self, String> {
+    pub fn end_turn(&self) -> Result<Self, CombatError> {
         // 1. Check if there's a turn in progress
-        let (side, entity_name) = match &self.current_turn {
-            Some(turn) => (turn.side, turn.entity_name.clone()),
-            None => return Err("No turn in progress to end.".to_string()),
+        let (side, entity) = match &self.current_turn {
+            Some(turn) => (turn.side, turn.entity),
+            None => return Err(CombatError::NoTurnInProgress),
         };
         
         // Create new state with updated values - mark turn as taken
         let (pc_taken_turns, npc_taken_turns, mut next_side) = match side {
             TurnSide::PC => {
                 let mut new_pc_turns = self.pc_taken_turns.clone();
-                new_pc_turns.insert(entity_name.clone());
+                new_pc_turns.insert(entity);
                 (new_pc_turns, self.npc_taken_turns.clone(), TurnSide::NPC)
             }
             TurnSide::NPC => {
                 let mut new_npc_turns = self.npc_taken_turns.clone();
-                new_npc_turns.insert(entity_name.clone());
+                new_npc_turns.insert(entity);
                 (self.pc_taken_turns.clone(), new_npc_turns, TurnSide::PC)
             }
         };
@@ -287,24 +406,25 @@ impl CombatState {
             pc_taken_turns,
             npc_taken_turns,
             round: self.round,
-        })
+            events: self.events.clone(),
+        }.with_event(CombatEvent::TurnEnded { side, entity }))
     }
 
-    pub fn complete_round(&self) -> Result<Self, String> {
+    pub fn complete_round(&self) -> Result<Self, CombatError> {
         //check if there is a turn in progress
         if self.current_turn.is_some() {
-            return Err("A turn is in progress. End the current turn first.".to_string());
+            return Err(CombatError::TurnInProgress);
         }
 
         //can only complete round if all entities have taken their turn
         if self.pc_taken_turns.len() != self.starting_parameters.pcs().len() || self.npc_taken_turns.len() != self.starting_parameters.npcs().len() {
-            return Err("Not all entities have taken their turn.".to_string());
+            return Err(CombatError::RoundNotFinished);
         }
 
     
         //check if the current side has no remaining turns
         if !self.available().is_empty() {
-            return Err("There are still entities available to take their turn.".to_string());
+            return Err(CombatError::RoundNotFinished);
         }
 
         Ok(Self {
@@ -314,24 +434,24 @@ impl CombatState {
             pc_taken_turns: HashSet::with_capacity(self.starting_parameters.pcs().len()),
             npc_taken_turns: HashSet::with_capacity(self.starting_parameters.npcs().len()),
             round: self.round + 1,
-        })
+            events: self.events.clone(),
+        }.with_event(CombatEvent::RoundCompleted { round: self.round + 1 }))
     }
 
-    pub fn add_pc(&self, pc: String) -> Result<Self, String> {
+    pub fn add_pc(&self, pc: EntityId) -> Result<Self, CombatError> {
         // Check if PC already exists
         if self.starting_parameters.pcs().contains(&pc) {
-            return Err(format!("PC '{}' already in combat", pc));
+            return Err(CombatError::AlreadyInCombat { side: TurnSide::PC, entity: pc });
         }
 
         // Check if a turn is in progress for this entity (shouldn't happen, but be safe)
-        if let Some(ref turn) = self.current_turn {
-            if turn.side == TurnSide::PC && turn.entity_name == pc {
-                return Err("Cannot add PC that is currently taking a turn".to_string());
-            }
+        if let Some(ref turn) = self.current_turn
+            && turn.side == TurnSide::PC && turn.entity == pc {
+            return Err(CombatError::TakingTurn { side: TurnSide::PC, entity: pc });
         }
 
         let mut new_params = self.starting_parameters.clone();
-        new_params.add_pc(pc.clone());
+        new_params.add_pc(pc);
 
         Ok(Self {
             starting_parameters: new_params,
@@ -340,24 +460,24 @@ impl CombatState {
             pc_taken_turns: self.pc_taken_turns.clone(),
             npc_taken_turns: self.npc_taken_turns.clone(),
             round: self.round,
-        })
+            events: self.events.clone(),
+        }.with_event(CombatEvent::EntityAdded { side: TurnSide::PC, entity: pc }))
     }
 
-    pub fn add_npc(&self, npc: String) -> Result<Self, String> {
+    pub fn add_npc(&self, npc: EntityId) -> Result<Self, CombatError> {
         // Check if NPC already exists
         if self.starting_parameters.npcs().contains(&npc) {
-            return Err(format!("NPC '{}' already in combat", npc));
+            return Err(CombatError::AlreadyInCombat { side: TurnSide::NPC, entity: npc });
         }
 
         // Check if a turn is in progress for this entity (shouldn't happen, but be safe)
-        if let Some(ref turn) = self.current_turn {
-            if turn.side == TurnSide::NPC && turn.entity_name == npc {
-                return Err("Cannot add NPC that is currently taking a turn".to_string());
-            }
+        if let Some(ref turn) = self.current_turn
+            && turn.side == TurnSide::NPC && turn.entity == npc {
+            return Err(CombatError::TakingTurn { side: TurnSide::NPC, entity: npc });
         }
 
         let mut new_params = self.starting_parameters.clone();
-        new_params.add_npc(npc.clone());
+        new_params.add_npc(npc);
 
         Ok(Self {
             starting_parameters: new_params,
@@ -366,20 +486,20 @@ impl CombatState {
             npc_taken_turns: self.npc_taken_turns.clone(),
             pc_taken_turns: self.pc_taken_turns.clone(),
             round: self.round,
-        })
+            events: self.events.clone(),
+        }.with_event(CombatEvent::EntityAdded { side: TurnSide::NPC, entity: npc }))
     }
 
-    pub fn remove_pc(&self, pc: &String) -> Result<Self, String> {
+    pub fn remove_pc(&self, pc: EntityId) -> Result<Self, CombatError> {
         // Check if PC exists
-        if !self.starting_parameters.pcs().contains(pc) {
-            return Err(format!("PC '{}' not in combat", pc));
+        if !self.starting_parameters.pcs().contains(&pc) {
+            return Err(CombatError::NotInCombat { side: TurnSide::PC, entity: pc });
         }
 
         // Check if PC is currently taking a turn
-        if let Some(turn) = &self.current_turn {
-            if turn.side == TurnSide::PC && turn.entity_name == *pc {
-                return Err("Cannot remove PC that is currently taking a turn. End or cancel the turn first.".to_string());
-            }
+        if let Some(turn) = &self.current_turn
+            && turn.side == TurnSide::PC && turn.entity == pc {
+            return Err(CombatError::TakingTurn { side: TurnSide::PC, entity: pc });
         }
 
         let mut new_params = self.starting_parameters.clone();
@@ -387,7 +507,7 @@ impl CombatState {
 
         // Remove from taken turns if present
         let mut new_pc_taken_turns = self.pc_taken_turns.clone();
-        new_pc_taken_turns.remove(pc);
+        new_pc_taken_turns.remove(&pc);
 
         Ok(Self {
             starting_parameters: new_params,
@@ -396,20 +516,20 @@ impl CombatState {
             pc_taken_turns: new_pc_taken_turns,
             npc_taken_turns: self.npc_taken_turns.clone(),
             round: self.round,
-        })
+            events: self.events.clone(),
+        }.with_side_settled().with_event(CombatEvent::EntityRemoved { side: TurnSide::PC, entity: pc }))
     }
 
-    pub fn remove_npc(&self, npc: &String) -> Result<Self, String> {
+    pub fn remove_npc(&self, npc: EntityId) -> Result<Self, CombatError> {
         // Check if NPC exists
-        if !self.starting_parameters.npcs().contains(npc) {
-            return Err(format!("NPC '{}' not in combat", npc));
+        if !self.starting_parameters.npcs().contains(&npc) {
+            return Err(CombatError::NotInCombat { side: TurnSide::NPC, entity: npc });
         }
 
         // Check if NPC is currently taking a turn
-        if let Some(ref turn) = self.current_turn {
-            if turn.side == TurnSide::NPC && turn.entity_name == *npc {
-                return Err("Cannot remove NPC that is currently taking a turn. End or cancel the turn first.".to_string());
-            }
+        if let Some(ref turn) = self.current_turn
+            && turn.side == TurnSide::NPC && turn.entity == npc {
+            return Err(CombatError::TakingTurn { side: TurnSide::NPC, entity: npc });
         }
 
         let mut new_params = self.starting_parameters.clone();
@@ -417,7 +537,7 @@ impl CombatState {
 
         // Remove from taken turns if present
         let mut new_npc_taken_turns = self.npc_taken_turns.clone();
-        new_npc_taken_turns.remove(npc);
+        new_npc_taken_turns.remove(&npc);
 
         Ok(Self {
             starting_parameters: new_params,
@@ -426,7 +546,8 @@ impl CombatState {
             pc_taken_turns: self.pc_taken_turns.clone(),
             npc_taken_turns: new_npc_taken_turns,
             round: self.round,
-        })
+            events: self.events.clone(),
+        }.with_side_settled().with_event(CombatEvent::EntityRemoved { side: TurnSide::NPC, entity: npc }))
     }
 }
 
@@ -434,13 +555,19 @@ impl CombatState {
 mod tests {
     use super::*;
 
-    fn create_test_combat(pc_count: usize, npc_count: usize, starting_side: TurnSide) -> CombatState {
-        let pcs: HashSet<String> = (0..pc_count)
-            .map(|i| format!("PC{}", i + 1))
-            .collect();
-        let npcs: HashSet<String> = (0..npc_count)
-            .map(|i| format!("NPC{}", i + 1))
-            .collect();
+    /// The nth PC, counting from 1
+    fn pc(n: u32) -> EntityId {
+        EntityId::new(n)
+    }
+
+    /// The nth NPC, counting from 1
+    fn npc(n: u32) -> EntityId {
+        EntityId::new(100 + n)
+    }
+
+    fn create_test_combat(pc_count: u32, npc_count: u32, starting_side: TurnSide) -> CombatState {
+        let pcs: HashSet<EntityId> = (1..=pc_count).map(pc).collect();
+        let npcs: HashSet<EntityId> = (1..=npc_count).map(npc).collect();
         
         let params = CombatParameters::new(pcs, npcs, starting_side);
         CombatState::new(params)
@@ -454,12 +581,12 @@ mod tests {
         assert_eq!(combat.current_side(), TurnSide::PC);
         
         // Start and end PC1's turn
-        let combat = combat.start_turn(TurnSide::PC, "PC1".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::PC, pc(1)).unwrap();
         let combat = combat.end_turn().unwrap();
         
         // Should switch to NPC side
         assert_eq!(combat.current_side(), TurnSide::NPC);
-        assert!(combat.pc_taken_turns().contains("PC1"));
+        assert!(combat.pc_taken_turns().contains(&pc(1)));
         assert_eq!(combat.pc_taken_turns().len(), 1);
     }
 
@@ -471,12 +598,12 @@ mod tests {
         assert_eq!(combat.current_side(), TurnSide::NPC);
         
         // Start and end NPC1's turn
-        let combat = combat.start_turn(TurnSide::NPC, "NPC1".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::NPC, npc(1)).unwrap();
         let combat = combat.end_turn().unwrap();
         
         // Should switch to PC side
         assert_eq!(combat.current_side(), TurnSide::PC);
-        assert!(combat.npc_taken_turns().contains("NPC1"));
+        assert!(combat.npc_taken_turns().contains(&npc(1)));
         assert_eq!(combat.npc_taken_turns().len(), 1);
     }
 
@@ -486,12 +613,12 @@ mod tests {
         let combat = create_test_combat(2, 1, TurnSide::PC);
         
         // PC1 takes turn
-        let combat = combat.start_turn(TurnSide::PC, "PC1".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::PC, pc(1)).unwrap();
         let combat = combat.end_turn().unwrap();
         assert_eq!(combat.current_side(), TurnSide::NPC);
         
         // NPC1 takes turn (only NPC)
-        let combat = combat.start_turn(TurnSide::NPC, "NPC1".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::NPC, npc(1)).unwrap();
         let combat = combat.end_turn().unwrap();
         
         // Should stay on NPC side since all NPCs are done, but wait - let me check the logic
@@ -500,7 +627,7 @@ mod tests {
         assert_eq!(combat.current_side(), TurnSide::PC);
         
         // Now PC2 takes turn
-        let combat = combat.start_turn(TurnSide::PC, "PC2".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::PC, pc(2)).unwrap();
         let combat = combat.end_turn().unwrap();
         
         // All NPCs are done, so should stay on PC side
@@ -513,12 +640,12 @@ mod tests {
         let combat = create_test_combat(1, 2, TurnSide::PC);
         
         // PC1 takes turn
-        let combat = combat.start_turn(TurnSide::PC, "PC1".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::PC, pc(1)).unwrap();
         let combat = combat.end_turn().unwrap();
         assert_eq!(combat.current_side(), TurnSide::NPC);
         
         // NPC1 takes turn
-        let combat = combat.start_turn(TurnSide::NPC, "NPC1".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::NPC, npc(1)).unwrap();
         let combat = combat.end_turn().unwrap();
         
         // Should switch to PC, but all PCs are done, so should stay on NPC
@@ -527,6 +654,32 @@ mod tests {
         assert_eq!(combat.current_side(), TurnSide::NPC);
     }
 
+    #[test]
+    fn test_removing_the_last_waiting_creature_passes_to_the_other_side() {
+        let combat = create_test_combat(2, 2, TurnSide::PC);
+        let combat = combat.start_turn(TurnSide::PC, pc(1)).unwrap();
+        let combat = combat.end_turn().unwrap();
+        let combat = combat.start_turn(TurnSide::NPC, npc(1)).unwrap();
+        let combat = combat.end_turn().unwrap();
+        let combat = combat.start_turn(TurnSide::PC, pc(2)).unwrap();
+        let combat = combat.end_turn().unwrap();
+        assert_eq!(combat.current_side(), TurnSide::NPC);
+
+        // NPC2 falls before acting, and nobody is left on either side
+        let combat = combat.remove_npc(npc(2)).unwrap();
+        assert!(combat.complete_round().is_ok());
+
+        // Here PC2 is still waiting once NPC2 is gone
+        let combat = create_test_combat(2, 2, TurnSide::NPC);
+        let combat = combat.start_turn(TurnSide::NPC, npc(1)).unwrap();
+        let combat = combat.end_turn().unwrap();
+        let combat = combat.start_turn(TurnSide::PC, pc(1)).unwrap();
+        let combat = combat.end_turn().unwrap();
+        let combat = combat.remove_npc(npc(2)).unwrap();
+        assert_eq!(combat.current_side(), TurnSide::PC);
+        assert_eq!(combat.available().into_iter().collect::<Vec<_>>(), vec![pc(2)]);
+    }
+
     #[test]
     fn test_round_completion_basic() {
         let combat = create_test_combat(2, 2, TurnSide::PC);
@@ -534,16 +687,16 @@ mod tests {
         assert_eq!(combat.round(), 1);
         
         // Complete all turns: PC1, NPC1, PC2, NPC2
-        let combat = combat.start_turn(TurnSide::PC, "PC1".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::PC, pc(1)).unwrap();
         let combat = combat.end_turn().unwrap();
         
-        let combat = combat.start_turn(TurnSide::NPC, "NPC1".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::NPC, npc(1)).unwrap();
         let combat = combat.end_turn().unwrap();
         
-        let combat = combat.start_turn(TurnSide::PC, "PC2".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::PC, pc(2)).unwrap();
         let combat = combat.end_turn().unwrap();
         
-        let combat = combat.start_turn(TurnSide::NPC, "NPC2".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::NPC, npc(2)).unwrap();
         let combat = combat.end_turn().unwrap();
         
         // Verify all turns are taken
@@ -571,10 +724,10 @@ mod tests {
         let combat = create_test_combat(1, 1, TurnSide::NPC);
         
         // Complete all turns
-        let combat = combat.start_turn(TurnSide::NPC, "NPC1".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::NPC, npc(1)).unwrap();
         let combat = combat.end_turn().unwrap();
         
-        let combat = combat.start_turn(TurnSide::PC, "PC1".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::PC, pc(1)).unwrap();
         let combat = combat.end_turn().unwrap();
         
         // Complete round
@@ -590,12 +743,12 @@ mod tests {
         let combat = create_test_combat(1, 1, TurnSide::PC);
         
         // Start a turn but don't end it
-        let combat = combat.start_turn(TurnSide::PC, "PC1".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::PC, pc(1)).unwrap();
         
         // Try to complete round - should fail
         let result = combat.complete_round();
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("turn is in progress"));
+        assert_eq!(result.unwrap_err(), CombatError::TurnInProgress);
     }
 
     #[test]
@@ -603,16 +756,31 @@ mod tests {
         let combat = create_test_combat(2, 2, TurnSide::PC);
         
         // Only take some turns
-        let combat = combat.start_turn(TurnSide::PC, "PC1".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::PC, pc(1)).unwrap();
         let combat = combat.end_turn().unwrap();
         
-        let combat = combat.start_turn(TurnSide::NPC, "NPC1".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::NPC, npc(1)).unwrap();
         let combat = combat.end_turn().unwrap();
         
         // Try to complete round - should fail (PC2 and NPC2 haven't taken turns)
         let result = combat.complete_round();
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Not all entities have taken their turn"));
+        assert_eq!(result.unwrap_err(), CombatError::RoundNotFinished);
+    }
+
+    #[test]
+    fn test_errors_carry_entity_and_side() {
+        let combat = create_test_combat(2, 1, TurnSide::PC);
+        assert_eq!(
+            combat.start_turn(TurnSide::NPC, npc(1)).unwrap_err(),
+            CombatError::WrongSide { attempted: TurnSide::NPC, current: TurnSide::PC }
+        );
+        let combat = combat.start_turn(TurnSide::PC, pc(1)).unwrap().end_turn().unwrap();
+        let combat = combat.start_turn(TurnSide::NPC, npc(1)).unwrap().end_turn().unwrap();
+        assert_eq!(
+            combat.start_turn(TurnSide::PC, pc(1)).unwrap_err(),
+            CombatError::AlreadyActed { side: TurnSide::PC, entity: pc(1) }
+        );
     }
 
     #[test]
@@ -620,16 +788,16 @@ mod tests {
         let combat = create_test_combat(2, 2, TurnSide::PC);
         
         // Take all turns but one
-        let combat = combat.start_turn(TurnSide::PC, "PC1".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::PC, pc(1)).unwrap();
         let combat = combat.end_turn().unwrap();
         
-        let combat = combat.start_turn(TurnSide::NPC, "NPC1".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::NPC, npc(1)).unwrap();
         let combat = combat.end_turn().unwrap();
         
-        let combat = combat.start_turn(TurnSide::PC, "PC2".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::PC, pc(2)).unwrap();
         let combat = combat.end_turn().unwrap();
         
-        let combat = combat.start_turn(TurnSide::NPC, "NPC2".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::NPC, npc(2)).unwrap();
         let combat = combat.end_turn().unwrap();
         
         // This should actually work since all turns are taken
@@ -644,9 +812,9 @@ mod tests {
         let combat = create_test_combat(1, 1, TurnSide::PC);
         
         // Round 1
-        let mut combat = combat.start_turn(TurnSide::PC, "PC1".to_string()).unwrap();
+        let mut combat = combat.start_turn(TurnSide::PC, pc(1)).unwrap();
         combat = combat.end_turn().unwrap();
-        combat = combat.start_turn(TurnSide::NPC, "NPC1".to_string()).unwrap();
+        combat = combat.start_turn(TurnSide::NPC, npc(1)).unwrap();
         combat = combat.end_turn().unwrap();
         combat = combat.complete_round().unwrap();
         
@@ -654,9 +822,9 @@ mod tests {
         assert_eq!(combat.current_side(), TurnSide::PC);
         
         // Round 2
-        combat = combat.start_turn(TurnSide::PC, "PC1".to_string()).unwrap();
+        combat = combat.start_turn(TurnSide::PC, pc(1)).unwrap();
         combat = combat.end_turn().unwrap();
-        combat = combat.start_turn(TurnSide::NPC, "NPC1".to_string()).unwrap();
+        combat = combat.start_turn(TurnSide::NPC, npc(1)).unwrap();
         combat = combat.end_turn().unwrap();
         combat = combat.complete_round().unwrap();
         
@@ -671,33 +839,59 @@ mod tests {
         let mut combat = combat;
         
         // PC1 -> NPC1 -> PC2 -> NPC2
-        combat = combat.start_turn(TurnSide::PC, "PC1".to_string()).unwrap();
+        combat = combat.start_turn(TurnSide::PC, pc(1)).unwrap();
         combat = combat.end_turn().unwrap();
         assert_eq!(combat.current_side(), TurnSide::NPC);
         
-        combat = combat.start_turn(TurnSide::NPC, "NPC1".to_string()).unwrap();
+        combat = combat.start_turn(TurnSide::NPC, npc(1)).unwrap();
         combat = combat.end_turn().unwrap();
         assert_eq!(combat.current_side(), TurnSide::PC);
         
-        combat = combat.start_turn(TurnSide::PC, "PC2".to_string()).unwrap();
+        combat = combat.start_turn(TurnSide::PC, pc(2)).unwrap();
         combat = combat.end_turn().unwrap();
         assert_eq!(combat.current_side(), TurnSide::NPC);
         
-        combat = combat.start_turn(TurnSide::NPC, "NPC2".to_string()).unwrap();
+        combat = combat.start_turn(TurnSide::NPC, npc(2)).unwrap();
         combat = combat.end_turn().unwrap();
         // After NPC2, all NPCs are done, so should stay on NPC side
         assert_eq!(combat.current_side(), TurnSide::NPC);
     }
 
+    #[test]
+    fn test_actions_are_spent_from_the_turn() {
+        let combat = create_test_combat(1, 1, TurnSide::PC).start_turn(TurnSide::PC, pc(1)).unwrap();
+        let struck = combat.use_action(ActionType::Main).unwrap();
+        assert_eq!(struck.current_turn().unwrap().main_actions, 0);
+        assert!(matches!(struck.use_action(ActionType::Main), Err(CombatError::NoActionLeft { action: ActionType::Main })));
+        assert!(struck.use_action(ActionType::Free).is_ok());
+
+        // A critical hit gives the main action back
+        let granted = struck.grant_main_action().unwrap();
+        assert_eq!(granted.current_turn().unwrap().main_actions, 1);
+        assert_eq!(granted.current_turn().unwrap().granted_main_actions, 1);
+
+        // A second move takes the place of the main action
+        let moved = combat.use_action(ActionType::Move).unwrap().use_action(ActionType::Move).unwrap();
+        let turn = moved.current_turn().unwrap();
+        assert_eq!((turn.main_actions, turn.move_actions), (0, 0));
+        assert!(matches!(moved.use_action(ActionType::Move), Err(CombatError::NoActionLeft { action: ActionType::Move })));
+
+        // A second maneuver takes the place of the main action
+        let maneuvered = combat.use_action(ActionType::Maneuver).unwrap().use_action(ActionType::Maneuver).unwrap();
+        let turn = maneuvered.current_turn().unwrap();
+        assert_eq!((turn.main_actions, turn.maneuvers), (0, 0));
+        assert!(create_test_combat(1, 1, TurnSide::PC).use_action(ActionType::Main).is_err());
+    }
+
     #[test]
     fn test_end_turn_marks_entity_as_taken() {
         let combat = create_test_combat(2, 2, TurnSide::PC);
         
-        let combat = combat.start_turn(TurnSide::PC, "PC1".to_string()).unwrap();
-        assert!(!combat.pc_taken_turns().contains("PC1"));
+        let combat = combat.start_turn(TurnSide::PC, pc(1)).unwrap();
+        assert!(!combat.pc_taken_turns().contains(&pc(1)));
         
         let combat = combat.end_turn().unwrap();
-        assert!(combat.pc_taken_turns().contains("PC1"));
+        assert!(combat.pc_taken_turns().contains(&pc(1)));
         assert_eq!(combat.pc_taken_turns().len(), 1);
     }
 
@@ -705,7 +899,7 @@ mod tests {
     fn test_end_turn_clears_current_turn() {
         let combat = create_test_combat(1, 1, TurnSide::PC);
         
-        let combat = combat.start_turn(TurnSide::PC, "PC1".to_string()).unwrap();
+        let combat = combat.start_turn(TurnSide::PC, pc(1)).unwrap();
         assert!(combat.current_turn().is_some());
         
         let combat = combat.end_turn().unwrap();
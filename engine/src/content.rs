@@ -0,0 +1,373 @@
+use std::{ffi::OsString, fs, path::{Path, PathBuf}};
+use indexmap::IndexMap;
+use serde::Deserialize;
+use crate::ability::Ability;
+use crate::entity::EntityDefinition;
+use crate::error::{ContentError, ContentWarning};
+use crate::fs::parse_json;
+use crate::map::{MapDefinition, load_maps};
+use crate::schema::load_checked_set;
+
+/// Environment variable listing content roots, separated like `PATH`
+pub const CONTENT_PATH_VAR: &str = "ODSR_CONTENT";
+/// Config file with a `content` list of roots, relative to the file
+pub const CONFIG_FILE: &str = "odsr.json";
+/// Manifest every content root must have
+pub const MANIFEST_FILE: &str = "pack.json";
+
+/// The kinds of definition a pack holds, each in a subdirectory of the same name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentSet {
+    Heroes,
+    Monsters,
+    Abilities,
+    Maps,
+}
+
+impl ContentSet {
+    pub const ALL: [ContentSet; 4] = [ContentSet::Heroes, ContentSet::Monsters, ContentSet::Abilities, ContentSet::Maps];
+
+    pub fn dir_name(&self) -> &'static str {
+        match self {
+            ContentSet::Heroes => "heroes",
+            ContentSet::Monsters => "monsters",
+            ContentSet::Abilities => "abilities",
+            ContentSet::Maps => "maps",
+        }
+    }
+}
+
+/**
+ * All the definitions a combat draws from, loaded from one content directory with
+ * `heroes`, `monsters`, `abilities` and `maps` subdirectories. Any of them may be missing.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Content {
+    pub heroes: IndexMap<String, EntityDefinition>,
+    pub monsters: IndexMap<String, EntityDefinition>,
+    pub abilities: IndexMap<String, Ability>,
+    pub maps: IndexMap<String, MapDefinition>,
+}
+
+impl Content {
+    /// Load every set of a directory, checking files against the schemas and reporting all problems at once.
+    /// References aren't checked, since they may point into another pack.
+    pub fn load(root: &Path) -> Result<Self, ContentError> {
+        let mut errors = vec![];
+        let mut content = Content::default();
+        let existing = |set: &str| Some(root.join(set)).filter(|dir| dir.is_dir());
+        if let Some(dir) = existing("heroes") {
+            match load_checked_set(&dir, ContentSet::Heroes, &[]) {
+                Ok(heroes) => content.heroes = heroes,
+                Err(e) => errors.push(e),
+            }
+        }
+        if let Some(dir) = existing("monsters") {
+            match load_checked_set(&dir, ContentSet::Monsters, &[]) {
+                Ok(monsters) => content.monsters = monsters,
+                Err(e) => errors.push(e),
+            }
+        }
+        if let Some(dir) = existing("abilities") {
+            match load_checked_set(&dir, ContentSet::Abilities, &[]) {
+                Ok(abilities) => content.abilities = abilities,
+                Err(e) => errors.push(e),
+            }
+        }
+        if let Some(dir) = existing("maps") {
+            match load_maps(&dir) {
+                Ok(maps) => content.maps = maps,
+                Err(e) => errors.push(e),
+            }
+        }
+        ContentError::from_all(errors)?;
+        Ok(content)
+    }
+
+    /// Put another pack's definitions on top of these. Definitions with the same ID are replaced.
+    pub fn layer(&mut self, other: Content) {
+        self.heroes.extend(other.heroes);
+        self.monsters.extend(other.monsters);
+        self.abilities.extend(other.abilities);
+        self.maps.extend(other.maps);
+    }
+
+    /// Check that every ability a hero or monster lists is defined
+    pub fn validate(&self) -> Result<(), ContentError> {
+        let mut errors = vec![];
+        for (kind, definitions) in [("heroes", &self.heroes), ("monsters", &self.monsters)] {
+            for (id, definition) in definitions {
+                for ability in &definition.abilities {
+                    if !self.abilities.contains_key(ability) {
+                        errors.push(ContentError::UnknownAbility {
+                            definition: format!("{}/{}", kind, id),
+                            ability: ability.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        ContentError::from_all(errors)
+    }
+
+    /// Definitions that share a display name with another of the same set. Not an error for
+    /// loading, but worth a warning when linting.
+    pub fn duplicate_names(&self) -> Vec<ContentWarning> {
+        let names: Vec<(ContentSet, Vec<(&String, &String)>)> = vec![
+            (ContentSet::Heroes, self.heroes.iter().map(|(id, hero)| (id, &hero.name)).collect()),
+            (ContentSet::Monsters, self.monsters.iter().map(|(id, monster)| (id, &monster.name)).collect()),
+            (ContentSet::Abilities, self.abilities.iter().map(|(id, ability)| (id, &ability.name)).collect()),
+            (ContentSet::Maps, self.maps.iter().map(|(id, map)| (id, &map.name)).collect()),
+        ];
+        let mut duplicates = vec![];
+        for (set, definitions) in names {
+            let mut by_name: IndexMap<&String, Vec<String>> = IndexMap::new();
+            for (id, name) in definitions {
+                by_name.entry(name).or_default().push(id.clone());
+            }
+            for (name, ids) in by_name {
+                if ids.len() > 1 {
+                    duplicates.push(ContentWarning::DuplicateName { set, name: name.clone(), ids });
+                }
+            }
+        }
+        duplicates
+    }
+}
+
+/// Describes a content pack: `pack.json` at its root
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct PackManifest {
+    pub name: String,
+    pub version: String,
+    /// Names of packs that must be loaded before this one
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ContentPack {
+    pub manifest: PackManifest,
+    pub root: PathBuf,
+    pub content: Content,
+}
+
+impl ContentPack {
+    /// Load a pack from a content root with a manifest
+    pub fn load(root: &Path) -> Result<Self, ContentError> {
+        let manifest_path = root.join(MANIFEST_FILE);
+        if !manifest_path.is_file() {
+            return Err(ContentError::MissingManifest { path: root.to_path_buf() });
+        }
+        let manifest_text = match fs::read_to_string(&manifest_path) {
+            Ok(text) => text,
+            Err(e) => return Err(ContentError::Io { path: manifest_path, source: e }),
+        };
+        let manifest: PackManifest = parse_json(&manifest_text, &manifest_path)?;
+        let content = Content::load(root)?;
+        Ok(Self { manifest, root: root.to_path_buf(), content })
+    }
+}
+
+/**
+ * Content layered from several packs. Later packs add definitions or override earlier ones by ID,
+ * so homebrew can sit on top of the official content.
+ */
+#[derive(Debug, Clone)]
+pub struct ContentLibrary {
+    packs: Vec<ContentPack>,
+    content: Content,
+}
+
+impl ContentLibrary {
+    /// Load packs in order, check their dependencies, and check references across the merged content
+    pub fn load(roots: &[PathBuf]) -> Result<Self, ContentError> {
+        let mut errors = vec![];
+        let mut packs: Vec<ContentPack> = vec![];
+        for root in roots {
+            match ContentPack::load(root) {
+                Ok(pack) => {
+                    for dependency in &pack.manifest.dependencies {
+                        if !packs.iter().any(|loaded| &loaded.manifest.name == dependency) {
+                            errors.push(ContentError::MissingDependency {
+                                pack: pack.manifest.name.clone(),
+                                dependency: dependency.clone(),
+                            });
+                        }
+                    }
+                    packs.push(pack);
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        ContentError::from_all(errors)?;
+
+        let mut content = Content::default();
+        for pack in &packs {
+            content.layer(pack.content.clone());
+        }
+        content.validate()?;
+        Ok(Self { packs, content })
+    }
+
+    pub fn packs(&self) -> &[ContentPack] {
+        &self.packs
+    }
+
+    pub fn content(&self) -> &Content {
+        &self.content
+    }
+
+    pub fn into_content(self) -> Content {
+        self.content
+    }
+}
+
+#[derive(Deserialize)]
+struct ContentConfig {
+    content: Vec<PathBuf>,
+}
+
+/// Choose the content roots. Roots given on the command line win, then the `ODSR_CONTENT`
+/// environment variable, then the config file. Empty if none of them lists any.
+pub fn resolve_roots(args: Vec<PathBuf>, env: Option<OsString>, config: &Path) -> Result<Vec<PathBuf>, ContentError> {
+    if !args.is_empty() {
+        return Ok(args);
+    }
+    if let Some(env) = env {
+        let roots: Vec<PathBuf> = std::env::split_paths(&env).filter(|root| !root.as_os_str().is_empty()).collect();
+        if !roots.is_empty() {
+            return Ok(roots);
+        }
+    }
+    if config.is_file() {
+        let text = match fs::read_to_string(config) {
+            Ok(text) => text,
+            Err(e) => return Err(ContentError::Io { path: config.to_path_buf(), source: e }),
+        };
+        let parsed: ContentConfig = parse_json(&text, config)?;
+        let base = config.parent().unwrap_or(Path::new(""));
+        return Ok(parsed.content.into_iter().map(|root| base.join(root)).collect());
+    }
+    Ok(vec![])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content_dir(name: &str) -> std::path::PathBuf {
+        let root = std::env::temp_dir().join(format!("odsr-content-{}-{}", name, std::process::id()));
+        for set in ["heroes", "monsters", "abilities", "maps"] {
+            fs::create_dir_all(root.join(set)).unwrap();
+        }
+        fs::write(
+            root.join("abilities/melee_free_strike.json"),
+            r#"{"name": "Free Strike", "power_roll_1": {"damage": 2}, "power_roll_2": {"damage": 4}, "power_roll_3": {"damage": 6}}"#,
+        )
+        .unwrap();
+        root
+    }
+
+    #[test]
+    fn test_loads_and_skips_non_content_files() {
+        let root = content_dir("valid");
+        fs::write(root.join("heroes/hero.json"), r#"{"name": "Hero", "max_stamina": 10, "abilities": ["melee_free_strike"]}"#).unwrap();
+        fs::write(root.join("heroes/README.md"), "# Heroes").unwrap();
+        let content = Content::load(&root);
+        fs::remove_dir_all(&root).ok();
+        let content = content.unwrap();
+        assert_eq!(content.heroes.len(), 1);
+        assert_eq!(content.abilities.len(), 1);
+    }
+
+    #[test]
+    fn test_reports_every_problem_with_position() {
+        let root = content_dir("broken");
+        fs::write(root.join("heroes/hero.json"), "{\n  \"name\": \"Hero\",\n  \"max_stamina\": ten\n}").unwrap();
+        fs::write(root.join("monsters/goblin.json"), r#"{"name": "Goblin", "max_stamina": 5, "abilities": ["stab"]}"#).unwrap();
+        fs::write(root.join("monsters/ogre.json"), r#"{"name": "Ogre", "max_stamina": 20}"#).unwrap();
+        let error = Content::load(&root).unwrap_err();
+        fs::remove_dir_all(&root).ok();
+
+        let problems = error.problems();
+        assert_eq!(problems.len(), 2);
+        assert!(matches!(problems[0], ContentError::Parse { line: 3, column: 19, .. }));
+        assert!(matches!(problems[1], ContentError::Schema { pointer, message, .. } if pointer.is_empty() && message.contains("abilities")));
+        assert!(error.to_string().contains("hero.json at line 3, column 19"));
+    }
+
+    #[test]
+    fn test_unknown_abilities_are_reported() {
+        let mut content = Content::default();
+        let goblin: EntityDefinition =
+            serde_json::from_str(r#"{"name": "Goblin", "max_stamina": 5, "abilities": ["stab", "bite"]}"#).unwrap();
+        content.monsters.insert("goblin".to_string(), goblin);
+        let error = content.validate().unwrap_err();
+        assert_eq!(error.problems().len(), 2);
+        assert!(matches!(
+            error.problems()[0],
+            ContentError::UnknownAbility { definition, ability } if definition == "monsters/goblin" && ability == "stab"
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_names_are_found() {
+        let mut content = Content::default();
+        let goblin: EntityDefinition = serde_json::from_str(r#"{"name": "Goblin", "max_stamina": 5, "abilities": []}"#).unwrap();
+        content.monsters.insert("goblin".to_string(), goblin.clone());
+        content.monsters.insert("goblins/goblin".to_string(), goblin.clone());
+        content.heroes.insert("goblin".to_string(), goblin);
+        let duplicates = content.duplicate_names();
+        assert_eq!(duplicates.len(), 1);
+        assert!(matches!(
+            &duplicates[0],
+            ContentWarning::DuplicateName { set: ContentSet::Monsters, ids, .. } if ids.len() == 2
+        ));
+    }
+
+    #[test]
+    fn test_later_packs_override_by_id() {
+        let core = content_dir("core");
+        fs::write(core.join(MANIFEST_FILE), r#"{"name": "core", "version": "1.0.0"}"#).unwrap();
+        fs::write(core.join("monsters/goblin.json"), r#"{"name": "Goblin", "max_stamina": 5, "abilities": ["melee_free_strike"]}"#).unwrap();
+        let homebrew = std::env::temp_dir().join(format!("odsr-content-homebrew-{}", std::process::id()));
+        fs::create_dir_all(homebrew.join("monsters")).unwrap();
+        fs::write(homebrew.join(MANIFEST_FILE), r#"{"name": "homebrew", "version": "0.1.0", "dependencies": ["core"]}"#).unwrap();
+        fs::write(homebrew.join("monsters/goblin.json"), r#"{"name": "Big Goblin", "max_stamina": 9, "abilities": ["melee_free_strike"]}"#).unwrap();
+        fs::write(homebrew.join("monsters/kobold.json"), r#"{"name": "Kobold", "max_stamina": 4, "abilities": []}"#).unwrap();
+
+        let library = ContentLibrary::load(&[core.clone(), homebrew.clone()]);
+        let reversed = ContentLibrary::load(&[homebrew.clone(), core.clone()]);
+        fs::remove_dir_all(&core).ok();
+        fs::remove_dir_all(&homebrew).ok();
+
+        let library = library.unwrap();
+        assert_eq!(library.packs().len(), 2);
+        assert_eq!(library.content().monsters.len(), 2);
+        assert_eq!(library.content().monsters["goblin"].name, "Big Goblin");
+        assert!(matches!(
+            reversed.unwrap_err().problems()[0],
+            ContentError::MissingDependency { pack, dependency } if pack == "homebrew" && dependency == "core"
+        ));
+    }
+
+    #[test]
+    fn test_roots_are_resolved_by_priority() {
+        let config = std::env::temp_dir().join(format!("odsr-config-{}", std::process::id())).join(CONFIG_FILE);
+        fs::create_dir_all(config.parent().unwrap()).unwrap();
+        fs::write(&config, r#"{"content": ["core", "homebrew"]}"#).unwrap();
+        let env = std::env::join_paths(["a", "b"]).unwrap();
+
+        let from_args = resolve_roots(vec![PathBuf::from("cli")], Some(env.clone()), &config).unwrap();
+        let from_env = resolve_roots(vec![], Some(env), &config).unwrap();
+        let from_config = resolve_roots(vec![], None, &config).unwrap();
+        let nothing = resolve_roots(vec![], None, Path::new("missing.json")).unwrap();
+        fs::remove_dir_all(config.parent().unwrap()).ok();
+
+        assert_eq!(from_args, vec![PathBuf::from("cli")]);
+        assert_eq!(from_env, vec![PathBuf::from("a"), PathBuf::from("b")]);
+        assert_eq!(from_config, vec![config.parent().unwrap().join("core"), config.parent().unwrap().join("homebrew")]);
+        assert!(nothing.is_empty());
+    }
+}
@@ -1,6 +1,11 @@
-use rand::Rng;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub mod expression;
+
+pub use expression::{DiceExpression, RollBreakdown, RollContext, RolledTerm, SURGES, Term};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PowerRollResultTier {
     Tier1, 
     Tier2, 
@@ -15,6 +20,22 @@ pub enum EdgeState {
     Double
 }
 
+impl EdgeState {
+    /// Add more edges on top of this state. Two or more edges make a double edge.
+    pub fn with_additional(self, edges: usize) -> Self {
+        let current = match self {
+            EdgeState::None => 0,
+            EdgeState::Single => 1,
+            EdgeState::Double => 2,
+        };
+        match current + edges {
+            0 => EdgeState::None,
+            1 => EdgeState::Single,
+            _ => EdgeState::Double,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BaneState {
     None,
@@ -39,6 +60,89 @@ pub fn power_roll(bonus: i32, edge: EdgeState, bane: BaneState) -> PowerRollResu
     to_power_roll(roll_result, bonus, edge, bane)
 }
 
+/**
+ * A source of dice rolls that can be seeded, so that a run of rolls can be repeated exactly.
+ * Simulations and tests use a seed; play uses fresh entropy.
+ */
+#[derive(Debug, Clone)]
+pub struct Dice {
+    rng: StdRng,
+}
+
+impl Dice {
+    /// Dice seeded from the operating system
+    pub fn new() -> Self {
+        Self { rng: StdRng::from_entropy() }
+    }
+
+    /// Dice that always roll the same sequence for the same seed
+    pub fn seeded(seed: u64) -> Self {
+        Self { rng: StdRng::seed_from_u64(seed) }
+    }
+
+    /// Roll `count` dice with `sides` sides each
+    pub fn roll(&mut self, count: u32, sides: u32) -> Vec<i32> {
+        (0..count).map(|_| self.rng.gen_range(1..=sides as i32)).collect()
+    }
+
+    pub fn d3s(&mut self, d3s: u32) -> Vec<i32> {
+        self.roll(d3s, 3)
+    }
+
+    pub fn d10s(&mut self, d10s: u32) -> Vec<i32> {
+        self.roll(d10s, 10)
+    }
+
+    pub fn power_roll(&mut self, bonus: i32, edge: EdgeState, bane: BaneState) -> PowerRollResultTier {
+        let roll_result = self.d10s(2).iter().sum();
+        to_power_roll(roll_result, bonus, edge, bane)
+    }
+}
+
+/// The chance of each power roll outcome, from 0 to 1. `tier3` doesn't include criticals.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TierOdds {
+    pub tier1: f64,
+    pub tier2: f64,
+    pub tier3: f64,
+    pub critical: f64,
+}
+
+impl TierOdds {
+    pub fn of(&self, tier: PowerRollResultTier) -> f64 {
+        match tier {
+            PowerRollResultTier::Tier1 => self.tier1,
+            PowerRollResultTier::Tier2 => self.tier2,
+            PowerRollResultTier::Tier3 => self.tier3,
+            PowerRollResultTier::Tier3Critical => self.critical,
+        }
+    }
+}
+
+/// The exact odds of each outcome of a power roll, counted over all 100 ways 2d10 can land
+pub fn power_roll_odds(bonus: i32, edge: EdgeState, bane: BaneState) -> TierOdds {
+    let mut counts = [0; 4];
+    for first in 1..=10 {
+        for second in 1..=10 {
+            let index = match to_power_roll(first + second, bonus, edge, bane) {
+                PowerRollResultTier::Tier1 => 0,
+                PowerRollResultTier::Tier2 => 1,
+                PowerRollResultTier::Tier3 => 2,
+                PowerRollResultTier::Tier3Critical => 3,
+            };
+            counts[index] += 1;
+        }
+    }
+    let chance = |count: i32| count as f64 / 100.0;
+    TierOdds { tier1: chance(counts[0]), tier2: chance(counts[1]), tier3: chance(counts[2]), critical: chance(counts[3]) }
+}
+
+impl Default for Dice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 fn to_power_roll(natural_roll: i32, bonus:i32, edge: EdgeState, bane: BaneState) -> PowerRollResultTier {
 
     let did_crit = natural_roll == 19 || natural_roll == 20;
@@ -48,13 +152,13 @@ fn to_power_roll(natural_roll: i32, bonus:i32, edge: EdgeState, bane: BaneState)
 
     let mut total_edge = 0;    
     match edge {
-        EdgeState::Single => total_edge = total_edge + 1,
-        EdgeState::Double => total_edge = total_edge + 2,
+        EdgeState::Single => total_edge += 1,
+        EdgeState::Double => total_edge += 2,
         _ => (),
     }
     match bane {
-        BaneState::Single => total_edge = total_edge - 1,
-        BaneState::Double => total_edge = total_edge - 2,
+        BaneState::Single => total_edge -= 1,
+        BaneState::Double => total_edge -= 2,
         _ => (),
     }
 
@@ -66,15 +170,13 @@ fn to_power_roll(natural_roll: i32, bonus:i32, edge: EdgeState, bane: BaneState)
 
     let tier = to_tier(final_roll_result);
 
-    let final_tier = match (total_edge, tier) {
+    match (total_edge, tier) {
         (2, PowerRollResultTier::Tier1) => PowerRollResultTier::Tier2,
         (2, PowerRollResultTier::Tier2) => PowerRollResultTier::Tier3,
         (-2, PowerRollResultTier::Tier2) => PowerRollResultTier::Tier1,
         (-2, PowerRollResultTier::Tier3) => PowerRollResultTier::Tier2,
         _ => tier,
-    };
-
-    final_tier
+    }
 }
 
 fn to_tier(roll:i32) -> PowerRollResultTier {
@@ -331,4 +433,33 @@ mod tests {
             assert_eq!(to_power_roll(roll, 0, e, b), PowerRollResultTier::Tier3Critical);
         }
     }
+    #[test]
+    fn test_seeded_dice_repeat_their_rolls() {
+        let (mut first, mut second) = (Dice::seeded(7), Dice::seeded(7));
+        let rolls = first.d10s(20);
+        assert_eq!(rolls, second.d10s(20));
+        assert!(rolls.iter().all(|roll| (1..=10).contains(roll)));
+        assert_eq!(first.power_roll(2, EdgeState::None, BaneState::None), second.power_roll(2, EdgeState::None, BaneState::None));
+        assert!(first.d3s(20).iter().all(|roll| (1..=3).contains(roll)));
+    }
+
+    #[test]
+    fn test_power_roll_odds_count_every_roll() {
+        let odds = power_roll_odds(0, EdgeState::None, BaneState::None);
+        // 2d10 totals 2-11 in 55 ways, 12-16 in 35, 17-18 in 7 and 19-20 in 3
+        assert_eq!(odds, TierOdds { tier1: 0.55, tier2: 0.35, tier3: 0.07, critical: 0.03 });
+
+        let odds = power_roll_odds(2, EdgeState::Double, BaneState::None);
+        assert_eq!(odds.tier1, 0.0);
+        assert!((odds.tier1 + odds.tier2 + odds.tier3 + odds.critical - 1.0).abs() < 1e-9);
+        assert_eq!(odds.of(PowerRollResultTier::Tier3Critical), 0.03);
+    }
+
+    #[test]
+    fn test_with_additional_caps_at_double() {
+        assert_eq!(EdgeState::None.with_additional(0), EdgeState::None);
+        assert_eq!(EdgeState::None.with_additional(1), EdgeState::Single);
+        assert_eq!(EdgeState::Single.with_additional(1), EdgeState::Double);
+        assert_eq!(EdgeState::Double.with_additional(3), EdgeState::Double);
+    }
 }
\ No newline at end of file
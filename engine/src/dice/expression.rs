@@ -0,0 +1,479 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use schemars::{JsonSchema, r#gen::SchemaGenerator, schema::Schema};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{self, Unexpected, Visitor};
+use serde_json::json;
+use crate::dice::Dice;
+use crate::entity::{Characteristic, Characteristics};
+use crate::error::ExpressionError;
+
+/// Most dice one term can roll, and most sides a die can have
+const MAX_DICE: u32 = 100;
+const MAX_SIDES: u32 = 1000;
+/// Largest number an expression can contain
+const MAX_NUMBER: u32 = 10_000;
+
+/// What `per surge` counts: the surges spent on a strike. It is the only count an expression can use.
+pub const SURGES: &str = "surge";
+
+/// One value in a dice expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Term {
+    Number(i32),
+    Dice { count: u32, sides: u32 },
+    /// The roller's score, written as its letter
+    Characteristic(Characteristic),
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Number(value) => write!(f, "{}", value),
+            Term::Dice { count, sides } => write!(f, "{}d{}", count, sides),
+            Term::Characteristic(characteristic) => write!(f, "{}", characteristic.letter()),
+        }
+    }
+}
+
+/**
+ * A formula for a number, such as `3 + M`, `2d6 + R`, `1d10+2` or `1d3 per surge`. Terms are added or
+ * subtracted; `per surge` multiplies the whole total by the surges spent, given when rolling.
+ * Characteristics are written as their letter or name.
+ *
+ * Only damage is written this way. Stamina, speed, range and characteristics are fixed numbers in a
+ * stat block, never rolled.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiceExpression {
+    /// Each term with its sign, 1 to add or -1 to subtract
+    terms: Vec<(i32, Term)>,
+    per: Option<String>,
+}
+
+/// What an expression is rolled for: the roller's characteristics and any counts `per` refers to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RollContext {
+    pub characteristics: Characteristics,
+    counts: HashMap<String, i32>,
+}
+
+impl RollContext {
+    pub fn new(characteristics: Characteristics) -> Self {
+        Self { characteristics, counts: HashMap::new() }
+    }
+
+    /// Set a count, such as the surges spent on a strike. Counts that aren't set are 0.
+    pub fn with_count(mut self, name: &str, count: i32) -> Self {
+        self.counts.insert(name.to_lowercase(), count);
+        self
+    }
+
+    pub fn count(&self, name: &str) -> i32 {
+        self.counts.get(&name.to_lowercase()).copied().unwrap_or(0)
+    }
+}
+
+/// A term as it was rolled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RolledTerm {
+    pub sign: i32,
+    pub term: Term,
+    /// Each die rolled, empty for numbers and characteristics
+    pub rolls: Vec<i32>,
+    pub value: i32,
+}
+
+/// How a roll added up, term by term
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RollBreakdown {
+    pub terms: Vec<RolledTerm>,
+    /// The count the sum was multiplied by, and its name
+    pub per: Option<(String, i32)>,
+    pub total: i32,
+}
+
+impl RollBreakdown {
+    /// Whether the total is a plain number, with nothing rolled or looked up
+    pub fn is_fixed(&self) -> bool {
+        self.per.is_none() && self.terms.iter().all(|rolled| matches!(rolled.term, Term::Number(_)))
+    }
+}
+
+impl fmt::Display for RollBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, rolled) in self.terms.iter().enumerate() {
+            match (index, rolled.sign < 0) {
+                (0, true) => write!(f, "-")?,
+                (0, false) => {}
+                (_, true) => write!(f, " - ")?,
+                (_, false) => write!(f, " + ")?,
+            }
+            match rolled.term {
+                Term::Number(value) => write!(f, "{}", value)?,
+                Term::Dice { .. } => {
+                    let rolls: Vec<String> = rolled.rolls.iter().map(|roll| roll.to_string()).collect();
+                    write!(f, "{} ({})", rolled.term, rolls.join(", "))?
+                }
+                Term::Characteristic(_) => write!(f, "{} ({})", rolled.term, rolled.value)?,
+            }
+        }
+        if let Some((name, count)) = &self.per {
+            write!(f, " per {} ({})", name, count)?;
+        }
+        write!(f, " = {}", self.total)
+    }
+}
+
+impl DiceExpression {
+    /// An expression that is always the same number
+    pub fn constant(value: i32) -> Self {
+        Self { terms: vec![(1, Term::Number(value))], per: None }
+    }
+
+    /// The value, if the expression has no dice, characteristics or counts
+    pub fn as_constant(&self) -> Option<i32> {
+        if self.per.is_some() {
+            return None;
+        }
+        self.terms.iter().try_fold(0, |total, (sign, term)| match term {
+            Term::Number(value) => Some(total + sign * value),
+            _ => None,
+        })
+    }
+
+    pub fn terms(&self) -> &[(i32, Term)] {
+        &self.terms
+    }
+
+    /// What the total is multiplied by, as in the `surge` of `1d3 per surge`
+    pub fn per(&self) -> Option<&str> {
+        self.per.as_deref()
+    }
+
+    pub fn roll(&self, dice: &mut Dice, context: &RollContext) -> RollBreakdown {
+        let terms: Vec<RolledTerm> = self
+            .terms
+            .iter()
+            .map(|(sign, term)| {
+                let rolls = match term {
+                    Term::Dice { count, sides } => dice.roll(*count, *sides),
+                    _ => vec![],
+                };
+                let value = match term {
+                    Term::Number(value) => *value,
+                    Term::Dice { .. } => rolls.iter().sum(),
+                    Term::Characteristic(characteristic) => context.characteristics.get(*characteristic),
+                };
+                RolledTerm { sign: *sign, term: *term, rolls, value }
+            })
+            .collect();
+        let sum: i32 = terms.iter().map(|rolled| rolled.sign * rolled.value).sum();
+        let per = self.per.as_ref().map(|name| (name.clone(), context.count(name)));
+        let total = per.as_ref().map_or(sum, |(_, count)| sum * count);
+        RollBreakdown { terms, per, total }
+    }
+
+    /// The average total over every way the dice can land
+    pub fn average(&self, context: &RollContext) -> f64 {
+        let sum: f64 = self
+            .terms
+            .iter()
+            .map(|(sign, term)| {
+                let value = match term {
+                    Term::Number(value) => *value as f64,
+                    Term::Dice { count, sides } => *count as f64 * (*sides as f64 + 1.0) / 2.0,
+                    Term::Characteristic(characteristic) => context.characteristics.get(*characteristic) as f64,
+                };
+                *sign as f64 * value
+            })
+            .sum();
+        match &self.per {
+            Some(name) => sum * context.count(name) as f64,
+            None => sum,
+        }
+    }
+}
+
+impl From<i32> for DiceExpression {
+    fn from(value: i32) -> Self {
+        Self::constant(value)
+    }
+}
+
+impl fmt::Display for DiceExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (sign, term)) in self.terms.iter().enumerate() {
+            match (index, *sign < 0) {
+                (0, true) => write!(f, "-")?,
+                (0, false) => {}
+                (_, true) => write!(f, " - ")?,
+                (_, false) => write!(f, " + ")?,
+            }
+            write!(f, "{}", term)?;
+        }
+        if let Some(name) = &self.per {
+            write!(f, " per {}", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// A piece of an expression and the column it starts at
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Plus(usize),
+    Minus(usize),
+    /// A run of letters, digits and underscores, such as `2d6`, `M` or `per`
+    Word(usize, String),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ExpressionError> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().enumerate().peekable();
+    while let Some((index, c)) = chars.next() {
+        let column = index + 1;
+        match c {
+            '+' => tokens.push(Token::Plus(column)),
+            '-' => tokens.push(Token::Minus(column)),
+            c if c.is_whitespace() => {}
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some((_, next)) = chars.next_if(|(_, next)| next.is_alphanumeric() || *next == '_') {
+                    word.push(next);
+                }
+                tokens.push(Token::Word(column, word));
+            }
+            other => return Err(ExpressionError::Unexpected { column, found: other.to_string() }),
+        }
+    }
+    Ok(tokens)
+}
+
+fn number(text: &str) -> Result<u32, ExpressionError> {
+    match text.parse::<u32>() {
+        Ok(value) if value <= MAX_NUMBER => Ok(value),
+        _ => Err(ExpressionError::TooLarge { text: text.to_string() }),
+    }
+}
+
+/// A number, dice such as `2d6` or `d6`, or a characteristic
+fn term(column: usize, word: &str) -> Result<Term, ExpressionError> {
+    if word.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(Term::Number(number(word)? as i32));
+    }
+    if let Some((count, sides)) = word.split_once(['d', 'D']) {
+        let is_digits = |text: &str| text.chars().all(|c| c.is_ascii_digit());
+        if is_digits(count) && is_digits(sides) && !sides.is_empty() {
+            let count = if count.is_empty() { 1 } else { number(count)? };
+            let sides = number(sides)?;
+            if count == 0 || sides == 0 {
+                return Err(ExpressionError::InvalidDice { text: word.to_string() });
+            }
+            if count > MAX_DICE || sides > MAX_SIDES {
+                return Err(ExpressionError::TooLarge { text: word.to_string() });
+            }
+            return Ok(Term::Dice { count, sides });
+        }
+    }
+    match Characteristic::parse(word) {
+        Some(characteristic) => Ok(Term::Characteristic(characteristic)),
+        None => Err(ExpressionError::Unexpected { column, found: word.to_string() }),
+    }
+}
+
+impl FromStr for DiceExpression {
+    type Err = ExpressionError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(text)?;
+        if tokens.is_empty() {
+            return Err(ExpressionError::Empty);
+        }
+        let end = text.chars().count() + 1;
+        let mut tokens = tokens.into_iter().peekable();
+        let mut terms = vec![];
+        let mut per = None;
+
+        // A sign is optional before the first term, and required before every other
+        let mut sign = match tokens.next_if(|token| matches!(token, Token::Plus(_) | Token::Minus(_))) {
+            Some(Token::Minus(_)) => -1,
+            _ => 1,
+        };
+        loop {
+            match tokens.next() {
+                Some(Token::Word(column, word)) => terms.push((sign, term(column, &word)?)),
+                Some(Token::Plus(column)) | Some(Token::Minus(column)) => return Err(ExpressionError::ExpectedTerm { column }),
+                None => return Err(ExpressionError::ExpectedTerm { column: end }),
+            }
+            sign = match tokens.next() {
+                Some(Token::Plus(_)) => 1,
+                Some(Token::Minus(_)) => -1,
+                Some(Token::Word(_, word)) if word.eq_ignore_ascii_case("per") => {
+                    match tokens.next() {
+                        Some(Token::Word(_, name)) if [SURGES, "surges"].iter().any(|count| name.eq_ignore_ascii_case(count)) => {
+                            per = Some(SURGES.to_string())
+                        }
+                        Some(Token::Word(column, name)) => return Err(ExpressionError::UnknownCount { column, name }),
+                        _ => return Err(ExpressionError::MissingCount),
+                    }
+                    break;
+                }
+                Some(Token::Word(column, word)) => return Err(ExpressionError::Unexpected { column, found: word }),
+                None => break,
+            };
+        }
+        match tokens.next() {
+            Some(Token::Plus(column)) => Err(ExpressionError::Unexpected { column, found: "+".to_string() }),
+            Some(Token::Minus(column)) => Err(ExpressionError::Unexpected { column, found: "-".to_string() }),
+            Some(Token::Word(column, word)) => Err(ExpressionError::Unexpected { column, found: word }),
+            None => Ok(Self { terms, per }),
+        }
+    }
+}
+
+impl Serialize for DiceExpression {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        match self.as_constant() {
+            Some(value) => serializer.serialize_i32(value),
+            None => serializer.serialize_str(&self.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for DiceExpression {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        // Plain numbers can be written without quotes. Formulas are parsed as they are read, so a mistake
+        // in one is reported where it is written.
+        struct ExpressionVisitor;
+
+        impl Visitor<'_> for ExpressionVisitor {
+            type Value = DiceExpression;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a whole number or a formula such as `2d6 + M`")
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<DiceExpression, E> where E: de::Error {
+                i32::try_from(value).map(DiceExpression::constant).map_err(|_| E::invalid_value(Unexpected::Signed(value), &self))
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<DiceExpression, E> where E: de::Error {
+                i32::try_from(value).map(DiceExpression::constant).map_err(|_| E::invalid_value(Unexpected::Unsigned(value), &self))
+            }
+
+            fn visit_str<E>(self, text: &str) -> Result<DiceExpression, E> where E: de::Error {
+                text.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(ExpressionVisitor)
+    }
+}
+
+impl JsonSchema for DiceExpression {
+    fn schema_name() -> String {
+        "DiceExpression".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        let schema = json!({
+            "description": "A whole number, or a formula such as `2d6 + M` or `1d3 per surge`",
+            "anyOf": [
+                { "type": "integer" },
+                { "type": "string", "pattern": "^[0-9A-Za-z_+\\- ]+$" }
+            ]
+        });
+        serde_json::from_value(schema).expect("dice expression schema is valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> DiceExpression {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_expressions_parse_into_terms() {
+        assert_eq!(parse("3 + M").terms(), &[(1, Term::Number(3)), (1, Term::Characteristic(Characteristic::Might))]);
+        assert_eq!(parse("2d6 + R").terms()[0], (1, Term::Dice { count: 2, sides: 6 }));
+        assert_eq!(parse("1d10+2").to_string(), "1d10 + 2");
+        assert_eq!(parse("-d4 - agility").to_string(), "-1d4 - A");
+
+        let per_surge = parse("1d3 per surge");
+        assert_eq!(per_surge.per(), Some(SURGES));
+        assert_eq!(per_surge.to_string(), "1d3 per surge");
+        assert_eq!(parse("1d3 per Surges"), per_surge);
+        assert_eq!(parse(" 7 ").as_constant(), Some(7));
+        assert_eq!(parse("7 - 2").as_constant(), Some(5));
+        assert_eq!(parse("2 + M").as_constant(), None);
+    }
+
+    #[test]
+    fn test_mistakes_are_located() {
+        assert_eq!("".parse::<DiceExpression>(), Err(ExpressionError::Empty));
+        assert_eq!("3 +".parse::<DiceExpression>(), Err(ExpressionError::ExpectedTerm { column: 4 }));
+        assert_eq!("3 + + 2".parse::<DiceExpression>(), Err(ExpressionError::ExpectedTerm { column: 5 }));
+        assert_eq!("2d6 * 2".parse::<DiceExpression>(), Err(ExpressionError::Unexpected { column: 5, found: "*".to_string() }));
+        assert_eq!("3 X".parse::<DiceExpression>(), Err(ExpressionError::Unexpected { column: 3, found: "X".to_string() }));
+        assert_eq!("2 + Q".parse::<DiceExpression>(), Err(ExpressionError::Unexpected { column: 5, found: "Q".to_string() }));
+        assert_eq!("0d6".parse::<DiceExpression>(), Err(ExpressionError::InvalidDice { text: "0d6".to_string() }));
+        assert_eq!("1000d6".parse::<DiceExpression>(), Err(ExpressionError::TooLarge { text: "1000d6".to_string() }));
+        assert_eq!("1d3 per".parse::<DiceExpression>(), Err(ExpressionError::MissingCount));
+        assert_eq!(
+            "1d3 per ally".parse::<DiceExpression>(),
+            Err(ExpressionError::UnknownCount { column: 9, name: "ally".to_string() })
+        );
+        assert_eq!(
+            "1d3 per surge + 1".parse::<DiceExpression>(),
+            Err(ExpressionError::Unexpected { column: 15, found: "+".to_string() })
+        );
+    }
+
+    #[test]
+    fn test_rolls_add_up_with_a_breakdown() {
+        let context = RollContext::new(Characteristics { reason: 2, ..Characteristics::default() });
+        let breakdown = parse("2d6 + R - 1").roll(&mut Dice::seeded(5), &context);
+        let dice = &breakdown.terms[0].rolls;
+        assert_eq!(dice.len(), 2);
+        assert_eq!(breakdown.total, dice.iter().sum::<i32>() + 1);
+        assert_eq!(breakdown.to_string(), format!("2d6 ({}, {}) + R (2) - 1 = {}", dice[0], dice[1], breakdown.total));
+
+        // The same seed rolls the same dice
+        assert_eq!(breakdown, parse("2d6 + R - 1").roll(&mut Dice::seeded(5), &context));
+    }
+
+    #[test]
+    fn test_per_multiplies_by_the_count() {
+        let expression = parse("1d3 per surge");
+        let none = expression.roll(&mut Dice::seeded(1), &RollContext::default());
+        assert_eq!(none.total, 0);
+
+        let context = RollContext::default().with_count("Surge", 3);
+        let three = expression.roll(&mut Dice::seeded(1), &context);
+        assert_eq!(three.total, three.terms[0].value * 3);
+        assert!(three.to_string().contains("per surge (3)"));
+        assert_eq!(expression.average(&context), 6.0);
+    }
+
+    #[test]
+    fn test_average_counts_characteristics() {
+        let context = RollContext::new(Characteristics { might: 3, ..Characteristics::default() });
+        assert_eq!(parse("2d6 + M").average(&context), 10.0);
+        assert_eq!(parse("1d10 + 2").average(&context), 7.5);
+    }
+
+    #[test]
+    fn test_numbers_and_formulas_deserialize() {
+        let number: DiceExpression = serde_json::from_value(json!(4)).unwrap();
+        assert_eq!(number, DiceExpression::constant(4));
+        let formula: DiceExpression = serde_json::from_value(json!("2 + m")).unwrap();
+        assert_eq!(serde_json::to_value(&formula).unwrap(), json!("2 + M"));
+        assert_eq!(serde_json::to_value(&number).unwrap(), json!(4));
+        assert!(serde_json::from_value::<DiceExpression>(json!("2 +")).is_err());
+    }
+}
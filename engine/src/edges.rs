@@ -0,0 +1,197 @@
+use crate::error::ResolutionError;
+use crate::dice::EdgeState;
+use crate::entity::EntityId;
+use crate::grid::{Battlefield, Placement};
+
+/// Why a power roll gained an edge from the battlefield
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EdgeSource {
+    /// The attacker and this ally are on opposite sides of the target
+    Flanking { ally: EntityId },
+    /// The attacker stands above the target
+    HighGround,
+}
+
+/// The edge state to pass into `power_roll`, along with the battlefield sources that contributed to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComputedEdges {
+    pub edge: EdgeState,
+    pub sources: Vec<EdgeSource>,
+}
+
+/// Which side of the target's space another space is on, as a direction of -1, 0 or 1 on each axis
+fn side_of(target: &Placement, other: &Placement) -> (i32, i32) {
+    let (target_far, other_far) = (target.far_corner(), other.far_corner());
+    let axis = |other_min: i32, other_max: i32, target_min: i32, target_max: i32| {
+        if other_max < target_min {
+            -1
+        } else if other_min > target_max {
+            1
+        } else {
+            0
+        }
+    };
+    (
+        axis(other.position.x, other_far.x, target.position.x, target_far.x),
+        axis(other.position.y, other_far.y, target.position.y, target_far.y),
+    )
+}
+
+/// Find an ally of the attacker who flanks the target with them.
+/// Both must be adjacent to the target and on opposite sides or corners of its space.
+pub fn flanking_ally(battlefield: &Battlefield, attacker: EntityId, target: EntityId) -> Option<EntityId> {
+    let attacker_placement = battlefield.placement(attacker)?;
+    let target_placement = battlefield.placement(target)?;
+    if attacker_placement.side == target_placement.side {
+        return None;
+    }
+    if !attacker_placement.is_adjacent_to(target_placement) {
+        return None;
+    }
+
+    let (ax, ay) = side_of(target_placement, attacker_placement);
+    battlefield
+        .allies_of(attacker)
+        .into_iter()
+        .filter(|ally| *ally != target)
+        .find(|ally| {
+            let placement = match battlefield.placement(*ally) {
+                Some(placement) => placement,
+                None => return false,
+            };
+            placement.is_adjacent_to(target_placement) && side_of(target_placement, placement) == (-ax, -ay)
+        })
+}
+
+/// The attacker has high ground when the square they stand on is at least as high as the top of the target.
+pub fn has_high_ground(battlefield: &Battlefield, attacker: EntityId, target: EntityId) -> bool {
+    let target_height = match battlefield.placement(target) {
+        Some(placement) => placement.size.footprint(),
+        None => return false,
+    };
+    match (battlefield.elevation_of(attacker), battlefield.elevation_of(target)) {
+        (Some(attacker_elevation), Some(target_elevation)) => attacker_elevation >= target_elevation + target_height,
+        _ => false,
+    }
+}
+
+/// Add the edges granted by positioning to a base edge state.
+/// Flanking only applies to melee strikes; high ground applies to any power roll against the target.
+pub fn compute_edges(battlefield: &Battlefield, attacker: EntityId, target: EntityId, is_melee: bool, base: EdgeState) -> Result<ComputedEdges, ResolutionError> {
+    if battlefield.placement(attacker).is_none() {
+        return Err(ResolutionError::NotOnBattlefield { entity: attacker });
+    }
+    if battlefield.placement(target).is_none() {
+        return Err(ResolutionError::NotOnBattlefield { entity: target });
+    }
+
+    let mut sources = Vec::new();
+    if is_melee
+        && let Some(ally) = flanking_ally(battlefield, attacker, target) {
+        sources.push(EdgeSource::Flanking { ally });
+    }
+    if has_high_ground(battlefield, attacker, target) {
+        sources.push(EdgeSource::HighGround);
+    }
+
+    Ok(ComputedEdges {
+        edge: base.with_additional(sources.len()),
+        sources,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::TurnSide;
+    use crate::entity::Size;
+    use crate::grid::{Grid, Position};
+
+    const PC1: EntityId = EntityId::new(1);
+    const PC2: EntityId = EntityId::new(2);
+    const GOBLIN: EntityId = EntityId::new(4);
+    const GOBLIN_2: EntityId = EntityId::new(5);
+    const OGRE: EntityId = EntityId::new(6);
+
+    fn battlefield() -> Battlefield {
+        battlefield_on(Grid::new(6, 6))
+    }
+
+    fn battlefield_on(grid: Grid) -> Battlefield {
+        let mut battlefield = Battlefield::new(grid);
+        battlefield.place(GOBLIN, TurnSide::NPC, Size::Medium, Position::new(2, 2)).unwrap();
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(1, 2)).unwrap();
+        battlefield
+    }
+
+    #[test]
+    fn test_flanking_requires_opposite_ally() {
+        let mut battlefield = battlefield();
+        battlefield.place(PC2, TurnSide::PC, Size::Medium, Position::new(2, 3)).unwrap();
+        assert_eq!(flanking_ally(&battlefield, PC1, GOBLIN), None);
+
+        battlefield.place(PC2, TurnSide::PC, Size::Medium, Position::new(3, 2)).unwrap();
+        assert_eq!(flanking_ally(&battlefield, PC1, GOBLIN), Some(PC2));
+    }
+
+    #[test]
+    fn test_flanking_on_corners() {
+        let mut battlefield = battlefield();
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(1, 1)).unwrap();
+        battlefield.place(PC2, TurnSide::PC, Size::Medium, Position::new(3, 3)).unwrap();
+        assert_eq!(flanking_ally(&battlefield, PC1, GOBLIN), Some(PC2));
+    }
+
+    #[test]
+    fn test_enemy_on_opposite_side_does_not_flank() {
+        let mut battlefield = battlefield();
+        battlefield.place(GOBLIN_2, TurnSide::NPC, Size::Medium, Position::new(3, 2)).unwrap();
+        assert_eq!(flanking_ally(&battlefield, PC1, GOBLIN), None);
+    }
+
+    #[test]
+    fn test_compute_edges_names_sources() {
+        let mut battlefield = battlefield();
+        battlefield.place(PC2, TurnSide::PC, Size::Medium, Position::new(3, 2)).unwrap();
+
+        let edges = compute_edges(&battlefield, PC1, GOBLIN, true, EdgeState::None).unwrap();
+        assert_eq!(edges.edge, EdgeState::Single);
+        assert_eq!(edges.sources, vec![EdgeSource::Flanking { ally: PC2 }]);
+
+        // Flanking doesn't apply to ranged strikes
+        let edges = compute_edges(&battlefield, PC1, GOBLIN, false, EdgeState::None).unwrap();
+        assert_eq!(edges.edge, EdgeState::None);
+        assert!(edges.sources.is_empty());
+    }
+
+    #[test]
+    fn test_high_ground_stacks_with_flanking() {
+        let mut grid = Grid::new(6, 6);
+        grid.set_elevation(Position::new(1, 2), 1).unwrap();
+        let mut battlefield = battlefield_on(grid);
+        battlefield.place(PC2, TurnSide::PC, Size::Medium, Position::new(3, 2)).unwrap();
+
+        let edges = compute_edges(&battlefield, PC1, GOBLIN, true, EdgeState::None).unwrap();
+        assert_eq!(edges.edge, EdgeState::Double);
+        assert_eq!(edges.sources.len(), 2);
+        assert!(edges.sources.contains(&EdgeSource::HighGround));
+        assert!(!has_high_ground(&battlefield, GOBLIN, PC1));
+    }
+
+    #[test]
+    fn test_flanking_large_target() {
+        let mut battlefield = Battlefield::new(Grid::new(8, 8));
+        battlefield.place(OGRE, TurnSide::NPC, Size::Squares(2), Position::new(3, 3)).unwrap();
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(2, 3)).unwrap();
+        battlefield.place(PC2, TurnSide::PC, Size::Medium, Position::new(5, 4)).unwrap();
+        assert_eq!(flanking_ally(&battlefield, PC1, OGRE), Some(PC2));
+
+        // One square of elevation isn't above a size 2 creature
+        let mut grid = Grid::new(8, 8);
+        grid.set_elevation(Position::new(2, 3), 1).unwrap();
+        let mut battlefield = Battlefield::new(grid);
+        battlefield.place(OGRE, TurnSide::NPC, Size::Squares(2), Position::new(3, 3)).unwrap();
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(2, 3)).unwrap();
+        assert!(!has_high_ground(&battlefield, PC1, OGRE));
+    }
+}
@@ -0,0 +1,417 @@
+use indexmap::{IndexMap, IndexSet};
+use crate::combat::{CombatParameters, CombatState, TurnSide};
+use crate::dice::RollContext;
+use crate::entity::{Entity, EntityDefinition, EntityId};
+use crate::error::{CombatError, ResolutionError};
+use crate::events::CombatEvent;
+use crate::grid::{Battlefield, Position};
+use crate::session::CombatSession;
+
+/// Whether an encounter is still being set up or its combat has begun
+#[derive(Debug, Clone)]
+pub enum EncounterPhase {
+    Setup(CombatParameters),
+    Active(CombatState),
+}
+
+/**
+ * The creatures of an encounter together with its turn order and the battlefield they stand on. The
+ * roster, the turn state, every creature's stamina and their places on the battlefield only change through
+ * here, so they can't drift apart. Creatures are identified by an `EntityId` handed out when they join, so
+ * their names are free to repeat or change.
+ */
+#[derive(Clone)]
+pub struct Encounter {
+    phase: EncounterPhase,
+    entities: IndexMap<EntityId, Entity>,
+    next_id: EntityId,
+    battlefield: Option<Battlefield>,
+}
+
+impl Encounter {
+    /// An empty encounter, being set up
+    pub fn new() -> Self {
+        Self {
+            phase: EncounterPhase::Setup(CombatParameters::new(vec![], vec![], TurnSide::PC)),
+            entities: IndexMap::new(),
+            next_id: EntityId::new(1),
+            battlefield: None,
+        }
+    }
+
+    /// Pick a saved or remembered combat back up
+    pub fn from_session(session: CombatSession) -> Self {
+        Self {
+            phase: EncounterPhase::Active(session.state),
+            entities: session.entities,
+            next_id: session.next_id,
+            battlefield: session.battlefield,
+        }
+    }
+
+    /// A copy of the running combat for saving or undo. `None` during setup.
+    pub fn to_session(&self) -> Option<CombatSession> {
+        self.state().map(|state| CombatSession {
+            state: state.clone(),
+            entities: self.entities.clone(),
+            next_id: self.next_id,
+            battlefield: self.battlefield.clone(),
+        })
+    }
+
+    pub fn phase(&self) -> &EncounterPhase {
+        &self.phase
+    }
+
+    /// The turn state, once the combat has started
+    pub fn state(&self) -> Option<&CombatState> {
+        match &self.phase {
+            EncounterPhase::Setup(_) => None,
+            EncounterPhase::Active(state) => Some(state),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.state().is_some()
+    }
+
+    /// Where everyone stands, once a battlefield has been laid out
+    pub fn battlefield(&self) -> Option<&Battlefield> {
+        self.battlefield.as_ref()
+    }
+
+    /// The battlefield, to place creatures on it or move them around
+    pub fn battlefield_mut(&mut self) -> Option<&mut Battlefield> {
+        self.battlefield.as_mut()
+    }
+
+    /// Lay out the battlefield the encounter is fought on, replacing any before it
+    pub fn set_battlefield(&mut self, battlefield: Battlefield) {
+        self.battlefield = Some(battlefield);
+    }
+
+    /// Put a creature on the battlefield at a position, with its size and movement modes
+    pub fn place(&mut self, id: EntityId, position: Position) -> Result<(), ResolutionError> {
+        let side = self.side_of(id).ok_or(CombatError::UnknownEntity { entity: id })?;
+        let (entity, battlefield) = match (self.entities.get(&id), &mut self.battlefield) {
+            (Some(entity), Some(battlefield)) => (entity, battlefield),
+            (None, _) => return Err(CombatError::UnknownEntity { entity: id }.into()),
+            (_, None) => return Err(ResolutionError::NotOnBattlefield { entity: id }),
+        };
+        battlefield.place_entity(id, side, entity.definition(), position)
+    }
+
+    pub fn entities(&self) -> &IndexMap<EntityId, Entity> {
+        &self.entities
+    }
+
+    pub fn entity(&self, id: EntityId) -> Option<&Entity> {
+        self.entities.get(&id)
+    }
+
+    /// The display name of a creature, or its ID once it has left the encounter
+    pub fn name_of(&self, id: EntityId) -> String {
+        match self.entities.get(&id) {
+            Some(entity) => entity.name().clone(),
+            None => id.to_string(),
+        }
+    }
+
+    /// What a creature's rolls use: its characteristics. Creatures that have left roll with none.
+    pub fn roll_context(&self, id: EntityId) -> RollContext {
+        match self.entities.get(&id) {
+            Some(entity) => RollContext::new(entity.definition().characteristics),
+            None => RollContext::default(),
+        }
+    }
+
+    /// Describe a combat event with the current names of the creatures in it
+    pub fn describe(&self, event: &CombatEvent) -> String {
+        event.describe(|id| self.name_of(id))
+    }
+
+    /// Everyone on one side, in the order they joined
+    pub fn combatants(&self, side: TurnSide) -> &IndexSet<EntityId> {
+        match &self.phase {
+            EncounterPhase::Setup(parameters) => parameters.players(side),
+            EncounterPhase::Active(state) => match side {
+                TurnSide::PC => state.all_pcs(),
+                TurnSide::NPC => state.all_npcs(),
+            },
+        }
+    }
+
+    pub fn side_of(&self, id: EntityId) -> Option<TurnSide> {
+        [TurnSide::PC, TurnSide::NPC]
+            .into_iter()
+            .find(|side| self.combatants(*side).contains(&id))
+    }
+
+    /// Add a creature to one side, as part of the setup or as a reinforcement. Returns its new ID.
+    pub fn add(&mut self, side: TurnSide, entity: Entity) -> Result<EntityId, CombatError> {
+        let id = self.next_id;
+        match &mut self.phase {
+            EncounterPhase::Setup(parameters) => match side {
+                TurnSide::PC => parameters.add_pc(id),
+                TurnSide::NPC => parameters.add_npc(id),
+            },
+            EncounterPhase::Active(state) => {
+                *state = match side {
+                    TurnSide::PC => state.add_pc(id)?,
+                    TurnSide::NPC => state.add_npc(id)?,
+                };
+            }
+        }
+        self.record(CombatEvent::CreatureJoined { entity: id, creature: Box::new(entity.clone()) });
+        self.entities.insert(id, entity);
+        self.next_id = id.next();
+        Ok(id)
+    }
+
+    /// Take a creature out of the encounter and off the battlefield, returning the side it was on
+    pub fn remove(&mut self, id: EntityId) -> Result<TurnSide, CombatError> {
+        let side = match self.side_of(id) {
+            Some(side) => side,
+            None => return Err(CombatError::UnknownEntity { entity: id }),
+        };
+        match &mut self.phase {
+            EncounterPhase::Setup(parameters) => {
+                match side {
+                    TurnSide::PC => parameters.remove_pc(id),
+                    TurnSide::NPC => parameters.remove_npc(id),
+                };
+            }
+            EncounterPhase::Active(state) => {
+                *state = match side {
+                    TurnSide::PC => state.remove_pc(id)?,
+                    TurnSide::NPC => state.remove_npc(id)?,
+                };
+            }
+        }
+        self.entities.shift_remove(&id);
+        if let Some(battlefield) = &mut self.battlefield {
+            battlefield.remove(id);
+        }
+        Ok(side)
+    }
+
+    /// Begin the combat with everyone added during setup
+    pub fn start(&mut self, starting_side: TurnSide) -> Result<(), CombatError> {
+        let parameters = match &self.phase {
+            EncounterPhase::Setup(parameters) => parameters,
+            EncounterPhase::Active(_) => return Err(CombatError::AlreadyStarted),
+        };
+        for side in [TurnSide::PC, TurnSide::NPC] {
+            if parameters.players(side).is_empty() {
+                return Err(CombatError::NoCombatants { side });
+            }
+        }
+        let parameters = CombatParameters::new(parameters.pcs().clone(), parameters.npcs().clone(), starting_side);
+        let mut state = CombatState::new(parameters);
+        for (id, entity) in &self.entities {
+            state = state.record(CombatEvent::CreatureJoined { entity: *id, creature: Box::new(entity.clone()) });
+        }
+        self.phase = EncounterPhase::Active(state);
+        Ok(())
+    }
+
+    /// Apply a turn transition such as `CombatState::end_turn`. Nothing changes if it fails.
+    pub fn update(&mut self, transition: impl FnOnce(&CombatState) -> Result<CombatState, CombatError>) -> Result<(), CombatError> {
+        match &mut self.phase {
+            EncounterPhase::Setup(_) => Err(CombatError::NotStarted),
+            EncounterPhase::Active(state) => {
+                *state = transition(state)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Add an event to the combat's log, such as a roll. Ignored during setup.
+    pub fn record(&mut self, event: CombatEvent) {
+        if let EncounterPhase::Active(state) = &mut self.phase {
+            *state = state.record(event);
+        }
+    }
+
+    fn entity_mut(&mut self, id: EntityId) -> Result<&mut Entity, CombatError> {
+        match self.entities.get_mut(&id) {
+            Some(entity) => Ok(entity),
+            None => Err(CombatError::UnknownEntity { entity: id }),
+        }
+    }
+
+    /// Deal damage to a creature and log it. Returns the creature's remaining stamina.
+    pub fn damage(&mut self, id: EntityId, amount: i32) -> Result<i32, CombatError> {
+        let entity = self.entity_mut(id)?;
+        entity.damage(amount);
+        let remaining = entity.current_health();
+        self.record(CombatEvent::DamageApplied { target: id, amount });
+        Ok(remaining)
+    }
+
+    /// Restore a creature's stamina, up to its maximum. Returns the creature's stamina.
+    pub fn heal(&mut self, id: EntityId, amount: i32) -> Result<i32, CombatError> {
+        let entity = self.entity_mut(id)?;
+        entity.heal(amount);
+        let stamina = entity.current_health();
+        self.record(CombatEvent::Healed { target: id, amount });
+        Ok(stamina)
+    }
+
+    /// Give a creature a new display name. Its turn and place on the battlefield are kept.
+    pub fn rename(&mut self, id: EntityId, name: String) -> Result<(), CombatError> {
+        self.entity_mut(id)?.set_name(name.clone());
+        self.record(CombatEvent::EntityRenamed { entity: id, name });
+        Ok(())
+    }
+
+    /// Swap in an updated definition for a creature, keeping its current stamina. On the battlefield it
+    /// takes the new size and movement modes where it stands, and the update is refused if it no longer fits.
+    pub fn redefine(&mut self, id: EntityId, definition: EntityDefinition) -> Result<(), ResolutionError> {
+        self.entity_mut(id)?;
+        if let Some(battlefield) = &mut self.battlefield
+            && battlefield.placement(id).is_some()
+        {
+            battlefield.redefine(id, &definition)?;
+        }
+        self.entity_mut(id)?.set_definition(definition.clone());
+        self.record(CombatEvent::Redefined { entity: id, definition: Box::new(definition) });
+        Ok(())
+    }
+}
+
+impl Default for Encounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::{MovementMode, Size};
+    use crate::grid::{Grid, Position};
+    use crate::testing::entity;
+
+    fn started() -> (Encounter, EntityId, EntityId) {
+        let mut encounter = Encounter::new();
+        let pc = encounter.add(TurnSide::PC, entity("PC1", 10)).unwrap();
+        let goblin = encounter.add(TurnSide::NPC, entity("Goblin 1", 10)).unwrap();
+        encounter.start(TurnSide::PC).unwrap();
+        (encounter, pc, goblin)
+    }
+
+    #[test]
+    fn test_roster_and_entities_stay_together() {
+        let mut encounter = Encounter::new();
+        encounter.add(TurnSide::PC, entity("PC1", 10)).unwrap();
+        let goblin = encounter.add(TurnSide::NPC, entity("Goblin 1", 10)).unwrap();
+
+        assert_eq!(encounter.remove(goblin), Ok(TurnSide::NPC));
+        assert!(encounter.entity(goblin).is_none());
+        assert!(encounter.combatants(TurnSide::NPC).is_empty());
+        assert_eq!(encounter.remove(goblin), Err(CombatError::UnknownEntity { entity: goblin }));
+        assert_eq!(encounter.start(TurnSide::PC), Err(CombatError::NoCombatants { side: TurnSide::NPC }));
+    }
+
+    #[test]
+    fn test_removed_creatures_leave_the_battlefield() {
+        let (mut encounter, pc, goblin) = started();
+        assert_eq!(encounter.place(pc, Position::new(0, 0)), Err(ResolutionError::NotOnBattlefield { entity: pc }));
+        encounter.set_battlefield(Battlefield::new(Grid::new(4, 4)));
+        encounter.place(pc, Position::new(0, 0)).unwrap();
+        encounter.place(goblin, Position::new(3, 3)).unwrap();
+
+        encounter.remove(goblin).unwrap();
+        let battlefield = encounter.battlefield().unwrap();
+        assert!(battlefield.placement(goblin).is_none());
+        assert!(battlefield.occupant(Position::new(3, 3)).is_none());
+        assert_eq!(battlefield.position(pc), Some(Position::new(0, 0)));
+    }
+
+    #[test]
+    fn test_redefined_creatures_take_their_new_size_where_they_stand() {
+        let (mut encounter, pc, goblin) = started();
+        encounter.set_battlefield(Battlefield::new(Grid::new(4, 4)));
+        encounter.place(pc, Position::new(0, 0)).unwrap();
+        encounter.place(goblin, Position::new(2, 2)).unwrap();
+
+        let mut ogre = encounter.entity(goblin).unwrap().definition().clone();
+        ogre.size = Size::Squares(2);
+        ogre.movement = vec![MovementMode::Fly];
+        encounter.redefine(goblin, ogre.clone()).unwrap();
+        let placement = encounter.battlefield().unwrap().placement(goblin).unwrap();
+        assert_eq!(placement.size, Size::Squares(2));
+        assert!(placement.modes.fly);
+
+        // Too big to fit before the edge of the map: refused, and nothing changes
+        ogre.size = Size::Squares(3);
+        assert!(matches!(encounter.redefine(goblin, ogre), Err(ResolutionError::DoesNotFit { .. })));
+        assert_eq!(encounter.battlefield().unwrap().placement(goblin).unwrap().size, Size::Squares(2));
+        assert_eq!(encounter.entity(goblin).unwrap().definition().size, Size::Squares(2));
+    }
+
+    #[test]
+    fn test_reinforcements_and_removals_during_combat() {
+        let (mut encounter, pc, goblin) = started();
+        let reinforcement = encounter.add(TurnSide::NPC, entity("Goblin 2", 10)).unwrap();
+        assert!(encounter.state().unwrap().all_npcs().contains(&reinforcement));
+
+        encounter.update(|state| state.start_turn(TurnSide::PC, pc)).unwrap();
+        assert!(matches!(encounter.remove(pc), Err(CombatError::TakingTurn { .. })));
+        assert!(encounter.entity(pc).is_some());
+
+        assert_eq!(encounter.remove(goblin), Ok(TurnSide::NPC));
+        assert!(!encounter.state().unwrap().all_npcs().contains(&goblin));
+        assert!(encounter.entity(goblin).is_none());
+    }
+
+    #[test]
+    fn test_same_names_and_renames_keep_turns_apart() {
+        let (mut encounter, pc, goblin) = started();
+        let twin = encounter.add(TurnSide::NPC, entity("Goblin 1", 10)).unwrap();
+        assert_ne!(twin, goblin);
+
+        encounter.update(|state| state.start_turn(TurnSide::PC, pc)).unwrap();
+        encounter.update(|state| state.end_turn()).unwrap();
+        encounter.update(|state| state.start_turn(TurnSide::NPC, goblin)).unwrap();
+        encounter.rename(goblin, "Goblin Boss".to_string()).unwrap();
+        encounter.update(|state| state.end_turn()).unwrap();
+
+        let state = encounter.state().unwrap();
+        assert!(state.npc_taken_turns().contains(&goblin));
+        assert!(!state.npc_taken_turns().contains(&twin));
+        assert_eq!(encounter.name_of(goblin), "Goblin Boss");
+        assert_eq!(encounter.name_of(twin), "Goblin 1");
+        let ended = state.events().last().unwrap();
+        assert_eq!(encounter.describe(ended), "Goblin Boss ended their turn");
+    }
+
+    #[test]
+    fn test_damage_is_applied_and_logged() {
+        let (mut encounter, _, goblin) = started();
+        assert_eq!(encounter.damage(goblin, 4), Ok(6));
+        assert_eq!(encounter.heal(goblin, 10), Ok(10));
+        let nobody = EntityId::new(99);
+        assert_eq!(encounter.damage(nobody, 1), Err(CombatError::UnknownEntity { entity: nobody }));
+        let events = encounter.state().unwrap().events();
+        assert!(events.contains(&CombatEvent::DamageApplied { target: goblin, amount: 4 }));
+        assert!(events.contains(&CombatEvent::Healed { target: goblin, amount: 10 }));
+    }
+
+    #[test]
+    fn test_turns_need_a_started_combat() {
+        let mut encounter = Encounter::new();
+        let result = encounter.update(|state| state.end_turn());
+        assert_eq!(result, Err(CombatError::NotStarted));
+        assert!(encounter.to_session().is_none());
+
+        let (encounter, _, goblin) = started();
+        let mut resumed = Encounter::from_session(encounter.to_session().unwrap());
+        assert!(resumed.battlefield().is_none());
+        assert_eq!(resumed.entities().len(), 2);
+        assert_eq!(resumed.combatants(TurnSide::PC).len(), 1);
+        // IDs keep counting from where the saved encounter left off
+        assert!(resumed.add(TurnSide::NPC, entity("Goblin 2", 10)).unwrap() > goblin);
+    }
+}
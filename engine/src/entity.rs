@@ -1,17 +1,302 @@
-use serde::Deserialize;
+use std::fmt;
+use schemars::{JsonSchema, r#gen::SchemaGenerator, schema::Schema};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::json;
+use crate::error::ContentError;
 
-#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+/// Creature size. 1T, 1S, 1M and 1L all occupy a single square; size N occupies an N x N area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Size {
+    Tiny,
+    Small,
+    #[default]
+    Medium,
+    Large,
+    Squares(u8),
+}
+
+impl Size {
+    /// Width, length and height of the creature's space, in squares
+    pub fn footprint(&self) -> i32 {
+        match self {
+            Size::Squares(n) => *n as i32,
+            _ => 1,
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, ContentError> {
+        match value.trim().to_uppercase().as_str() {
+            "1T" => Ok(Size::Tiny),
+            "1S" => Ok(Size::Small),
+            "1M" | "1" => Ok(Size::Medium),
+            "1L" => Ok(Size::Large),
+            other => match other.parse::<u8>() {
+                Ok(n) if n >= 2 => Ok(Size::Squares(n)),
+                _ => Err(ContentError::InvalidSize { value: value.to_string() }),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Size::Tiny => write!(f, "1T"),
+            Size::Small => write!(f, "1S"),
+            Size::Medium => write!(f, "1M"),
+            Size::Large => write!(f, "1L"),
+            Size::Squares(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+impl Serialize for Size {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Size {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        // Sizes are written either as "1M" or as a bare number of squares
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum SizeRepr {
+            Text(String),
+            Squares(u8),
+        }
+
+        match SizeRepr::deserialize(deserializer)? {
+            SizeRepr::Text(text) => Size::parse(&text).map_err(serde::de::Error::custom),
+            SizeRepr::Squares(n) => Size::parse(&n.to_string()).map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+impl JsonSchema for Size {
+    fn schema_name() -> String {
+        "Size".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        let schema = json!({
+            "description": "1T, 1S, 1M or 1L for a single square, or a number of squares of 2 or more",
+            "anyOf": [
+                { "type": "string", "pattern": "^\\s*(1[TtSsMmLl]?|[1-9][0-9]*)\\s*$" },
+                { "type": "integer", "minimum": 1, "maximum": 255 }
+            ]
+        });
+        serde_json::from_value(schema).expect("size schema is valid")
+    }
+}
+
+/// Ways a creature can move besides walking
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum MovementMode {
+    Fly,
+    Climb,
+    Swim,
+    Burrow,
+    Teleport,
+}
+
+/// The set of movement modes a creature has, in a form cheap to copy onto the battlefield
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct MovementModes {
+    pub fly: bool,
+    pub climb: bool,
+    pub swim: bool,
+    pub burrow: bool,
+    pub teleport: bool,
+}
+
+impl MovementModes {
+    pub fn from_modes(modes: &[MovementMode]) -> Self {
+        let mut set = Self::default();
+        for mode in modes {
+            match mode {
+                MovementMode::Fly => set.fly = true,
+                MovementMode::Climb => set.climb = true,
+                MovementMode::Swim => set.swim = true,
+                MovementMode::Burrow => set.burrow = true,
+                MovementMode::Teleport => set.teleport = true,
+            }
+        }
+        set
+    }
+}
+
+fn default_speed() -> i32 {
+    5
+}
+
+/// How a monster fights. Automated turns use it to pick targets and positions.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Ambusher,
+    Artillery,
+    Brute,
+    Controller,
+    Defender,
+    Harrier,
+    Hexer,
+    Mount,
+    Support,
+}
+
+/// One of the five characteristics every creature has
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Characteristic {
+    Might,
+    Agility,
+    Reason,
+    Intuition,
+    Presence,
+}
+
+impl Characteristic {
+    pub const ALL: [Characteristic; 5] =
+        [Characteristic::Might, Characteristic::Agility, Characteristic::Reason, Characteristic::Intuition, Characteristic::Presence];
+
+    /// The letter standing for the characteristic in formulas, such as the M in `2 + M`
+    pub fn letter(&self) -> char {
+        match self {
+            Characteristic::Might => 'M',
+            Characteristic::Agility => 'A',
+            Characteristic::Reason => 'R',
+            Characteristic::Intuition => 'I',
+            Characteristic::Presence => 'P',
+        }
+    }
+
+    /// A characteristic by its letter or its name, ignoring case
+    pub fn parse(text: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|characteristic| {
+            text.eq_ignore_ascii_case(&characteristic.letter().to_string())
+                || text.eq_ignore_ascii_case(&format!("{:?}", characteristic))
+        })
+    }
+}
+
+/// A creature's characteristic scores. Any left out are 0.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
+pub struct Characteristics {
+    pub might: i32,
+    pub agility: i32,
+    pub reason: i32,
+    pub intuition: i32,
+    pub presence: i32,
+}
+
+impl Characteristics {
+    pub fn get(&self, characteristic: Characteristic) -> i32 {
+        match characteristic {
+            Characteristic::Might => self.might,
+            Characteristic::Agility => self.agility,
+            Characteristic::Reason => self.reason,
+            Characteristic::Intuition => self.intuition,
+            Characteristic::Presence => self.presence,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A hero or monster stat block
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+#[schemars(deny_unknown_fields)]
 pub struct EntityDefinition {
     pub name: String,
     pub max_stamina: i32,
+    #[serde(default)]
+    pub size: Size,
+    #[serde(default = "default_speed")]
+    pub speed: i32,
+    #[serde(default)]
+    pub movement: Vec<MovementMode>,
+    /// IDs of abilities in the `abilities` content set
     #[serde(rename = "abilities")]
     pub abilities: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<Role>,
+    /// Scores that formulas such as `2 + M` refer to
+    #[serde(default, skip_serializing_if = "Characteristics::is_zero")]
+    pub characteristics: Characteristics,
+    /// Skills such as `sneak` that add 2 to tests calling for them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skills: Vec<String>,
+}
+
+/// An unnamed medium creature with 1 stamina, a base speed and no abilities, for filling in the rest of a literal
+impl Default for EntityDefinition {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            max_stamina: 1,
+            size: Size::default(),
+            speed: default_speed(),
+            movement: vec![],
+            abilities: vec![],
+            role: None,
+            characteristics: Characteristics::default(),
+            skills: vec![],
+        }
+    }
+}
+
+impl EntityDefinition {
+    pub fn movement_modes(&self) -> MovementModes {
+        MovementModes::from_modes(&self.movement)
+    }
+
+    /// Whether the creature has a skill, ignoring case
+    pub fn has_skill(&self, skill: &str) -> bool {
+        self.skills.iter().any(|known| known.eq_ignore_ascii_case(skill.trim()))
+    }
+}
+
+/// Identifies a creature for the whole of an encounter. Unlike its name, it never changes,
+/// so turn tracking, targeting and the battlefield all refer to creatures by ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EntityId(u32);
+
+impl EntityId {
+    pub const fn new(id: u32) -> Self {
+        Self(id)
+    }
+
+    pub fn get(self) -> u32 {
+        self.0
+    }
+
+    /// The ID handed out after this one
+    pub fn next(self) -> Self {
+        Self(self.0 + 1)
+    }
 }
 
+impl fmt::Display for EntityId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
 
+/// A creature in play. Serialized with its definition so a saved session doesn't depend on the content files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Entity {
     instance_name: String,
     definition: EntityDefinition,
+    /// The content the definition was made from, as `<set>/<id>` such as `monsters/goblins/goblin_warrior`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    definition_id: Option<String>,
     current_stamina: i32,
 }
 
@@ -21,14 +306,22 @@ impl Entity {
         Self {
             instance_name,
             definition,
+            definition_id: None,
             current_stamina,
         }
     }
 
+    /// Remember which content definition the creature was made from, so it can be found again on reload
+    pub fn with_definition_id(mut self, definition_id: String) -> Self {
+        self.definition_id = Some(definition_id);
+        self
+    }
+
     pub fn from_definition_with_health(instance_name: String, definition: EntityDefinition, current_health: i32) -> Self {
         Self {
             instance_name,
             definition,
+            definition_id: None,
             current_stamina: current_health,
         }
     }
@@ -49,10 +342,27 @@ impl Entity {
         &self.instance_name
     }
 
+    /// Display names are only for people, so they can change at any time
+    pub fn set_name(&mut self, name: String) {
+        self.instance_name = name;
+    }
+
+    /// `<set>/<id>` of the content definition, for creatures made from content
+    pub fn definition_id(&self) -> Option<&str> {
+        self.definition_id.as_deref()
+    }
+
     pub fn definition_name(&self) -> &String {
         &self.definition.name
     }
 
+    /// Swap in an updated definition, such as after content is reloaded.
+    /// Current stamina is kept, capped at the new maximum.
+    pub fn set_definition(&mut self, definition: EntityDefinition) {
+        self.definition = definition;
+        self.current_stamina = self.current_stamina.min(self.definition.max_stamina);
+    }
+
     pub fn set_health(&mut self, health: i32) {
         self.current_stamina = health.max(0).min(self.definition.max_stamina);
     }
@@ -68,4 +378,33 @@ impl Entity {
     pub fn is_alive(&self) -> bool {
         self.current_stamina > 0
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::creature;
+
+    #[test]
+    fn test_set_definition_keeps_stamina() {
+        let mut goblin = Entity::new("Goblin 1".to_string(), creature("Goblin", 10, &[]));
+        goblin.damage(3);
+        goblin.set_definition(creature("Goblin", 15, &[]));
+        assert_eq!(goblin.current_health(), 7);
+        assert_eq!(goblin.max_health(), 15);
+        goblin.set_definition(creature("Goblin", 5, &[]));
+        assert_eq!(goblin.current_health(), 5);
+    }
+
+    #[test]
+    fn test_definition_id_is_saved_with_the_entity() {
+        let goblin = Entity::new("Goblin 1".to_string(), creature("Goblin", 10, &[])).with_definition_id("monsters/goblin".to_string());
+        let json = serde_json::to_value(&goblin).unwrap();
+        assert_eq!(json["definition_id"], "monsters/goblin");
+        let loaded: Entity = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.definition_id(), Some("monsters/goblin"));
+
+        let anonymous = serde_json::to_value(Entity::new("Goblin 2".to_string(), creature("Goblin", 10, &[]))).unwrap();
+        assert!(anonymous.get("definition_id").is_none());
+    }
+}
@@ -0,0 +1,419 @@
+use std::{error::Error, fmt, io, path::PathBuf};
+use crate::combat::{ActionType, TurnSide};
+use crate::content::ContentSet;
+use crate::entity::EntityId;
+use crate::events::CombatEvent;
+use crate::grid::Position;
+
+/// Why a combat transition was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CombatError {
+    TurnInProgress,
+    NoTurnInProgress,
+    /// The current turn has used an action and can no longer be cancelled
+    TurnCommitted,
+    WrongSide { attempted: TurnSide, current: TurnSide },
+    NotInCombat { side: TurnSide, entity: EntityId },
+    AlreadyInCombat { side: TurnSide, entity: EntityId },
+    AlreadyActed { side: TurnSide, entity: EntityId },
+    /// The entity can't join or leave the combat in the middle of its own turn
+    TakingTurn { side: TurnSide, entity: EntityId },
+    /// Someone still has to take their turn before the round can end
+    RoundNotFinished,
+    AlreadyStarted,
+    /// The encounter is still being set up
+    NotStarted,
+    /// A combat can't start without someone on each side
+    NoCombatants { side: TurnSide },
+    UnknownEntity { entity: EntityId },
+    EmptyEventLog,
+    MissingCombatStart { first: CombatEvent },
+    /// Replaying an event log failed. `index` counts from 1.
+    Replay { index: usize, event: CombatEvent, source: Box<CombatError> },
+    /// The current turn has no action of this type left
+    NoActionLeft { action: ActionType },
+}
+
+impl fmt::Display for CombatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CombatError::TurnInProgress => write!(f, "A turn is already in progress. End the current turn first."),
+            CombatError::NoTurnInProgress => write!(f, "No turn in progress."),
+            CombatError::TurnCommitted => write!(f, "Cannot cancel turn after using an ability."),
+            CombatError::WrongSide { attempted, current } => write!(f, "Not {:?}'s turn, current side is {:?}", attempted, current),
+            CombatError::NotInCombat { side, entity } => write!(f, "{:?} {} is not in the combat", side, entity),
+            CombatError::AlreadyInCombat { side, entity } => write!(f, "{:?} {} already in combat", side, entity),
+            CombatError::AlreadyActed { side, entity } => write!(f, "{:?} {} has already taken their turn this round", side, entity),
+            CombatError::TakingTurn { side, entity } => {
+                write!(f, "{:?} {} is currently taking a turn. End or cancel the turn first.", side, entity)
+            }
+            CombatError::RoundNotFinished => write!(f, "Not all entities have taken their turn."),
+            CombatError::AlreadyStarted => write!(f, "Combat has already started"),
+            CombatError::NotStarted => write!(f, "Combat has not started yet"),
+            CombatError::NoCombatants { side } => write!(f, "Add at least one {:?} before starting combat", side),
+            CombatError::UnknownEntity { entity } => write!(f, "No entity {}", entity),
+            CombatError::EmptyEventLog => write!(f, "No events to replay"),
+            CombatError::MissingCombatStart { first } => {
+                write!(f, "Event log must start with the combat starting, not '{}'", first)
+            }
+            CombatError::Replay { index, event, source } => write!(f, "Failed to replay event {} ({}): {}", index, event, source),
+            CombatError::NoActionLeft { action } => {
+                write!(f, "No {} action left this turn", format!("{:?}", action).to_lowercase())
+            }
+        }
+    }
+}
+
+impl Error for CombatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CombatError::Replay { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Why something couldn't happen on the battlefield, or an ability couldn't be resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolutionError {
+    NotOnBattlefield { entity: EntityId },
+    OutsideGrid { position: Position },
+    DoesNotFit { entity: EntityId, position: Position },
+    Occupied { position: Position, occupant: EntityId },
+    NotAdjacent { from: Position, to: Position },
+    CannotPass { entity: EntityId, blocker: EntityId },
+    CannotEndIn { entity: EntityId, occupant: EntityId },
+    CannotFly { entity: EntityId },
+    /// Only a creature with a teleport speed can teleport as its move
+    CannotTeleport { entity: EntityId },
+    BelowGround { entity: EntityId },
+    OutOfRange { position: Position, range: i32 },
+    /// The path costs more movement than the creature has
+    TooFar { entity: EntityId, cost: i32, allowance: i32 },
+    AbilityNotFound { name: String },
+    /// The action was refused by the turn rules, or named someone not in the encounter
+    Combat(CombatError),
+}
+
+impl fmt::Display for ResolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolutionError::NotOnBattlefield { entity } => write!(f, "{} is not on the battlefield", entity),
+            ResolutionError::OutsideGrid { position } => write!(f, "Square ({}, {}) is outside the grid", position.x, position.y),
+            ResolutionError::DoesNotFit { entity, position } => {
+                write!(f, "{} does not fit on the grid at ({}, {})", entity, position.x, position.y)
+            }
+            ResolutionError::Occupied { position, occupant } => {
+                write!(f, "Square ({}, {}) is occupied by {}", position.x, position.y, occupant)
+            }
+            ResolutionError::NotAdjacent { from, to } => {
+                write!(f, "({}, {}) is not adjacent to ({}, {})", to.x, to.y, from.x, from.y)
+            }
+            ResolutionError::CannotPass { entity, blocker } => write!(f, "{} cannot move through {}", entity, blocker),
+            ResolutionError::CannotEndIn { entity, occupant } => {
+                write!(f, "{} cannot end their movement in the space of {}", entity, occupant)
+            }
+            ResolutionError::CannotFly { entity } => write!(f, "{} cannot fly", entity),
+            ResolutionError::CannotTeleport { entity } => write!(f, "{} cannot teleport", entity),
+            ResolutionError::BelowGround { entity } => write!(f, "{} cannot fly below the ground", entity),
+            ResolutionError::OutOfRange { position, range } => {
+                write!(f, "({}, {}) is out of range {}", position.x, position.y, range)
+            }
+            ResolutionError::TooFar { entity, cost, allowance } => {
+                write!(f, "{} needs {} movement to get there but only has {}", entity, cost, allowance)
+            }
+            ResolutionError::AbilityNotFound { name } => write!(f, "Ability '{}' not found", name),
+            ResolutionError::Combat(source) => write!(f, "{}", source),
+        }
+    }
+}
+
+impl Error for ResolutionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ResolutionError::Combat(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<CombatError> for ResolutionError {
+    fn from(source: CombatError) -> Self {
+        ResolutionError::Combat(source)
+    }
+}
+
+/// Why content files couldn't be loaded
+#[derive(Debug)]
+pub enum ContentError {
+    MissingDirectory { path: PathBuf },
+    Io { path: PathBuf, source: io::Error },
+    Parse { path: PathBuf, line: usize, column: usize, message: String },
+    UnknownMapSymbol { path: PathBuf, line: usize, column: usize, symbol: char },
+    InvalidSize { value: String },
+    InvalidMap { name: String, source: ResolutionError },
+    /// Two files in one set define the same ID, such as `goblin.json` and `goblin.yaml`
+    DuplicateId { id: String, first: PathBuf, second: PathBuf },
+    /// A content file doesn't match the schema of its set. `pointer` locates the offending value, and
+    /// `line` and `column` where it is written, or are 0 if that isn't known.
+    Schema { path: PathBuf, pointer: String, line: usize, column: usize, message: String },
+    /// A hero or monster lists an ability that isn't defined
+    UnknownAbility { definition: String, ability: String },
+    /// A content root without a `pack.json`
+    MissingManifest { path: PathBuf },
+    /// A pack depends on one that isn't loaded before it
+    MissingDependency { pack: String, dependency: String },
+    /// Several problems found in one pass, so they can all be fixed at once
+    Multiple(Vec<ContentError>),
+}
+
+impl ContentError {
+    /// Fold a list of problems into one error, or nothing if the list is empty
+    pub fn from_all(mut errors: Vec<ContentError>) -> Result<(), ContentError> {
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.remove(0)),
+            _ => Err(ContentError::Multiple(errors)),
+        }
+    }
+
+    /// Every individual problem, with nested lists flattened
+    pub fn problems(&self) -> Vec<&ContentError> {
+        match self {
+            ContentError::Multiple(errors) => errors.iter().flat_map(|error| error.problems()).collect(),
+            error => vec![error],
+        }
+    }
+}
+
+impl fmt::Display for ContentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentError::MissingDirectory { path } => write!(f, "Directory {} does not exist", path.display()),
+            ContentError::Io { path, source } => write!(f, "Failed to read {}: {}", path.display(), source),
+            ContentError::Parse { path, line, column, message } => {
+                write!(f, "Failed to parse file {} at line {}, column {}: {}", path.display(), line, column, message)
+            }
+            ContentError::UnknownMapSymbol { path, line, column, symbol } => write!(
+                f,
+                "Unknown map symbol '{}' in file {} at line {}, column {}",
+                symbol,
+                path.display(),
+                line,
+                column
+            ),
+            ContentError::InvalidSize { value } => {
+                write!(f, "Invalid size '{}', expected 1T, 1S, 1M, 1L or a number of squares", value)
+            }
+            ContentError::InvalidMap { name, source } => write!(f, "Invalid map '{}': {}", name, source),
+            ContentError::DuplicateId { id, first, second } => {
+                write!(f, "'{}' is defined by both {} and {}", id, first.display(), second.display())
+            }
+            ContentError::Schema { path, pointer, line, column, message } => {
+                let pointer = if pointer.is_empty() { "/" } else { pointer };
+                write!(f, "{} does not match its schema at {}", path.display(), pointer)?;
+                if *line > 0 {
+                    write!(f, " (line {}, column {})", line, column)?;
+                }
+                write!(f, ": {}", message)
+            }
+            ContentError::UnknownAbility { definition, ability } => {
+                write!(f, "'{}' uses ability '{}', which is not defined", definition, ability)
+            }
+            ContentError::MissingManifest { path } => write!(f, "Content pack {} has no pack.json", path.display()),
+            ContentError::MissingDependency { pack, dependency } => {
+                write!(f, "Pack '{}' depends on '{}', which is not loaded before it", pack, dependency)
+            }
+            ContentError::Multiple(_) => {
+                let problems = self.problems();
+                write!(f, "{} content problems:", problems.len())?;
+                for error in problems {
+                    write!(f, "\n  {}", error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for ContentError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ContentError::Io { source, .. } => Some(source),
+            ContentError::InvalidMap { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Something about content that doesn't stop it loading, but is worth fixing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentWarning {
+    /// Several definitions of one set share a display name, so they can't be told apart in play
+    DuplicateName { set: ContentSet, name: String, ids: Vec<String> },
+}
+
+impl fmt::Display for ContentWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentWarning::DuplicateName { set, name, ids } => {
+                write!(f, "{} '{}' is the name of {}", set.dir_name(), name, ids.join(", "))
+            }
+        }
+    }
+}
+
+/// Why a saved session couldn't be written or read back
+#[derive(Debug)]
+pub enum SessionError {
+    Io { path: PathBuf, source: io::Error },
+    Json(serde_json::Error),
+    MissingVersion,
+    UnsupportedVersion { found: u64, expected: u32 },
+    MissingSession,
+    Replay(CombatError),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Io { path, source } => write!(f, "Failed to access {}: {}", path.display(), source),
+            SessionError::Json(source) => write!(f, "Invalid session: {}", source),
+            SessionError::MissingVersion => write!(f, "Session has no version"),
+            SessionError::UnsupportedVersion { found, expected } => {
+                write!(f, "Unsupported session version {} (expected {})", found, expected)
+            }
+            SessionError::MissingSession => write!(f, "Session is missing its combat state"),
+            SessionError::Replay(source) => write!(f, "Session event log is invalid: {}", source),
+        }
+    }
+}
+
+impl Error for SessionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SessionError::Io { source, .. } => Some(source),
+            SessionError::Json(source) => Some(source),
+            SessionError::Replay(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for SessionError {
+    fn from(source: serde_json::Error) -> Self {
+        SessionError::Json(source)
+    }
+}
+
+impl From<CombatError> for SessionError {
+    fn from(source: CombatError) -> Self {
+        SessionError::Replay(source)
+    }
+}
+
+/// Why a simulated combat couldn't be played out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimulationError {
+    Combat(CombatError),
+    Resolution(ResolutionError),
+    /// There is no free space on the battlefield for a creature
+    NoRoom { name: String },
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulationError::Combat(source) => write!(f, "Simulated combat failed: {}", source),
+            SimulationError::Resolution(source) => write!(f, "Simulated combat failed: {}", source),
+            SimulationError::NoRoom { name } => write!(f, "No room on the battlefield for {}", name),
+        }
+    }
+}
+
+impl Error for SimulationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SimulationError::Combat(source) => Some(source),
+            SimulationError::Resolution(source) => Some(source),
+            SimulationError::NoRoom { .. } => None,
+        }
+    }
+}
+
+impl From<CombatError> for SimulationError {
+    fn from(source: CombatError) -> Self {
+        SimulationError::Combat(source)
+    }
+}
+
+impl From<ResolutionError> for SimulationError {
+    fn from(source: ResolutionError) -> Self {
+        SimulationError::Resolution(source)
+    }
+}
+
+/// Why a dice expression such as `2d6 + M` couldn't be read. Columns count from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpressionError {
+    Empty,
+    /// Something other than a number, dice or characteristic where one was expected
+    ExpectedTerm { column: usize },
+    Unexpected { column: usize, found: String },
+    /// Dice need at least one die and at least one side, as in `1d3`
+    InvalidDice { text: String },
+    /// A number too large to roll or add up
+    TooLarge { text: String },
+    /// `per` must be followed by what to count, as in `1d3 per surge`
+    MissingCount,
+    /// `per` counts something rolls don't know, as anything but surges
+    UnknownCount { column: usize, name: String },
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpressionError::Empty => write!(f, "Empty dice expression"),
+            ExpressionError::ExpectedTerm { column } => {
+                write!(f, "Expected a number, dice or characteristic at column {}", column)
+            }
+            ExpressionError::Unexpected { column, found } => write!(f, "Unexpected '{}' at column {}", found, column),
+            ExpressionError::InvalidDice { text } => write!(f, "Invalid dice '{}'", text),
+            ExpressionError::TooLarge { text } => write!(f, "'{}' is too large", text),
+            ExpressionError::MissingCount => write!(f, "Expected what to count after 'per', as in '1d3 per surge'"),
+            ExpressionError::UnknownCount { column, name } => {
+                write!(f, "Can't count '{}' at column {}; only 'per surge' is known", name, column)
+            }
+        }
+    }
+}
+
+impl Error for ExpressionError {}
+
+/// Why a montage test refused an attempt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MontageError {
+    /// The montage has already succeeded or failed
+    Finished,
+    NotParticipating { hero: EntityId },
+    /// Each hero makes at most one test a round
+    AlreadyActed { hero: EntityId, round: u32 },
+    /// Limits and the round count must be at least 1
+    InvalidLimits,
+    /// A montage needs at least one hero to take part
+    NoHeroes,
+}
+
+impl fmt::Display for MontageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MontageError::Finished => write!(f, "The montage is already over"),
+            MontageError::NotParticipating { hero } => write!(f, "{} isn't taking part in the montage", hero),
+            MontageError::AlreadyActed { hero, round } => write!(f, "{} has already acted in round {}", hero, round),
+            MontageError::InvalidLimits => write!(f, "Success and failure limits and the number of rounds must be at least 1"),
+            MontageError::NoHeroes => write!(f, "A montage needs at least one hero"),
+        }
+    }
+}
+
+impl Error for MontageError {}
@@ -0,0 +1,394 @@
+use std::{fmt, sync::Arc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::combat::{ActionType, CombatParameters, CombatState, TurnSide};
+use crate::dice::PowerRollResultTier;
+use indexmap::IndexMap;
+use crate::entity::{Entity, EntityDefinition, EntityId};
+use crate::error::CombatError;
+
+/**
+ * Something that happened during a combat. Every `CombatState` transition records one,
+ * and replaying the recorded events in order rebuilds the same state.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CombatEvent {
+    CombatStarted { pcs: Vec<EntityId>, npcs: Vec<EntityId>, starting_side: TurnSide },
+    TurnStarted { side: TurnSide, entity: EntityId },
+    /// An action was taken, so the current turn can no longer be cancelled
+    TurnCommitted,
+    ActionUsed { entity: EntityId, action: ActionType },
+    /// A critical hit with a main action gave the creature another
+    MainActionGranted { entity: EntityId },
+    TurnEnded { side: TurnSide, entity: EntityId },
+    TurnCancelled { side: TurnSide, entity: EntityId },
+    /// `round` is the round that begins
+    RoundCompleted { round: i16 },
+    EntityAdded { side: TurnSide, entity: EntityId },
+    /// A creature's name, stat block and stamina as the combat starts or as it joins later,
+    /// so the log alone can rebuild every creature
+    CreatureJoined { entity: EntityId, creature: Box<Entity> },
+    EntityRemoved { side: TurnSide, entity: EntityId },
+    /// An entity's display name changed. Its turn tracking is unaffected.
+    EntityRenamed { entity: EntityId, name: String },
+    /// A creature's stat block was swapped for an updated one, such as after content is reloaded
+    Redefined { entity: EntityId, definition: Box<EntityDefinition> },
+    RollMade { actor: EntityId, ability: String, tier: PowerRollResultTier },
+    DamageApplied { target: EntityId, amount: i32 },
+    Healed { target: EntityId, amount: i32 },
+}
+
+impl CombatEvent {
+    /// Describe the event for people, naming entities with `name` rather than by ID
+    pub fn describe(&self, name: impl Fn(EntityId) -> String) -> String {
+        let names = |entities: &[EntityId]| entities.iter().map(|entity| name(*entity)).collect::<Vec<_>>().join(", ");
+        match self {
+            CombatEvent::CombatStarted { pcs, npcs, starting_side } => {
+                format!("Combat started: {} vs {}, {:?} first", names(pcs), names(npcs), starting_side)
+            }
+            CombatEvent::TurnStarted { entity, .. } => format!("{} started their turn", name(*entity)),
+            CombatEvent::TurnCommitted => "Turn committed".to_string(),
+            CombatEvent::ActionUsed { entity, action } => format!("{} used a {} action", name(*entity), format!("{:?}", action).to_lowercase()),
+            CombatEvent::MainActionGranted { entity } => format!("{} gained an extra main action", name(*entity)),
+            CombatEvent::TurnEnded { entity, .. } => format!("{} ended their turn", name(*entity)),
+            CombatEvent::TurnCancelled { entity, .. } => format!("{} cancelled their turn", name(*entity)),
+            CombatEvent::RoundCompleted { round } => format!("Round {} begins", round),
+            CombatEvent::EntityAdded { side, entity } => format!("{:?} {} joined the combat", side, name(*entity)),
+            CombatEvent::EntityRemoved { side, entity } => format!("{:?} {} left the combat", side, name(*entity)),
+            CombatEvent::CreatureJoined { entity, creature } => format!(
+                "{} is a {} with {}/{} stamina",
+                name(*entity),
+                creature.definition_name(),
+                creature.current_health(),
+                creature.max_health()
+            ),
+            CombatEvent::EntityRenamed { entity, name: new_name } => format!("{} is now called {}", entity, new_name),
+            CombatEvent::Redefined { entity, .. } => format!("{} has an updated stat block", name(*entity)),
+            CombatEvent::RollMade { actor, ability, tier } => format!("{} used {}: {:?}", name(*actor), ability, tier),
+            CombatEvent::DamageApplied { target, amount } => format!("{} took {} damage", name(*target), amount),
+            CombatEvent::Healed { target, amount } => format!("{} regained {} stamina", name(*target), amount),
+        }
+    }
+}
+
+impl fmt::Display for CombatEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(|entity| entity.to_string()))
+    }
+}
+
+/**
+ * The events of a combat. Events are only ever appended, so a clone shares the events recorded so far
+ * instead of copying them. Every state a transition produces and every undo snapshot then costs the same
+ * to make, however long the combat has run. Each event links back to the one before, so the log is
+ * walked from the latest event.
+ */
+#[derive(Clone, Default)]
+pub struct EventLog {
+    last: Option<Arc<LoggedEvent>>,
+    len: usize,
+}
+
+struct LoggedEvent {
+    event: CombatEvent,
+    previous: Option<Arc<LoggedEvent>>,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: CombatEvent) {
+        let previous = self.last.take();
+        self.last = Some(Arc::new(LoggedEvent { event, previous }));
+        self.len += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The most recent event
+    pub fn last(&self) -> Option<&CombatEvent> {
+        self.last.as_ref().map(|logged| &logged.event)
+    }
+
+    /// Every event, newest first
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { next: self.last.as_deref(), remaining: self.len }
+    }
+
+    pub fn contains(&self, event: &CombatEvent) -> bool {
+        self.iter().any(|logged| logged == event)
+    }
+
+    /// Every event, oldest first, as replaying the combat needs them
+    pub fn to_vec(&self) -> Vec<CombatEvent> {
+        let mut events: Vec<CombatEvent> = self.iter().cloned().collect();
+        events.reverse();
+        events
+    }
+}
+
+impl Drop for EventLog {
+    fn drop(&mut self) {
+        // Unlink events one at a time, since dropping a long chain recursively could overflow the stack.
+        // Events still shared with another log are left to it.
+        let mut next = self.last.take();
+        while let Some(logged) = next {
+            next = Arc::try_unwrap(logged).ok().and_then(|mut logged| logged.previous.take());
+        }
+    }
+}
+
+/// Walks an event log from the latest event back to the first
+pub struct Iter<'a> {
+    next: Option<&'a LoggedEvent>,
+    remaining: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a CombatEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let logged = self.next?;
+        self.next = logged.previous.as_deref();
+        self.remaining -= 1;
+        Some(&logged.event)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl FromIterator<CombatEvent> for EventLog {
+    fn from_iter<I: IntoIterator<Item = CombatEvent>>(events: I) -> Self {
+        let mut log = Self::new();
+        for event in events {
+            log.push(event);
+        }
+        log
+    }
+}
+
+impl fmt::Debug for EventLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.to_vec()).finish()
+    }
+}
+
+impl PartialEq for EventLog {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for EventLog {}
+
+impl Serialize for EventLog {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        serializer.collect_seq(self.to_vec())
+    }
+}
+
+impl<'de> Deserialize<'de> for EventLog {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        Ok(Vec::<CombatEvent>::deserialize(deserializer)?.into_iter().collect())
+    }
+}
+
+impl CombatState {
+    /// Apply a recorded event through the same transition that first produced it
+    pub fn apply(&self, event: &CombatEvent) -> Result<Self, CombatError> {
+        match event {
+            CombatEvent::CombatStarted { .. } => Err(CombatError::AlreadyStarted),
+            CombatEvent::TurnStarted { side, entity } => self.start_turn(*side, *entity),
+            CombatEvent::TurnCommitted => self.commit_turn(),
+            CombatEvent::ActionUsed { action, .. } => self.use_action(*action),
+            CombatEvent::MainActionGranted { .. } => self.grant_main_action(),
+            CombatEvent::TurnEnded { .. } => self.end_turn(),
+            CombatEvent::TurnCancelled { .. } => self.cancel_turn(),
+            CombatEvent::RoundCompleted { .. } => self.complete_round(),
+            CombatEvent::EntityAdded { side: TurnSide::PC, entity } => self.add_pc(*entity),
+            CombatEvent::EntityAdded { side: TurnSide::NPC, entity } => self.add_npc(*entity),
+            CombatEvent::EntityRemoved { side: TurnSide::PC, entity } => self.remove_pc(*entity),
+            CombatEvent::EntityRemoved { side: TurnSide::NPC, entity } => self.remove_npc(*entity),
+            CombatEvent::CreatureJoined { .. }
+            | CombatEvent::EntityRenamed { .. }
+            | CombatEvent::Redefined { .. }
+            | CombatEvent::RollMade { .. }
+            | CombatEvent::DamageApplied { .. }
+            | CombatEvent::Healed { .. } => Ok(self.record(event.clone())),
+        }
+    }
+
+    /// Record an event that doesn't change the turn order, such as a roll, damage or a new name
+    pub fn record(&self, event: CombatEvent) -> Self {
+        let mut new_state = self.clone();
+        new_state.push_event(event);
+        new_state
+    }
+
+    /// Rebuild a combat from its event log. The first event must start the combat.
+    pub fn replay(events: &[CombatEvent]) -> Result<Self, CombatError> {
+        let (first, rest) = match events.split_first() {
+            Some(split) => split,
+            None => return Err(CombatError::EmptyEventLog),
+        };
+        let mut state = match first {
+            CombatEvent::CombatStarted { pcs, npcs, starting_side } => {
+                CombatState::new(CombatParameters::new(pcs.clone(), npcs.clone(), *starting_side))
+            }
+            other => return Err(CombatError::MissingCombatStart { first: other.clone() }),
+        };
+        for (index, event) in rest.iter().enumerate() {
+            state = state
+                .apply(event)
+                .map_err(|e| CombatError::Replay { index: index + 2, event: event.clone(), source: Box::new(e) })?;
+        }
+        Ok(state)
+    }
+}
+
+/// Rebuild every creature still in a combat from its event log: who joined with what stat block,
+/// and every rename, update, damage and healing since
+pub fn replay_roster<'a>(events: impl IntoIterator<Item = &'a CombatEvent>) -> Result<IndexMap<EntityId, Entity>, CombatError> {
+    let mut entities: IndexMap<EntityId, Entity> = IndexMap::new();
+    for (index, event) in events.into_iter().enumerate() {
+        let target = match event {
+            CombatEvent::CreatureJoined { entity, creature } => {
+                entities.insert(*entity, creature.as_ref().clone());
+                continue;
+            }
+            CombatEvent::EntityRemoved { entity, .. } => {
+                entities.shift_remove(entity);
+                continue;
+            }
+            CombatEvent::EntityRenamed { entity, .. } | CombatEvent::Redefined { entity, .. } => *entity,
+            CombatEvent::DamageApplied { target, .. } | CombatEvent::Healed { target, .. } => *target,
+            _ => continue,
+        };
+        let creature = match entities.get_mut(&target) {
+            Some(creature) => creature,
+            None => {
+                return Err(CombatError::Replay {
+                    index: index + 1,
+                    event: event.clone(),
+                    source: Box::new(CombatError::UnknownEntity { entity: target }),
+                });
+            }
+        };
+        match event {
+            CombatEvent::EntityRenamed { name, .. } => creature.set_name(name.clone()),
+            CombatEvent::Redefined { definition, .. } => creature.set_definition(definition.as_ref().clone()),
+            CombatEvent::DamageApplied { amount, .. } => creature.damage(*amount),
+            CombatEvent::Healed { amount, .. } => creature.heal(*amount),
+            _ => {}
+        }
+    }
+    Ok(entities)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PC1: EntityId = EntityId::new(1);
+    const NPC1: EntityId = EntityId::new(2);
+    const NPC2: EntityId = EntityId::new(3);
+
+    fn combat() -> CombatState {
+        let params = CombatParameters::new(vec![PC1], vec![NPC1], TurnSide::PC);
+        CombatState::new(params)
+    }
+
+    #[test]
+    fn test_transitions_emit_events() {
+        let state = combat()
+            .start_turn(TurnSide::PC, PC1).unwrap()
+            .commit_turn().unwrap()
+            .end_turn().unwrap();
+        assert_eq!(state.events().len(), 4);
+        assert!(matches!(state.events().iter().last(), Some(CombatEvent::CombatStarted { .. })));
+        assert_eq!(state.events().iter().len(), 4);
+        assert_eq!(state.events().last(), Some(&CombatEvent::TurnEnded { side: TurnSide::PC, entity: PC1 }));
+    }
+
+    #[test]
+    fn test_failed_transitions_emit_nothing() {
+        let state = combat();
+        assert!(state.end_turn().is_err());
+        assert_eq!(state.events().len(), 1);
+    }
+
+    #[test]
+    fn test_replay_rebuilds_state() {
+        let state = combat()
+            .start_turn(TurnSide::PC, PC1).unwrap()
+            .record(CombatEvent::DamageApplied { target: NPC1, amount: 3 })
+            .end_turn().unwrap()
+            .add_npc(NPC2).unwrap()
+            .start_turn(TurnSide::NPC, NPC1).unwrap()
+            .end_turn().unwrap()
+            .start_turn(TurnSide::NPC, NPC2).unwrap()
+            .end_turn().unwrap()
+            .complete_round().unwrap()
+            .remove_npc(NPC2).unwrap();
+
+        let replayed = CombatState::replay(&state.events().to_vec()).unwrap();
+        assert_eq!(replayed.events(), state.events());
+        assert_eq!(replayed.round(), 2);
+        assert_eq!(replayed.current_side(), state.current_side());
+        assert_eq!(replayed.all_npcs(), state.all_npcs());
+        assert!(replayed.pc_taken_turns().is_empty());
+    }
+
+    #[test]
+    fn test_replay_rejects_invalid_logs() {
+        assert!(CombatState::replay(&[]).is_err());
+        assert!(CombatState::replay(&[CombatEvent::TurnCommitted]).is_err());
+
+        let mut events = combat().events().to_vec();
+        events.push(CombatEvent::TurnEnded { side: TurnSide::PC, entity: PC1 });
+        assert!(matches!(CombatState::replay(&events), Err(CombatError::Replay { index: 2, .. })));
+    }
+
+    #[test]
+    fn test_logs_share_events_with_their_clones() {
+        let mut log: EventLog = (1..=3).map(|round| CombatEvent::RoundCompleted { round }).collect();
+        let snapshot = log.clone();
+        log.push(CombatEvent::TurnCommitted);
+        assert_eq!(snapshot.len(), 3);
+        assert_eq!(log.len(), 4);
+        assert_eq!(log.iter().skip(1).collect::<Vec<_>>(), snapshot.iter().collect::<Vec<_>>());
+        assert_eq!(log.to_vec().first(), Some(&CombatEvent::RoundCompleted { round: 1 }));
+        assert_eq!(serde_json::from_value::<EventLog>(serde_json::to_value(&log).unwrap()).unwrap(), log);
+
+        // Long logs are dropped without recursing once per event
+        let long: EventLog = (0..200_000).map(|_| CombatEvent::TurnCommitted).collect();
+        drop(long);
+    }
+
+    #[test]
+    fn test_events_describe_entities_by_name() {
+        let event = CombatEvent::DamageApplied { target: NPC1, amount: 3 };
+        assert_eq!(event.to_string(), "#2 took 3 damage");
+        assert_eq!(event.describe(|_| "Goblin 1".to_string()), "Goblin 1 took 3 damage");
+    }
+
+    #[test]
+    fn test_events_serialize_with_type_tag() {
+        let event = CombatEvent::RoundCompleted { round: 3 };
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(json, r#"{"type":"round_completed","round":3}"#);
+        assert_eq!(serde_json::from_str::<CombatEvent>(&json).unwrap(), event);
+    }
+}
@@ -1,48 +1,365 @@
-use std::{fs, path::Path};
+use std::{fmt, fs, path::{Path, PathBuf}};
 use indexmap::IndexMap;
-use serde::de::DeserializeOwned;
-use serde_json::from_str;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::{Value, from_str};
+use crate::error::ContentError;
 
-pub fn load_set<T>(dir: &Path) -> Result<IndexMap<String, T>, String> where T: DeserializeOwned {
-    if !dir.exists() {
-        return Err(format!("Directory {} does not exist", dir.display()));
+/// Parses the text of a content file. Receives the file path for error messages.
+pub type Parser<T> = fn(&str, &Path) -> Result<T, ContentError>;
+
+pub fn parse_json<T>(content: &str, path: &Path) -> Result<T, ContentError> where T: DeserializeOwned {
+    match from_str(content) {
+        Ok(d) => Ok(d),
+        Err(e) => {
+            // serde_json appends the position to its message; it is reported separately
+            let position = format!(" at line {} column {}", e.line(), e.column());
+            let message = e.to_string();
+            Err(ContentError::Parse {
+                path: path.to_path_buf(),
+                line: e.line(),
+                column: e.column(),
+                message: message.strip_suffix(&position).unwrap_or(&message).to_string(),
+            })
+        }
+    }
+}
+
+pub fn parse_yaml<T>(content: &str, path: &Path) -> Result<T, ContentError> where T: DeserializeOwned {
+    match serde_yaml::from_str(content) {
+        Ok(d) => Ok(d),
+        Err(e) => {
+            let (line, column) = e.location().map(|location| (location.line(), location.column())).unwrap_or((0, 0));
+            // Like serde_json, serde_yaml appends the position to its message
+            let position = format!(" at line {} column {}", line, column);
+            let message = e.to_string();
+            Err(ContentError::Parse {
+                path: path.to_path_buf(),
+                line,
+                column,
+                message: message.strip_suffix(&position).unwrap_or(&message).to_string(),
+            })
+        }
+    }
+}
+
+pub fn parse_toml<T>(content: &str, path: &Path) -> Result<T, ContentError> where T: DeserializeOwned {
+    match toml::from_str(content) {
+        Ok(d) => Ok(d),
+        Err(e) => {
+            let (line, column) = e.span().map(|span| line_column(content, span.start)).unwrap_or((0, 0));
+            Err(ContentError::Parse { path: path.to_path_buf(), line, column, message: e.message().to_string() })
+        }
+    }
+}
+
+/// 1-based line and column of a byte offset
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |last| last.chars().count()) + 1;
+    (line, column)
+}
+
+/// Raised on reaching the value being located, so the deserializer reports where it is
+const LOCATED: &str = "located";
+
+/// Walks a document down a JSON pointer without building anything, failing once it reaches the value
+struct Locate<'a> {
+    pointer: &'a [String],
+}
+
+impl<'de> DeserializeSeed<'de> for Locate<'_> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error> where D: Deserializer<'de> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl Locate<'_> {
+    /// A scalar is where the search ends: found if nothing is left of the pointer, otherwise not there
+    fn scalar<E>(&self) -> Result<(), E> where E: de::Error {
+        if self.pointer.is_empty() { Err(E::custom(LOCATED)) } else { Ok(()) }
+    }
+}
+
+impl<'de> Visitor<'de> for Locate<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "any value")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<(), A::Error> where A: MapAccess<'de> {
+        if self.pointer.is_empty() {
+            return Err(de::Error::custom(LOCATED));
+        }
+        while let Some(key) = map.next_key::<Value>()? {
+            let key = key.as_str().map_or_else(|| key.to_string(), str::to_string);
+            if key == self.pointer[0] {
+                return map.next_value_seed(Locate { pointer: &self.pointer[1..] });
+            }
+            map.next_value::<IgnoredAny>()?;
+        }
+        Ok(())
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<(), A::Error> where A: SeqAccess<'de> {
+        if self.pointer.is_empty() {
+            return Err(de::Error::custom(LOCATED));
+        }
+        let mut index = 0;
+        loop {
+            if self.pointer[0] == index.to_string() {
+                return seq.next_element_seed(Locate { pointer: &self.pointer[1..] }).map(|_| ());
+            }
+            if seq.next_element::<IgnoredAny>()?.is_none() {
+                return Ok(());
+            }
+            index += 1;
+        }
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<(), E> where E: de::Error {
+        self.scalar()
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<(), E> where E: de::Error {
+        self.scalar()
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<(), E> where E: de::Error {
+        self.scalar()
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<(), E> where E: de::Error {
+        self.scalar()
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<(), E> where E: de::Error {
+        self.scalar()
+    }
+
+    fn visit_unit<E>(self) -> Result<(), E> where E: de::Error {
+        self.scalar()
     }
-    
+}
+
+/// Line and column in the text of a content file of the value a JSON pointer such as `/tiers/0/damage`
+/// points to, asking the file's own parser where it finds it. `None` if the format is unknown or the
+/// value isn't there.
+pub fn locate(extension: &str, content: &str, pointer: &str) -> Option<(usize, usize)> {
+    let pointer: Vec<String> =
+        pointer.split('/').skip(1).map(|segment| segment.replace("~1", "/").replace("~0", "~")).collect();
+    let seed = Locate { pointer: &pointer };
+    match extension {
+        "json" => seed
+            .deserialize(&mut serde_json::Deserializer::from_str(content))
+            .err()
+            .filter(|e| e.to_string().starts_with(LOCATED))
+            .map(|e| (e.line(), e.column())),
+        // serde_yaml puts the path to the value in front of the message
+        "yaml" | "yml" => seed
+            .deserialize(serde_yaml::Deserializer::from_str(content))
+            .err()
+            .filter(|e| e.to_string().contains(LOCATED))
+            .and_then(|e| e.location())
+            .map(|location| (location.line(), location.column())),
+        "toml" => seed
+            .deserialize(toml::Deserializer::new(content))
+            .err()
+            .filter(|e| e.message() == LOCATED)
+            .and_then(|e| e.span())
+            .map(|span| line_column(content, span.start)),
+        _ => None,
+    }
+}
+
+/// Parsers for every format a definition can be written in, by file extension
+pub fn structured_parsers<T>() -> Vec<(&'static str, Parser<T>)> where T: DeserializeOwned {
+    vec![
+        ("json", parse_json::<T>),
+        ("yaml", parse_yaml::<T>),
+        ("yml", parse_yaml::<T>),
+        ("toml", parse_toml::<T>),
+    ]
+}
+
+pub fn load_set<T>(dir: &Path) -> Result<IndexMap<String, T>, ContentError> where T: DeserializeOwned {
+    load_set_with(dir, &structured_parsers::<T>())
+}
+
+/// Every file below a directory, in a stable order. Hidden files and directories are skipped, and so are
+/// symlinks to directories, which could otherwise lead back up the tree and recurse forever.
+pub(crate) fn content_files(dir: &Path, files: &mut Vec<PathBuf>, errors: &mut Vec<ContentError>) {
     let entries = match fs::read_dir(dir) {
         Ok(entries) => entries,
-        Err(_) => return Err(format!("Failed to read directory {}", dir.display())),
+        Err(e) => {
+            errors.push(ContentError::Io { path: dir.to_path_buf(), source: e });
+            return;
+        }
     };
+    let mut paths: Vec<(PathBuf, fs::FileType)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_type().ok().map(|file_type| (entry.path(), file_type)))
+        .filter(|(path, _)| !path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.')))
+        .collect();
+    paths.sort_by(|a, b| a.0.cmp(&b.0));
+    for (path, file_type) in paths {
+        if file_type.is_dir() {
+            content_files(&path, files, errors);
+        } else if !file_type.is_symlink() || !path.is_dir() {
+            files.push(path);
+        }
+    }
+}
+
+/// Load every file below a directory, choosing the parser by file extension.
+/// Definitions are keyed by their path relative to the directory, without the extension and with
+/// `/` between folders, so `goblins/goblin_warrior.yaml` becomes `goblins/goblin_warrior`.
+/// Files with other extensions, such as a README, are skipped. Every broken file is reported,
+/// not just the first one, as is every ID defined by more than one file.
+pub fn load_set_with<T>(dir: &Path, parsers: &[(&str, Parser<T>)]) -> Result<IndexMap<String, T>, ContentError> {
+    let extensions: Vec<&str> = parsers.iter().map(|(extension, _)| *extension).collect();
+    load_files(dir, &extensions, |extension, content, path| {
+        let (_, parser) = parsers.iter().find(|(ext, _)| *ext == extension).expect("extension has a parser");
+        parser(content, path).map_err(|e| vec![e])
+    })
+}
+
+/// Like `load_set_with`, but `read` turns the text of a file with one of the extensions into a
+/// definition, and may find several problems with it
+pub(crate) fn load_files<T>(
+    dir: &Path,
+    extensions: &[&str],
+    read: impl Fn(&str, &str, &Path) -> Result<T, Vec<ContentError>>,
+) -> Result<IndexMap<String, T>, ContentError> {
+    if !dir.exists() {
+        return Err(ContentError::MissingDirectory { path: dir.to_path_buf() });
+    }
+
+    let mut files = vec![];
+    let mut errors = vec![];
+    content_files(dir, &mut files, &mut errors);
 
     let mut definitions = IndexMap::new();
-    
-    for entry in entries {
-        let entry = match entry {
-            Ok(e) => e,
-            Err(_) => continue,
-        };
-        let file_name_with_ext = entry.file_name().to_string_lossy().to_string();
-        let file_name = file_name_with_ext
-            .strip_suffix(".json")
-            .unwrap_or(&file_name_with_ext)
-            .to_string();
-        
-        let path = entry.path();
-        if path.extension().and_then(|s| s.to_str()) != Some("json") {
-            return Err(format!("File {} is not a JSON file", path.display()));
-        }
-        
+    let mut sources: IndexMap<String, PathBuf> = IndexMap::new();
+
+    for path in files {
+        let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+        if !extensions.contains(&extension) {
+            continue;
+        }
+        let id = content_id(dir, &path);
+        if let Some(first) = sources.get(&id) {
+            errors.push(ContentError::DuplicateId { id, first: first.clone(), second: path });
+            continue;
+        }
+
         let content = match fs::read_to_string(&path) {
             Ok(c) => c,
-            Err(_) => return Err(format!("Failed to read file {}", path.display())),
+            Err(e) => {
+                errors.push(ContentError::Io { path, source: e });
+                continue;
+            }
         };
-        
-        let definition: T = match from_str(&content) {
-            Ok(d) => d,
-            Err(_) => return Err(format!("Failed to parse file {}", path.display())),
-        };
-        
-        definitions.insert(file_name, definition);
+
+        match read(extension, &content, &path) {
+            Ok(definition) => {
+                definitions.insert(id.clone(), definition);
+            }
+            Err(problems) => errors.extend(problems),
+        }
+        sources.insert(id, path);
     }
-    
+
+    ContentError::from_all(errors)?;
     Ok(definitions)
-}
\ No newline at end of file
+}
+
+/// The ID of a content file: its path below the set's directory, without the extension
+fn content_id(dir: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(dir).unwrap_or(path).with_extension("");
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Monster {
+        name: String,
+        max_stamina: i32,
+    }
+
+    fn set_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("odsr-fs-{}-{}", name, std::process::id()));
+        fs::create_dir_all(dir.join("goblins")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_loads_nested_files_in_every_format() {
+        let dir = set_dir("formats");
+        fs::write(dir.join("ogre.json"), r#"{"name": "Ogre", "max_stamina": 30}"#).unwrap();
+        fs::write(dir.join("goblins/goblin_warrior.yaml"), "name: Goblin Warrior\nmax_stamina: 15\n").unwrap();
+        fs::write(dir.join("goblins/goblin_sniper.toml"), "name = \"Goblin Sniper\"\nmax_stamina = 10\n").unwrap();
+        fs::write(dir.join("goblins/notes.txt"), "not content").unwrap();
+        let monsters = load_set::<Monster>(&dir);
+        fs::remove_dir_all(&dir).ok();
+
+        let monsters = monsters.unwrap();
+        assert_eq!(monsters.keys().collect::<Vec<_>>(), vec!["goblins/goblin_sniper", "goblins/goblin_warrior", "ogre"]);
+        assert_eq!(monsters["goblins/goblin_warrior"], Monster { name: "Goblin Warrior".to_string(), max_stamina: 15 });
+        assert_eq!(monsters["goblins/goblin_sniper"].max_stamina, 10);
+    }
+
+    #[test]
+    fn test_duplicate_ids_across_formats_are_reported() {
+        let dir = set_dir("duplicates");
+        fs::write(dir.join("goblins/scout.json"), r#"{"name": "Scout", "max_stamina": 8}"#).unwrap();
+        fs::write(dir.join("goblins/scout.yaml"), "name: Scout\nmax_stamina: 8\n").unwrap();
+        let error = load_set::<Monster>(&dir).unwrap_err();
+        fs::remove_dir_all(&dir).ok();
+        assert!(matches!(error, ContentError::DuplicateId { ref id, .. } if id == "goblins/scout"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_directories_are_not_followed() {
+        let dir = set_dir("symlinks");
+        fs::write(dir.join("goblins/goblin.json"), r#"{"name": "Goblin", "max_stamina": 5}"#).unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("goblins/loop")).unwrap();
+        std::os::unix::fs::symlink(dir.join("goblins/goblin.json"), dir.join("linked.json")).unwrap();
+        let monsters = load_set::<Monster>(&dir);
+        fs::remove_dir_all(&dir).ok();
+
+        let monsters = monsters.unwrap();
+        assert_eq!(monsters.keys().collect::<Vec<_>>(), vec!["goblins/goblin", "linked"]);
+    }
+
+    #[test]
+    fn test_yaml_and_toml_errors_have_positions() {
+        let yaml = parse_yaml::<Monster>("name: Goblin\nmax_stamina: lots\n", Path::new("goblin.yaml")).unwrap_err();
+        assert!(matches!(yaml, ContentError::Parse { line: 2, column: 14, .. }), "{}", yaml);
+        let toml = parse_toml::<Monster>("name = \"Goblin\"\nmax_stamina = \"lots\"\n", Path::new("goblin.toml")).unwrap_err();
+        assert!(matches!(toml, ContentError::Parse { line: 2, column: 15, .. }), "{}", toml);
+    }
+
+    #[test]
+    fn test_values_are_located_in_every_format() {
+        let json = "{\n  \"name\": \"Goblin\",\n  \"tiers\": [1, {\"damage\": \"1 + Q\"}]\n}";
+        assert_eq!(locate("json", json, "/tiers/1/damage"), Some((3, 33)));
+        let yaml = "name: Goblin\ntiers:\n  - 1\n  - damage: 1 + Q\n";
+        assert_eq!(locate("yaml", yaml, "/tiers/1/damage"), Some((4, 13)));
+        let toml = "name = \"Goblin\"\n[power_roll_1]\ndamage = \"1 + Q\"\n";
+        assert_eq!(locate("toml", toml, "/power_roll_1/damage"), Some((3, 10)));
+        assert_eq!(locate("json", json, "/missing"), None);
+    }
+}
@@ -0,0 +1,887 @@
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use crate::combat::TurnSide;
+use crate::entity::{EntityDefinition, EntityId, MovementModes, Size};
+use crate::error::ResolutionError;
+
+/// A square on the battle grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Position {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    pub fn offset(&self, dx: i32, dy: i32) -> Self {
+        Self { x: self.x + dx, y: self.y + dy }
+    }
+
+    /// Distance in squares. Diagonal steps count as a single square.
+    pub fn distance(&self, other: Position) -> i32 {
+        (self.x - other.x).abs().max((self.y - other.y).abs())
+    }
+
+    pub fn is_adjacent(&self, other: Position) -> bool {
+        self.distance(other) == 1
+    }
+}
+
+/// All squares of a cube area with its lowest corner at `origin`
+pub fn cube(origin: Position, side: i32) -> Vec<Position> {
+    let mut squares = Vec::with_capacity((side.max(0) * side.max(0)) as usize);
+    for y in 0..side {
+        for x in 0..side {
+            squares.push(origin.offset(x, y));
+        }
+    }
+    squares
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Terrain {
+    #[default]
+    Floor,
+    /// Costs an extra square of movement to enter
+    Difficult,
+    /// Costs an extra square of movement to enter without a swim speed
+    Water,
+    /// An obstacle that can't be entered
+    Wall,
+}
+
+/**
+ * The terrain of a battle map. Each square has a terrain type and an elevation in squares above the ground.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Grid {
+    width: i32,
+    height: i32,
+    elevation: Vec<i32>,
+    terrain: Vec<Terrain>,
+}
+
+impl Grid {
+    pub fn new(width: i32, height: i32) -> Self {
+        let width = width.max(0);
+        let height = height.max(0);
+        Self {
+            width,
+            height,
+            elevation: vec![0; (width * height) as usize],
+            terrain: vec![Terrain::Floor; (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn contains(&self, position: Position) -> bool {
+        position.x >= 0 && position.y >= 0 && position.x < self.width && position.y < self.height
+    }
+
+    fn index(&self, position: Position) -> Option<usize> {
+        if self.contains(position) {
+            Some((position.y * self.width + position.x) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Elevation of a square. Squares outside the grid are treated as ground level.
+    pub fn elevation(&self, position: Position) -> i32 {
+        self.index(position).map(|i| self.elevation[i]).unwrap_or(0)
+    }
+
+    pub fn set_elevation(&mut self, position: Position, elevation: i32) -> Result<(), ResolutionError> {
+        match self.index(position) {
+            Some(i) => {
+                self.elevation[i] = elevation;
+                Ok(())
+            }
+            None => Err(ResolutionError::OutsideGrid { position }),
+        }
+    }
+
+    /// Terrain of a square. Squares outside the grid are treated as walls.
+    pub fn terrain(&self, position: Position) -> Terrain {
+        self.index(position).map(|i| self.terrain[i]).unwrap_or(Terrain::Wall)
+    }
+
+    pub fn set_terrain(&mut self, position: Position, terrain: Terrain) -> Result<(), ResolutionError> {
+        match self.index(position) {
+            Some(i) => {
+                self.terrain[i] = terrain;
+                Ok(())
+            }
+            None => Err(ResolutionError::OutsideGrid { position }),
+        }
+    }
+
+    /// Whether a creature can stand in a square
+    pub fn is_open(&self, position: Position) -> bool {
+        self.terrain(position) != Terrain::Wall
+    }
+}
+
+/// Where an entity stands. `position` is the corner of its space with the lowest coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Placement {
+    pub position: Position,
+    /// Bottom of the creature's space, in squares above ground level
+    pub elevation: i32,
+    pub side: TurnSide,
+    pub size: Size,
+    pub modes: MovementModes,
+    /// True while the creature is airborne using its fly speed
+    pub flying: bool,
+}
+
+impl Placement {
+    /// The same creature standing somewhere else
+    pub fn at(&self, position: Position) -> Self {
+        Self { position, ..*self }
+    }
+
+    /// Corner of the space with the highest coordinates
+    pub fn far_corner(&self) -> Position {
+        let footprint = self.size.footprint();
+        self.position.offset(footprint - 1, footprint - 1)
+    }
+
+    pub fn squares(&self) -> Vec<Position> {
+        cube(self.position, self.size.footprint())
+    }
+
+    /// Top of the creature's space. A creature is as tall as it is wide.
+    pub fn top(&self) -> i32 {
+        self.elevation + self.size.footprint() - 1
+    }
+
+    /// Whether the space covers a square of the map at any height
+    pub fn occupies(&self, position: Position) -> bool {
+        let far = self.far_corner();
+        position.x >= self.position.x && position.x <= far.x && position.y >= self.position.y && position.y <= far.y
+    }
+
+    /// Distance between the nearest squares of two spaces, including height
+    pub fn distance_to(&self, other: &Placement) -> i32 {
+        let (far, other_far) = (self.far_corner(), other.far_corner());
+        let dx = (other.position.x - far.x).max(self.position.x - other_far.x).max(0);
+        let dy = (other.position.y - far.y).max(self.position.y - other_far.y).max(0);
+        let dz = (other.elevation - self.top()).max(self.elevation - other.top()).max(0);
+        dx.max(dy).max(dz)
+    }
+
+    pub fn overlaps(&self, other: &Placement) -> bool {
+        self.distance_to(other) == 0
+    }
+
+    pub fn is_adjacent_to(&self, other: &Placement) -> bool {
+        self.distance_to(other) == 1
+    }
+
+    /// Center of the space, doubled so that it stays on whole numbers
+    fn doubled_center(&self) -> (i32, i32) {
+        let footprint = self.size.footprint();
+        (2 * self.position.x + footprint - 1, 2 * self.position.y + footprint - 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Collision {
+    /// Ran into another creature. Both creatures take the damage.
+    Creature { damage: i32 },
+    /// Ran into a wall or the edge of the map.
+    Obstacle { damage: i32 },
+}
+
+/// Result of a push, pull or slide
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForcedMovementOutcome {
+    pub moved: i32,
+    pub final_position: Position,
+    pub collision: Option<Collision>,
+    /// The creature that was hit, if the collision was with a creature
+    pub collided_with: Option<EntityId>,
+    /// The drop to the ground at the end, if the target was moved off a ledge
+    pub fall: Option<FallOutcome>,
+}
+
+/// Result of a creature dropping to the ground
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FallOutcome {
+    pub distance: i32,
+    pub damage: i32,
+}
+
+/// Falling 2 or more squares deals 2 damage per square fallen, to a maximum of 50
+pub fn falling_damage(distance: i32) -> i32 {
+    if distance < 2 {
+        0
+    } else {
+        (distance * 2).min(50)
+    }
+}
+
+/// Dropping from one space down to another. A drop of a single square is just a step down; anything
+/// further is a fall.
+fn drop_between(from: &Placement, to: &Placement) -> Option<FallOutcome> {
+    let distance = from.elevation - to.elevation;
+    (distance >= 2).then(|| FallOutcome {
+        distance,
+        damage: falling_damage(distance),
+    })
+}
+
+/**
+ * The grid together with where each entity stands on it. Entities are keyed by ID, like in `CombatState`.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Battlefield {
+    grid: Grid,
+    placements: IndexMap<EntityId, Placement>,
+}
+
+impl Battlefield {
+    pub fn new(grid: Grid) -> Self {
+        Self {
+            grid,
+            placements: IndexMap::new(),
+        }
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn placements(&self) -> &IndexMap<EntityId, Placement> {
+        &self.placements
+    }
+
+    pub fn placement(&self, entity: EntityId) -> Option<&Placement> {
+        self.placements.get(&entity)
+    }
+
+    pub fn position(&self, entity: EntityId) -> Option<Position> {
+        self.placements.get(&entity).map(|p| p.position)
+    }
+
+    /// The entity whose space includes a square, if any
+    pub fn occupant(&self, position: Position) -> Option<EntityId> {
+        self.placements
+            .iter()
+            .find(|(_, placement)| placement.occupies(position))
+            .map(|(entity, _)| *entity)
+    }
+
+    /// Entities other than `entity` whose space overlaps the given space
+    pub(crate) fn overlapping(&self, entity: EntityId, placement: &Placement) -> Vec<EntityId> {
+        self.placements
+            .iter()
+            .filter(|(other, other_placement)| **other != entity && placement.overlaps(other_placement))
+            .map(|(other, _)| *other)
+            .collect()
+    }
+
+    /// Whether every square of a space is on the grid and free of obstacles
+    pub(crate) fn in_bounds(&self, placement: &Placement) -> bool {
+        placement.squares().iter().all(|square| self.grid.is_open(*square))
+    }
+
+    /// Whether a creature can move through a space: open squares, or for a burrower any square of the map,
+    /// as it tunnels through walls
+    fn can_pass(&self, placement: &Placement) -> bool {
+        if placement.modes.burrow && !placement.flying {
+            placement.squares().iter().all(|square| self.grid.contains(*square))
+        } else {
+            self.in_bounds(placement)
+        }
+    }
+
+    /// Height of the highest square of terrain under a space, where a creature on the ground stands
+    pub fn surface(&self, placement: &Placement) -> i32 {
+        placement
+            .squares()
+            .iter()
+            .map(|square| self.grid.elevation(*square))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Put a space at the right height: on the ground, or for a flier no lower than the ground
+    pub(crate) fn settle(&self, placement: Placement) -> Placement {
+        let surface = self.surface(&placement);
+        let elevation = if placement.flying { placement.elevation.max(surface) } else { surface };
+        Placement { elevation, ..placement }
+    }
+
+    /// Bottom of an entity's space, in squares above ground level
+    pub fn elevation_of(&self, entity: EntityId) -> Option<i32> {
+        self.placement(entity).map(|placement| placement.elevation)
+    }
+
+    /// Place a creature with the size and movement modes of its definition
+    pub fn place_entity(&mut self, entity: EntityId, side: TurnSide, definition: &EntityDefinition, position: Position) -> Result<(), ResolutionError> {
+        self.place(entity, side, definition.size, position)?;
+        if let Some(placement) = self.placements.get_mut(&entity) {
+            placement.modes = definition.movement_modes();
+        }
+        Ok(())
+    }
+
+    /// Give a creature already placed the size and movement modes of an updated definition, where it stands.
+    /// Fails, leaving it as it was, if it no longer fits there. A creature that can no longer fly lands.
+    pub fn redefine(&mut self, entity: EntityId, definition: &EntityDefinition) -> Result<(), ResolutionError> {
+        let placement = match self.placement(entity) {
+            Some(placement) => *placement,
+            None => return Err(ResolutionError::NotOnBattlefield { entity }),
+        };
+        let modes = definition.movement_modes();
+        let redefined = self.settle(Placement {
+            size: definition.size,
+            modes,
+            flying: placement.flying && modes.fly,
+            ..placement
+        });
+        if !self.in_bounds(&redefined) {
+            return Err(ResolutionError::DoesNotFit { entity, position: placement.position });
+        }
+        if let Some(occupant) = self.overlapping(entity, &redefined).first() {
+            return Err(ResolutionError::Occupied { position: placement.position, occupant: *occupant });
+        }
+        self.placements.insert(entity, redefined);
+        Ok(())
+    }
+
+    pub fn place(&mut self, entity: EntityId, side: TurnSide, size: Size, position: Position) -> Result<(), ResolutionError> {
+        let placement = self.settle(Placement {
+            position,
+            elevation: 0,
+            side,
+            size,
+            modes: MovementModes::default(),
+            flying: false,
+        });
+        if !self.in_bounds(&placement) {
+            return Err(ResolutionError::DoesNotFit { entity, position });
+        }
+        if let Some(occupant) = self.overlapping(entity, &placement).first() {
+            return Err(ResolutionError::Occupied { position, occupant: *occupant });
+        }
+        self.placements.insert(entity, placement);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, entity: EntityId) -> bool {
+        self.placements.shift_remove(&entity).is_some()
+    }
+
+    /// Entities on the same side as the given entity, excluding itself
+    pub fn allies_of(&self, entity: EntityId) -> Vec<EntityId> {
+        let side = match self.placement(entity) {
+            Some(placement) => placement.side,
+            None => return vec![],
+        };
+        self.placements
+            .iter()
+            .filter(|(other, placement)| **other != entity && placement.side == side)
+            .map(|(other, _)| *other)
+            .collect()
+    }
+
+    /// Entities whose space touches the given entity's space
+    pub fn adjacent_to(&self, entity: EntityId) -> Vec<EntityId> {
+        let placement = match self.placement(entity) {
+            Some(placement) => *placement,
+            None => return vec![],
+        };
+        self.placements
+            .iter()
+            .filter(|(other, other_placement)| **other != entity && placement.is_adjacent_to(other_placement))
+            .map(|(other, _)| *other)
+            .collect()
+    }
+
+    /// A creature can move through an ally's space, or through the space of a creature smaller than itself.
+    /// It can never end its movement in another creature's space.
+    pub fn can_move_through(&self, mover: EntityId, other: EntityId) -> bool {
+        match (self.placement(mover), self.placement(other)) {
+            (Some(mover), Some(other)) => mover.side == other.side || other.size < mover.size,
+            _ => true,
+        }
+    }
+
+    /// Check a single step of movement to an adjacent square, passing through creatures where allowed
+    pub(crate) fn check_step(&self, entity: EntityId, from: &Placement, to: Position) -> Result<Placement, ResolutionError> {
+        if !from.position.is_adjacent(to) {
+            return Err(ResolutionError::NotAdjacent { from: from.position, to });
+        }
+        let moved = self.settle(from.at(to));
+        if !self.can_pass(&moved) {
+            return Err(ResolutionError::DoesNotFit { entity, position: to });
+        }
+        if let Some(blocker) = self.overlapping(entity, &moved).into_iter().find(|other| !self.can_move_through(entity, *other)) {
+            return Err(ResolutionError::CannotPass { entity, blocker });
+        }
+        Ok(moved)
+    }
+
+    /// Check that an entity could move along a path, ending in an unoccupied space
+    pub fn validate_path(&self, entity: EntityId, path: &[Position]) -> Result<(), ResolutionError> {
+        self.walk_path(entity, path).map(|_| ())
+    }
+
+    /// Where an entity would end up after moving along a path
+    fn walk_path(&self, entity: EntityId, path: &[Position]) -> Result<Placement, ResolutionError> {
+        let mut current = match self.placement(entity) {
+            Some(placement) => *placement,
+            None => return Err(ResolutionError::NotOnBattlefield { entity }),
+        };
+        for step in path {
+            current = self.check_step(entity, &current, *step)?;
+        }
+        if !self.in_bounds(&current) {
+            return Err(ResolutionError::DoesNotFit { entity, position: current.position });
+        }
+        if let Some(occupant) = self.overlapping(entity, &current).first() {
+            return Err(ResolutionError::CannotEndIn { entity, occupant: *occupant });
+        }
+        Ok(current)
+    }
+
+    /// Move an entity a single square. Used to move step by step when something can interrupt the movement.
+    /// Returns the fall if the step takes a creature without a fly or climb speed off a ledge.
+    pub fn step(&mut self, entity: EntityId, to: Position) -> Result<Option<FallOutcome>, ResolutionError> {
+        let from = match self.placement(entity) {
+            Some(placement) => *placement,
+            None => return Err(ResolutionError::NotOnBattlefield { entity }),
+        };
+        let moved = self.check_step(entity, &from, to)?;
+        self.placements.insert(entity, moved);
+        Ok(Self::step_fall(&from, &moved))
+    }
+
+    /// Move an entity square by square along a path. Every step must be to an adjacent square.
+    /// Returns the falls taken stepping off ledges on the way.
+    pub fn move_along(&mut self, entity: EntityId, path: &[Position]) -> Result<Vec<FallOutcome>, ResolutionError> {
+        self.walk_path(entity, path)?;
+        let mut falls = vec![];
+        for step in path {
+            falls.extend(self.step(entity, *step)?);
+        }
+        Ok(falls)
+    }
+
+    /// Fliers stay aloft and climbers climb down, but anyone else stepping off a ledge falls
+    fn step_fall(from: &Placement, to: &Placement) -> Option<FallOutcome> {
+        if from.flying || to.modes.climb {
+            return None;
+        }
+        drop_between(from, to)
+    }
+
+    /// Take off, change altitude or land using a fly speed
+    pub fn fly(&mut self, entity: EntityId, elevation: i32) -> Result<(), ResolutionError> {
+        let placement = match self.placement(entity) {
+            Some(placement) => *placement,
+            None => return Err(ResolutionError::NotOnBattlefield { entity }),
+        };
+        if !placement.modes.fly {
+            return Err(ResolutionError::CannotFly { entity });
+        }
+        let surface = self.surface(&placement);
+        if elevation < surface {
+            return Err(ResolutionError::BelowGround { entity });
+        }
+        let moved = Placement { elevation, flying: elevation > surface, ..placement };
+        if let Some(occupant) = self.overlapping(entity, &moved).first() {
+            return Err(ResolutionError::CannotEndIn { entity, occupant: *occupant });
+        }
+        self.placements.insert(entity, moved);
+        Ok(())
+    }
+
+    /// Drop an entity to the ground beneath it
+    pub fn fall(&mut self, entity: EntityId) -> Result<FallOutcome, ResolutionError> {
+        let placement = match self.placement(entity) {
+            Some(placement) => *placement,
+            None => return Err(ResolutionError::NotOnBattlefield { entity }),
+        };
+        let landed = self.settle(Placement { flying: false, ..placement });
+        let distance = (placement.elevation - landed.elevation).max(0);
+        self.placements.insert(entity, landed);
+        Ok(FallOutcome {
+            distance,
+            damage: falling_damage(distance),
+        })
+    }
+
+    /// A flying creature knocked prone falls. A creature on the ground doesn't move.
+    pub fn knock_prone(&mut self, entity: EntityId) -> Result<FallOutcome, ResolutionError> {
+        self.fall(entity)
+    }
+
+    /// Remove a creature's ability to fly, such as when it is slowed or its wings are hurt. It falls if airborne.
+    pub fn lose_flight(&mut self, entity: EntityId) -> Result<FallOutcome, ResolutionError> {
+        match self.placements.get_mut(&entity) {
+            Some(placement) => placement.modes.fly = false,
+            None => return Err(ResolutionError::NotOnBattlefield { entity }),
+        }
+        self.fall(entity)
+    }
+
+    /**
+     * Move an entity to any unoccupied space within range, ignoring everything in between. The range counts
+     * the squares up or down to the ground at the destination as well as across. It comes from whatever
+     * teleports the entity: an ability's distance, or the creature's speed when it moves by teleporting.
+     */
+    pub fn teleport(&mut self, entity: EntityId, destination: Position, range: i32) -> Result<(), ResolutionError> {
+        let placement = match self.placement(entity) {
+            Some(placement) => *placement,
+            None => return Err(ResolutionError::NotOnBattlefield { entity }),
+        };
+        let moved = self.settle(Placement { flying: false, ..placement.at(destination) });
+        let distance = placement.position.distance(destination).max((moved.elevation - placement.elevation).abs());
+        if distance > range {
+            return Err(ResolutionError::OutOfRange { position: destination, range });
+        }
+        if !self.in_bounds(&moved) {
+            return Err(ResolutionError::DoesNotFit { entity, position: destination });
+        }
+        if let Some(occupant) = self.overlapping(entity, &moved).first() {
+            return Err(ResolutionError::Occupied { position: destination, occupant: *occupant });
+        }
+        self.placements.insert(entity, moved);
+        Ok(())
+    }
+
+    /// Entities within a distance of the given entity's space, such as the targets of a burst
+    pub fn within_distance(&self, entity: EntityId, distance: i32) -> Vec<EntityId> {
+        let placement = match self.placement(entity) {
+            Some(placement) => *placement,
+            None => return vec![],
+        };
+        self.placements
+            .iter()
+            .filter(|(other, other_placement)| **other != entity && placement.distance_to(other_placement) <= distance)
+            .map(|(other, _)| *other)
+            .collect()
+    }
+
+    /// Entities with any part of their space inside an area
+    pub fn in_area(&self, squares: &[Position]) -> Vec<EntityId> {
+        self.placements
+            .iter()
+            .filter(|(_, placement)| squares.iter().any(|square| placement.occupies(*square)))
+            .map(|(entity, _)| *entity)
+            .collect()
+    }
+
+    /// Direction pointing from the source's space towards the target's space
+    fn direction(&self, source: EntityId, target: EntityId) -> Result<(i32, i32), ResolutionError> {
+        let source_placement = self.placement(source).ok_or(ResolutionError::NotOnBattlefield { entity: source })?;
+        let target_placement = self.placement(target).ok_or(ResolutionError::NotOnBattlefield { entity: target })?;
+        let (sx, sy) = source_placement.doubled_center();
+        let (tx, ty) = target_placement.doubled_center();
+        Ok(((tx - sx).signum(), (ty - sy).signum()))
+    }
+
+    /// Push the target directly away from the source
+    pub fn push(&mut self, source: EntityId, target: EntityId, distance: i32) -> Result<ForcedMovementOutcome, ResolutionError> {
+        let direction = self.direction(source, target)?;
+        self.slide(target, direction, distance)
+    }
+
+    /// Pull the target directly towards the source. The pull ends early once the target reaches the source.
+    pub fn pull(&mut self, source: EntityId, target: EntityId, distance: i32) -> Result<ForcedMovementOutcome, ResolutionError> {
+        let (dx, dy) = self.direction(source, target)?;
+        let source_placement = *self.placement(source).ok_or(ResolutionError::NotOnBattlefield { entity: source })?;
+        let mut probe = self.placements[&target];
+        let mut pulled = 0;
+        while pulled < distance {
+            let next = self.settle(probe.at(probe.position.offset(-dx, -dy)));
+            if next.distance_to(&source_placement) == 0 || next.distance_to(&source_placement) > probe.distance_to(&source_placement) {
+                break;
+            }
+            probe = next;
+            pulled += 1;
+        }
+        self.slide(target, (-dx, -dy), pulled)
+    }
+
+    /// Move the target in a straight line. Forced movement stops at the first creature or obstacle in the way:
+    /// hitting a creature deals 1 damage per remaining square to both, hitting an obstacle deals 2 plus 1 per remaining square.
+    /// A target moved off a ledge keeps its height until the movement ends, then falls unless it is flying.
+    pub fn slide(&mut self, target: EntityId, direction: (i32, i32), distance: i32) -> Result<ForcedMovementOutcome, ResolutionError> {
+        let start = match self.placement(target) {
+            Some(placement) => *placement,
+            None => return Err(ResolutionError::NotOnBattlefield { entity: target }),
+        };
+        let (dx, dy) = (direction.0.signum(), direction.1.signum());
+
+        let mut current = start;
+        let mut moved = 0;
+        let mut collision = None;
+        let mut collided_with = None;
+        while moved < distance {
+            let next = self.settle(Placement { flying: true, ..current.at(current.position.offset(dx, dy)) });
+            let next = Placement { flying: current.flying, ..next };
+            let remaining = distance - moved;
+            if !self.in_bounds(&next) {
+                collision = Some(Collision::Obstacle { damage: 2 + remaining });
+                break;
+            }
+            if let Some(other) = self.overlapping(target, &next).first() {
+                collision = Some(Collision::Creature { damage: remaining });
+                collided_with = Some(*other);
+                break;
+            }
+            current = next;
+            moved += 1;
+        }
+
+        let landed = self.settle(current);
+        self.placements.insert(target, landed);
+        Ok(ForcedMovementOutcome {
+            moved,
+            final_position: landed.position,
+            collision,
+            collided_with,
+            fall: drop_between(&current, &landed),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::creature;
+
+    const PC1: EntityId = EntityId::new(1);
+    const PC2: EntityId = EntityId::new(2);
+    const NPC1: EntityId = EntityId::new(3);
+    const GOBLIN: EntityId = EntityId::new(4);
+    const OGRE: EntityId = EntityId::new(6);
+    const HARPY: EntityId = EntityId::new(7);
+    const DRAGON: EntityId = EntityId::new(8);
+
+    #[test]
+    fn test_distance_counts_diagonals_as_one() {
+        let origin = Position::new(0, 0);
+        assert_eq!(origin.distance(Position::new(1, 1)), 1);
+        assert_eq!(origin.distance(Position::new(3, -2)), 3);
+        assert!(origin.is_adjacent(Position::new(-1, 1)));
+        assert!(!origin.is_adjacent(origin));
+        assert!(!origin.is_adjacent(Position::new(2, 0)));
+    }
+
+    #[test]
+    fn test_place_rejects_occupied_and_out_of_bounds() {
+        let mut battlefield = Battlefield::new(Grid::new(5, 5));
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(1, 1)).unwrap();
+
+        assert!(battlefield.place(NPC1, TurnSide::NPC, Size::Medium, Position::new(1, 1)).is_err());
+        assert!(battlefield.place(NPC1, TurnSide::NPC, Size::Medium, Position::new(5, 0)).is_err());
+        assert!(battlefield.place(NPC1, TurnSide::NPC, Size::Medium, Position::new(2, 1)).is_ok());
+        assert_eq!(battlefield.occupant(Position::new(2, 1)), Some(NPC1));
+    }
+
+    #[test]
+    fn test_large_creatures_occupy_several_squares() {
+        let mut battlefield = Battlefield::new(Grid::new(6, 6));
+        battlefield.place(OGRE, TurnSide::NPC, Size::Squares(2), Position::new(2, 2)).unwrap();
+
+        assert_eq!(battlefield.occupant(Position::new(3, 3)), Some(OGRE));
+        assert!(battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(3, 2)).is_err());
+        // Doesn't fit past the edge of the grid
+        assert!(battlefield.place(DRAGON, TurnSide::NPC, Size::Squares(3), Position::new(4, 0)).is_err());
+
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(4, 4)).unwrap();
+        battlefield.place(PC2, TurnSide::PC, Size::Medium, Position::new(0, 0)).unwrap();
+        assert_eq!(battlefield.adjacent_to(OGRE), vec![PC1]);
+        assert_eq!(battlefield.within_distance(OGRE, 2).len(), 2);
+    }
+
+    #[test]
+    fn test_moving_through_smaller_creatures() {
+        let mut battlefield = Battlefield::new(Grid::new(6, 6));
+        battlefield.place(OGRE, TurnSide::NPC, Size::Large, Position::new(0, 0)).unwrap();
+        battlefield.place(GOBLIN, TurnSide::NPC, Size::Small, Position::new(0, 2)).unwrap();
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(1, 0)).unwrap();
+
+        // A hero can't move through a larger enemy
+        let path = [Position::new(0, 1), Position::new(0, 2), Position::new(0, 3)];
+        assert!(battlefield.move_along(PC1, &[Position::new(0, 0), Position::new(0, 1)]).is_err());
+        // But can move through a smaller one, as long as they don't stop there
+        assert!(battlefield.move_along(PC1, &path[..2]).is_err());
+        battlefield.move_along(PC1, &path).unwrap();
+        assert_eq!(battlefield.position(PC1), Some(Position::new(0, 3)));
+
+        // Allies can always pass through each other
+        battlefield.move_along(OGRE, &[Position::new(0, 1), Position::new(1, 2)]).unwrap();
+    }
+
+    #[test]
+    fn test_push_into_creature_damages_both() {
+        let mut battlefield = Battlefield::new(Grid::new(8, 3));
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(0, 1)).unwrap();
+        battlefield.place(GOBLIN, TurnSide::NPC, Size::Small, Position::new(1, 1)).unwrap();
+        battlefield.place(OGRE, TurnSide::NPC, Size::Squares(2), Position::new(4, 0)).unwrap();
+
+        let outcome = battlefield.push(PC1, GOBLIN, 5).unwrap();
+        assert_eq!(outcome.moved, 2);
+        assert_eq!(outcome.final_position, Position::new(3, 1));
+        assert_eq!(outcome.collision, Some(Collision::Creature { damage: 3 }));
+        assert_eq!(outcome.collided_with, Some(OGRE));
+    }
+
+    #[test]
+    fn test_slide_into_map_edge_is_an_obstacle() {
+        let mut battlefield = Battlefield::new(Grid::new(4, 4));
+        battlefield.place(GOBLIN, TurnSide::NPC, Size::Small, Position::new(1, 1)).unwrap();
+
+        let outcome = battlefield.slide(GOBLIN, (-1, 0), 3).unwrap();
+        assert_eq!(outcome.moved, 1);
+        assert_eq!(outcome.collision, Some(Collision::Obstacle { damage: 4 }));
+    }
+
+    #[test]
+    fn test_pushed_off_a_ledge_falls_at_the_end() {
+        let mut grid = Grid::new(6, 1);
+        for x in 0..3 {
+            grid.set_elevation(Position::new(x, 0), 3).unwrap();
+        }
+        grid.set_elevation(Position::new(4, 0), 1).unwrap();
+        let mut battlefield = Battlefield::new(grid);
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(1, 0)).unwrap();
+        battlefield.place(GOBLIN, TurnSide::NPC, Size::Small, Position::new(2, 0)).unwrap();
+
+        // Carried out over the drop, then down to the ground where the push ends
+        let outcome = battlefield.push(PC1, GOBLIN, 3).unwrap();
+        assert_eq!(outcome.final_position, Position::new(5, 0));
+        assert_eq!(outcome.fall, Some(FallOutcome { distance: 3, damage: 6 }));
+        assert_eq!(battlefield.elevation_of(GOBLIN), Some(0));
+
+        // Pushed only to the edge, nothing to fall from
+        let outcome = battlefield.push(GOBLIN, PC1, 1).unwrap();
+        assert_eq!(outcome.fall, None);
+    }
+
+    #[test]
+    fn test_pull_stops_adjacent() {
+        let mut battlefield = Battlefield::new(Grid::new(8, 3));
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(0, 1)).unwrap();
+        battlefield.place(GOBLIN, TurnSide::NPC, Size::Small, Position::new(4, 1)).unwrap();
+
+        let outcome = battlefield.pull(PC1, GOBLIN, 5).unwrap();
+        assert_eq!(outcome.final_position, Position::new(1, 1));
+        assert_eq!(outcome.collision, None);
+    }
+
+    fn flier() -> EntityDefinition {
+        EntityDefinition { movement: vec![crate::entity::MovementMode::Fly], ..creature("Harpy", 10, &[]) }
+    }
+
+    #[test]
+    fn test_fliers_pass_over_creatures() {
+        let mut battlefield = Battlefield::new(Grid::new(6, 6));
+        battlefield.place_entity(HARPY, TurnSide::NPC, &flier(), Position::new(0, 0)).unwrap();
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(1, 0)).unwrap();
+
+        assert!(battlefield.fly(PC1, 3).is_err());
+        battlefield.fly(HARPY, 3).unwrap();
+        assert!(!battlefield.placement(HARPY).unwrap().is_adjacent_to(battlefield.placement(PC1).unwrap()));
+
+        // Hovering over the hero is fine when high enough
+        battlefield.move_along(HARPY, &[Position::new(1, 0)]).unwrap();
+        assert_eq!(battlefield.elevation_of(HARPY), Some(3));
+    }
+
+    #[test]
+    fn test_falling_damage() {
+        let mut battlefield = Battlefield::new(Grid::new(6, 6));
+        battlefield.place_entity(HARPY, TurnSide::NPC, &flier(), Position::new(0, 0)).unwrap();
+
+        battlefield.fly(HARPY, 1).unwrap();
+        assert_eq!(battlefield.knock_prone(HARPY).unwrap(), FallOutcome { distance: 1, damage: 0 });
+
+        battlefield.fly(HARPY, 4).unwrap();
+        assert_eq!(battlefield.lose_flight(HARPY).unwrap(), FallOutcome { distance: 4, damage: 8 });
+        assert!(battlefield.fly(HARPY, 1).is_err());
+        assert_eq!(falling_damage(40), 50);
+    }
+
+    #[test]
+    fn test_walking_follows_terrain() {
+        let mut grid = Grid::new(4, 4);
+        grid.set_elevation(Position::new(1, 0), 2).unwrap();
+        let mut battlefield = Battlefield::new(grid);
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(0, 0)).unwrap();
+
+        battlefield.move_along(PC1, &[Position::new(1, 0)]).unwrap();
+        assert_eq!(battlefield.elevation_of(PC1), Some(2));
+
+        // Walking off the ledge is a fall
+        assert_eq!(battlefield.step(PC1, Position::new(2, 0)).unwrap(), Some(FallOutcome { distance: 2, damage: 4 }));
+        assert_eq!(battlefield.elevation_of(PC1), Some(0));
+    }
+
+    #[test]
+    fn test_teleport_ignores_path() {
+        let mut battlefield = Battlefield::new(Grid::new(8, 3));
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(0, 1)).unwrap();
+        battlefield.place(OGRE, TurnSide::NPC, Size::Squares(3), Position::new(1, 0)).unwrap();
+
+        assert!(battlefield.teleport(PC1, Position::new(7, 1), 5).is_err());
+        assert!(battlefield.teleport(PC1, Position::new(2, 1), 5).is_err());
+        battlefield.teleport(PC1, Position::new(5, 1), 5).unwrap();
+        assert_eq!(battlefield.position(PC1), Some(Position::new(5, 1)));
+    }
+
+    #[test]
+    fn test_teleport_range_counts_elevation() {
+        let mut grid = Grid::new(8, 3);
+        grid.set_elevation(Position::new(2, 1), 4).unwrap();
+        let mut battlefield = Battlefield::new(grid);
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(0, 1)).unwrap();
+
+        assert!(matches!(battlefield.teleport(PC1, Position::new(2, 1), 3), Err(ResolutionError::OutOfRange { .. })));
+        battlefield.teleport(PC1, Position::new(2, 1), 4).unwrap();
+        assert_eq!(battlefield.elevation_of(PC1), Some(4));
+    }
+
+    #[test]
+    fn test_walls_block_movement_and_stop_forced_movement() {
+        let mut grid = Grid::new(6, 3);
+        grid.set_terrain(Position::new(3, 1), Terrain::Wall).unwrap();
+        let mut battlefield = Battlefield::new(grid);
+        battlefield.place(GOBLIN, TurnSide::NPC, Size::Small, Position::new(1, 1)).unwrap();
+
+        assert!(battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(3, 1)).is_err());
+        assert!(battlefield.move_along(GOBLIN, &[Position::new(2, 1), Position::new(3, 1)]).is_err());
+
+        let outcome = battlefield.slide(GOBLIN, (1, 0), 4).unwrap();
+        assert_eq!(outcome.moved, 1);
+        assert_eq!(outcome.collision, Some(Collision::Obstacle { damage: 5 }));
+    }
+
+    #[test]
+    fn test_area_includes_partially_covered_creatures() {
+        let mut battlefield = Battlefield::new(Grid::new(8, 8));
+        battlefield.place(OGRE, TurnSide::NPC, Size::Squares(2), Position::new(3, 3)).unwrap();
+        battlefield.place(GOBLIN, TurnSide::NPC, Size::Small, Position::new(0, 0)).unwrap();
+
+        assert_eq!(battlefield.in_area(&cube(Position::new(1, 1), 3)), vec![OGRE]);
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+use crate::session::CombatSession;
+
+/// How many actions can be undone by default
+pub const DEFAULT_HISTORY_LIMIT: usize = 200;
+
+/**
+ * Undo and redo for a combat session. Frontends remember the session as it was before each action,
+ * and step back and forth through those snapshots.
+ */
+pub struct SessionHistory {
+    /// Oldest first, so the oldest snapshot can be dropped cheaply once the limit is reached
+    undo: VecDeque<CombatSession>,
+    redo: Vec<CombatSession>,
+    limit: usize,
+}
+
+impl SessionHistory {
+    pub fn new() -> Self {
+        Self::with_limit(DEFAULT_HISTORY_LIMIT)
+    }
+
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: vec![],
+            limit,
+        }
+    }
+
+    /// Remember the session as it was before an action. Anything that was undone can no longer be redone.
+    pub fn record(&mut self, before: CombatSession) {
+        self.push_undo(before);
+        self.redo.clear();
+    }
+
+    fn push_undo(&mut self, session: CombatSession) {
+        self.undo.push_back(session);
+        if self.undo.len() > self.limit {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Step back to the session before the last action. Returns `None` if there is nothing to undo.
+    pub fn undo(&mut self, current: &CombatSession) -> Option<CombatSession> {
+        let previous = self.undo.pop_back()?;
+        self.redo.push(current.clone());
+        Some(previous)
+    }
+
+    /// Reapply the last undone action. Returns `None` if there is nothing to redo.
+    pub fn redo(&mut self, current: &CombatSession) -> Option<CombatSession> {
+        let next = self.redo.pop()?;
+        self.push_undo(current.clone());
+        Some(next)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+impl Default for SessionHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use super::*;
+    use crate::combat::{CombatParameters, CombatState, TurnSide};
+    use crate::entity::EntityId;
+    use crate::testing::entity;
+
+    const PC1: EntityId = EntityId::new(1);
+    const NPC1: EntityId = EntityId::new(2);
+
+    fn session() -> CombatSession {
+        let params = CombatParameters::new(vec![PC1], vec![NPC1], TurnSide::PC);
+        let mut entities = IndexMap::new();
+        entities.insert(NPC1, entity("NPC1", 10));
+        CombatSession { state: CombatState::new(params), entities, next_id: EntityId::new(3), battlefield: None }
+    }
+
+    #[test]
+    fn test_undo_and_redo_across_turns_and_damage() {
+        let mut history = SessionHistory::new();
+        let start = session();
+
+        let mut started = start.clone();
+        started.state = started.state.start_turn(TurnSide::PC, PC1).unwrap().commit_turn().unwrap();
+        history.record(start.clone());
+
+        let mut damaged = started.clone();
+        damaged.entities[&NPC1].damage(4);
+        history.record(started.clone());
+
+        let undone = history.undo(&damaged).unwrap();
+        assert_eq!(undone.entities[&NPC1].current_health(), 10);
+        // Undo reaches past a committed turn
+        let undone = history.undo(&undone).unwrap();
+        assert!(undone.state.current_turn().is_none());
+        assert!(history.undo(&undone).is_none());
+
+        let redone = history.redo(&undone).unwrap();
+        assert!(redone.state.is_turn_committed());
+        let redone = history.redo(&redone).unwrap();
+        assert_eq!(redone.entities[&NPC1].current_health(), 6);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn test_new_action_clears_redo() {
+        let mut history = SessionHistory::new();
+        history.record(session());
+        history.undo(&session()).unwrap();
+        assert!(history.can_redo());
+        history.record(session());
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn test_history_is_limited() {
+        let mut history = SessionHistory::with_limit(2);
+        for _ in 0..3 {
+            history.record(session());
+        }
+        assert!(history.undo(&session()).is_some());
+        assert!(history.undo(&session()).is_some());
+        assert!(history.undo(&session()).is_none());
+    }
+}
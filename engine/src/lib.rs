@@ -1,30 +1,67 @@
+pub mod error;
 pub mod fs;
+pub mod content;
+pub mod schema;
 pub mod combat;
 pub mod dice;
 pub mod entity;
 pub mod ability;
+pub mod grid;
+pub mod edges;
+pub mod movement;
+pub mod map;
+pub mod pathfinding;
+pub mod session;
+pub mod encounter;
+pub mod events;
+pub mod history;
+pub mod actions;
+pub mod ai;
+pub mod simulation;
+pub mod skill_test;
+pub mod montage;
+#[cfg(test)]
+mod testing;
 
-pub use combat::{CombatParameters, CombatState, CurrentTurn, TurnSide};
-pub use dice::{rolld3s, rolld10s, power_roll};
-pub use entity::{Entity, EntityDefinition};
-pub use ability::{Ability, PowerRoll};
+pub use combat::{ActionType, CombatParameters, CombatState, CurrentTurn, TurnSide};
+pub use dice::{BaneState, Dice, DiceExpression, EdgeState, PowerRollResultTier, RollBreakdown, RollContext, RolledTerm, SURGES, Term, TierOdds, power_roll_odds, rolld3s, rolld10s, power_roll};
+pub use entity::{Characteristic, Characteristics, Entity, EntityDefinition, EntityId, MovementMode, MovementModes, Role, Size};
+pub use ability::{Ability, AbilityResolution, MAX_SURGES, PowerRoll};
+pub use grid::{Battlefield, FallOutcome, ForcedMovementOutcome, Grid, Placement, Position, Terrain};
+pub use map::MapDefinition;
+pub use content::{Content, ContentLibrary, ContentPack, ContentSet, PackManifest};
+pub use edges::{ComputedEdges, EdgeSource, compute_edges};
+pub use pathfinding::{MovementMap, ReachableSquare};
+pub use error::{CombatError, ContentError, ContentWarning, ExpressionError, MontageError, ResolutionError, SessionError, SimulationError};
+pub use events::{CombatEvent, EventLog};
+pub use history::SessionHistory;
+pub use encounter::{Encounter, EncounterPhase};
+pub use session::{CombatSession, SESSION_VERSION, load_session, save_session};
+pub use movement::{Movement, MovementKind, MovementProgress, OpportunityAttack, movement_allowance};
+pub use actions::{AbilityUse, CreatureMove, FreeStrike, MoveOutcome, move_creature, strike_odds, teleport_creature, use_ability};
+pub use ai::{LowestStamina, NearestTarget, PlannedStrike, Policy, RoleAware, TurnContext, TurnPlan, TurnReport, finish_planned_turn, play_turn, start_planned_move};
+pub use skill_test::{Difficulty, SKILL_BONUS, SkillTest, TestOutcome, TestResult};
+pub use montage::{DEFAULT_MONTAGE_ROUNDS, MONTAGE_PARTY_SIZE, Montage, MontageAttempt, MontageDefinition, MontageOutcome, montage_limits};
+pub use simulation::{CombatOutcome, CreatureReport, Distribution, Simulation, SimulationReport, Victory};
 
 #[cfg(test)]
 mod tests {
     
-    use crate::{EntityDefinition, combat::{CombatParameters, CombatState, TurnSide}, dice::rolld10s};
+    use crate::{EntityDefinition, entity::EntityId, combat::{CombatParameters, CombatState, TurnSide}, dice::rolld10s, testing::creature};
 
     #[test]
     fn test_combat_flow() {
-        let mut pcs: Vec<EntityDefinition> = Vec::new();
-        pcs.push(EntityDefinition { name: "PC1".to_string(), max_stamina: 10, abilities: vec![] });
-        pcs.push(EntityDefinition { name: "PC2".to_string(), max_stamina: 10, abilities: vec![] });
-        pcs.push(EntityDefinition { name: "PC3".to_string(), max_stamina: 10, abilities: vec![] });
+        let pcs: Vec<EntityDefinition> = vec![
+            creature("PC1", 10, &[]),
+            creature("PC2", 10, &[]),
+            creature("PC3", 10, &[]),
+        ];
     
-        let mut npcs: Vec<EntityDefinition> = Vec::new();
-        npcs.push(EntityDefinition { name: "NPC1".to_string(), max_stamina: 10, abilities: vec![] });
-        npcs.push(EntityDefinition { name: "NPC2".to_string(), max_stamina: 10, abilities: vec![] });
-        npcs.push(EntityDefinition { name: "NPC3".to_string(), max_stamina: 10, abilities: vec![] });
+        let npcs: Vec<EntityDefinition> = vec![
+            creature("NPC1", 10, &[]),
+            creature("NPC2", 10, &[]),
+            creature("NPC3", 10, &[]),
+        ];
     
         let starting_roll:i32 = rolld10s(1).iter().sum();
         let starting_side = if starting_roll > 5 {
@@ -34,8 +71,8 @@ mod tests {
         };
     
         let combat_parameters = CombatParameters::new(
-            pcs.iter().map(|pc| pc.name.clone()),
-            npcs.iter().map(|npc| npc.name.clone()),
+            (1..=pcs.len() as u32).map(EntityId::new),
+            (101..=100 + npcs.len() as u32).map(EntityId::new),
             starting_side,
         );
         
@@ -43,7 +80,7 @@ mod tests {
         println!("combat started: {:?}", combat);
         
         // Start PC1's turn
-        let combat = match combat.start_turn(TurnSide::PC, "PC1".to_string()) {
+        let combat = match combat.start_turn(TurnSide::PC, EntityId::new(1)) {
             Ok(new_state) => {
                 println!("PC1 started their turn");
                 new_state
@@ -69,7 +106,7 @@ mod tests {
         println!("combat state after ending PC1 turn: {:?}", combat);
         
         // Start NPC1's turn
-        let combat = match combat.start_turn(TurnSide::NPC, "NPC1".to_string()) {
+        let combat = match combat.start_turn(TurnSide::NPC, EntityId::new(101)) {
             Ok(new_state) => {
                 println!("NPC1 started their turn");
                 new_state
@@ -0,0 +1,200 @@
+use std::path::Path;
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use crate::combat::TurnSide;
+use crate::error::ContentError;
+use crate::content::ContentSet;
+use crate::schema::load_checked_set;
+use crate::grid::{Grid, Position, Terrain};
+
+/// Height of a single square of terrain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct SquareElevation {
+    pub x: i32,
+    pub y: i32,
+    pub elevation: i32,
+}
+
+/**
+ * A battle map drawn before a session. Loaded from `content/maps/`, either as JSON or as ASCII art in a `.map` file:
+ *
+ * - `.` floor
+ * - `#` wall
+ * - `~` difficult terrain
+ * - `w` water
+ * - `0`-`9` floor at that elevation
+ * - `H` a hero starting square, `M` a monster starting square
+ *
+ * An ASCII map may start with a `name: ...` line. Otherwise it is named after its file.
+ */
+#[derive(Debug, Clone, Deserialize, JsonSchema, PartialEq, Eq)]
+#[schemars(deny_unknown_fields)]
+pub struct MapDefinition {
+    pub name: String,
+    pub width: i32,
+    pub height: i32,
+    #[serde(default)]
+    pub walls: Vec<Position>,
+    #[serde(default)]
+    pub difficult_terrain: Vec<Position>,
+    /// Squares that cost extra to cross without a swim speed
+    #[serde(default)]
+    pub water: Vec<Position>,
+    #[serde(default)]
+    pub elevation: Vec<SquareElevation>,
+    #[serde(default)]
+    pub hero_start: Vec<Position>,
+    #[serde(default)]
+    pub monster_start: Vec<Position>,
+}
+
+impl MapDefinition {
+    /// Build the engine's grid for this map
+    pub fn to_grid(&self) -> Result<Grid, ContentError> {
+        let invalid = |source| ContentError::InvalidMap { name: self.name.clone(), source };
+        let mut grid = Grid::new(self.width, self.height);
+        for wall in &self.walls {
+            grid.set_terrain(*wall, Terrain::Wall).map_err(invalid)?;
+        }
+        for square in &self.difficult_terrain {
+            grid.set_terrain(*square, Terrain::Difficult).map_err(invalid)?;
+        }
+        for square in &self.water {
+            grid.set_terrain(*square, Terrain::Water).map_err(invalid)?;
+        }
+        for square in &self.elevation {
+            grid.set_elevation(Position::new(square.x, square.y), square.elevation).map_err(invalid)?;
+        }
+        Ok(grid)
+    }
+
+    /// Squares where a side's creatures start. Empty if the map doesn't define a zone.
+    pub fn start_zone(&self, side: TurnSide) -> &[Position] {
+        match side {
+            TurnSide::PC => &self.hero_start,
+            TurnSide::NPC => &self.monster_start,
+        }
+    }
+}
+
+pub fn parse_ascii_map(content: &str, path: &Path) -> Result<MapDefinition, ContentError> {
+    let mut name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut map = MapDefinition {
+        name: String::new(),
+        width: 0,
+        height: 0,
+        walls: vec![],
+        difficult_terrain: vec![],
+        water: vec![],
+        elevation: vec![],
+        hero_start: vec![],
+        monster_start: vec![],
+    };
+
+    let mut y = 0;
+    for (line_number, line) in content.lines().enumerate() {
+        if let Some(map_name) = line.strip_prefix("name:") {
+            name = map_name.trim().to_string();
+            continue;
+        }
+        let row = line.trim_end();
+        if row.is_empty() {
+            continue;
+        }
+
+        for (x, symbol) in row.chars().enumerate() {
+            let position = Position::new(x as i32, y);
+            match symbol {
+                '.' => {}
+                '#' => map.walls.push(position),
+                '~' => map.difficult_terrain.push(position),
+                'w' => map.water.push(position),
+                'H' => map.hero_start.push(position),
+                'M' => map.monster_start.push(position),
+                digit if digit.is_ascii_digit() => map.elevation.push(SquareElevation {
+                    x: position.x,
+                    y: position.y,
+                    elevation: digit.to_digit(10).unwrap_or(0) as i32,
+                }),
+                other => {
+                    return Err(ContentError::UnknownMapSymbol {
+                        path: path.to_path_buf(),
+                        line: line_number + 1,
+                        column: x + 1,
+                        symbol: other,
+                    })
+                }
+            }
+        }
+        map.width = map.width.max(row.chars().count() as i32);
+        y += 1;
+    }
+
+    map.name = name;
+    map.height = y;
+    Ok(map)
+}
+
+/// Load every map below a directory, from structured files checked against the schema or ASCII `.map` files
+pub fn load_maps(dir: &Path) -> Result<IndexMap<String, MapDefinition>, ContentError> {
+    load_checked_set(dir, ContentSet::Maps, &[("map", parse_ascii_map)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ascii_map() {
+        let content = "name: Ruined Bridge\n\
+                       H..#...\n\
+                       H.~#.2M\n\
+                       w.~..2M\n";
+        let map = parse_ascii_map(content, Path::new("bridge.map")).unwrap();
+        assert_eq!(map.name, "Ruined Bridge");
+        assert_eq!((map.width, map.height), (7, 3));
+        assert_eq!(map.start_zone(TurnSide::PC), &[Position::new(0, 0), Position::new(0, 1)]);
+        assert_eq!(map.start_zone(TurnSide::NPC).len(), 2);
+
+        let grid = map.to_grid().unwrap();
+        assert_eq!(grid.terrain(Position::new(3, 1)), Terrain::Wall);
+        assert_eq!(grid.terrain(Position::new(2, 2)), Terrain::Difficult);
+        assert_eq!(grid.terrain(Position::new(0, 2)), Terrain::Water);
+        assert_eq!(grid.elevation(Position::new(5, 2)), 2);
+        assert_eq!(grid.terrain(Position::new(6, 2)), Terrain::Floor);
+    }
+
+    #[test]
+    fn test_ascii_map_named_after_file() {
+        let map = parse_ascii_map("...\n...\n", Path::new("content/maps/cave.map")).unwrap();
+        assert_eq!(map.name, "cave");
+        assert!(map.hero_start.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_symbol_reports_position() {
+        let error = parse_ascii_map("...\n.X.\n", Path::new("cave.map")).unwrap_err();
+        assert!(matches!(error, ContentError::UnknownMapSymbol { line: 2, column: 2, symbol: 'X', .. }));
+        assert!(error.to_string().contains("line 2, column 2"));
+    }
+
+    #[test]
+    fn test_load_bundled_maps() {
+        let maps = load_maps(Path::new("../content/maps")).unwrap();
+        assert!(!maps.is_empty());
+        for map in maps.values() {
+            assert!(map.to_grid().is_ok(), "map {} should build a grid", map.name);
+        }
+    }
+
+    #[test]
+    fn test_json_map_out_of_bounds_wall() {
+        let map: MapDefinition = serde_json::from_str(r#"{"name": "Tiny", "width": 2, "height": 2, "walls": [{"x": 4, "y": 0}]}"#).unwrap();
+        assert!(map.to_grid().is_err());
+    }
+}
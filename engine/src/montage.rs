@@ -0,0 +1,299 @@
+use std::fmt;
+use indexmap::IndexSet;
+use serde::{Deserialize, Serialize};
+use crate::dice::{BaneState, Dice, EdgeState};
+use crate::entity::{EntityDefinition, EntityId};
+use crate::error::MontageError;
+use crate::skill_test::{Difficulty, SkillTest, TestResult};
+
+/// Rounds a montage lasts unless stated
+pub const DEFAULT_MONTAGE_ROUNDS: u32 = 2;
+
+/// Party size the montage difficulty table is written for
+pub const MONTAGE_PARTY_SIZE: u32 = 5;
+
+/**
+ * Success and failure limits for a party, from the montage difficulty table: 5 and 5 for an easy montage,
+ * 6 and 4 for a medium one and 7 and 3 for a hard one, for five heroes. Each hero more or fewer raises or
+ * lowers both limits by one, to no less than 1.
+ */
+pub fn montage_limits(difficulty: Difficulty, heroes: u32) -> (u32, u32) {
+    let (success_limit, failure_limit): (i64, i64) = match difficulty {
+        Difficulty::Easy => (5, 5),
+        Difficulty::Medium => (6, 4),
+        Difficulty::Hard => (7, 3),
+    };
+    let adjustment = heroes as i64 - MONTAGE_PARTY_SIZE as i64;
+    let limit = |base: i64| (base + adjustment).max(1) as u32;
+    (limit(success_limit), limit(failure_limit))
+}
+
+/// A group challenge: how hard it is, how many successes win it and failures lose it, and how long it lasts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MontageDefinition {
+    pub name: String,
+    pub difficulty: Difficulty,
+    pub success_limit: u32,
+    pub failure_limit: u32,
+    pub rounds: u32,
+}
+
+impl MontageDefinition {
+    pub fn new(name: &str, difficulty: Difficulty, success_limit: u32, failure_limit: u32) -> Self {
+        Self { name: name.to_string(), difficulty, success_limit, failure_limit, rounds: DEFAULT_MONTAGE_ROUNDS }
+    }
+
+    /// A montage with the limits the difficulty table gives for a party of this size
+    pub fn for_party(name: &str, difficulty: Difficulty, heroes: u32) -> Self {
+        let (success_limit, failure_limit) = montage_limits(difficulty, heroes);
+        Self::new(name, difficulty, success_limit, failure_limit)
+    }
+
+    pub fn with_rounds(mut self, rounds: u32) -> Self {
+        self.rounds = rounds;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MontageOutcome {
+    TotalSuccess,
+    PartialSuccess,
+    Failure,
+}
+
+impl fmt::Display for MontageOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            MontageOutcome::TotalSuccess => "total success",
+            MontageOutcome::PartialSuccess => "partial success",
+            MontageOutcome::Failure => "failure",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// A test a hero made during a montage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MontageAttempt {
+    pub round: u32,
+    pub hero: EntityId,
+    pub test: SkillTest,
+    pub result: TestResult,
+}
+
+/**
+ * A montage test being played out. Each round every participating hero makes one test or sits it out.
+ * Every success, with or without a consequence, counts toward the success limit, and every failure toward
+ * the failure limit. Reaching the success limit is a total success. Otherwise the montage ends when the
+ * failure limit is reached or the rounds run out, as a partial success if the heroes have at least two
+ * more successes than failures, and a failure if not.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Montage {
+    definition: MontageDefinition,
+    heroes: IndexSet<EntityId>,
+    round: u32,
+    /// Heroes who have tested or sat out this round
+    acted: IndexSet<EntityId>,
+    attempts: Vec<MontageAttempt>,
+    outcome: Option<MontageOutcome>,
+}
+
+impl Montage {
+    pub fn new(definition: MontageDefinition, heroes: impl IntoIterator<Item = EntityId>) -> Result<Self, MontageError> {
+        if definition.success_limit == 0 || definition.failure_limit == 0 || definition.rounds == 0 {
+            return Err(MontageError::InvalidLimits);
+        }
+        let heroes: IndexSet<EntityId> = heroes.into_iter().collect();
+        if heroes.is_empty() {
+            return Err(MontageError::NoHeroes);
+        }
+        Ok(Self { definition, heroes, round: 1, acted: IndexSet::new(), attempts: vec![], outcome: None })
+    }
+
+    pub fn definition(&self) -> &MontageDefinition {
+        &self.definition
+    }
+
+    pub fn heroes(&self) -> &IndexSet<EntityId> {
+        &self.heroes
+    }
+
+    /// The current round, counting from 1
+    pub fn round(&self) -> u32 {
+        self.round
+    }
+
+    pub fn attempts(&self) -> &[MontageAttempt] {
+        &self.attempts
+    }
+
+    /// How the montage ended, once it has
+    pub fn outcome(&self) -> Option<MontageOutcome> {
+        self.outcome
+    }
+
+    pub fn successes(&self) -> u32 {
+        self.attempts.iter().filter(|attempt| attempt.result.outcome.is_success()).count() as u32
+    }
+
+    pub fn failures(&self) -> u32 {
+        self.attempts.len() as u32 - self.successes()
+    }
+
+    /// Heroes who still have to test or sit out this round
+    pub fn waiting(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.heroes.iter().copied().filter(|hero| !self.acted.contains(hero))
+    }
+
+    /// Record a test a hero made, resolved elsewhere, and end the round or the montage if that finishes it
+    pub fn record(&mut self, hero: EntityId, test: SkillTest, result: TestResult) -> Result<&MontageAttempt, MontageError> {
+        self.act(hero)?;
+        self.attempts.push(MontageAttempt { round: self.round, hero, test, result });
+        self.advance();
+        Ok(self.attempts.last().expect("an attempt was just recorded"))
+    }
+
+    /// Roll a test for a hero and record it. The test's own difficulty applies, usually the montage's.
+    pub fn attempt(
+        &mut self,
+        dice: &mut Dice,
+        hero: EntityId,
+        definition: &EntityDefinition,
+        test: SkillTest,
+    ) -> Result<&MontageAttempt, MontageError> {
+        self.check(hero)?;
+        let result = test.resolve(dice, definition, EdgeState::None, BaneState::None);
+        self.record(hero, test, result)
+    }
+
+    /// Let a hero sit out this round without testing
+    pub fn pass(&mut self, hero: EntityId) -> Result<(), MontageError> {
+        self.act(hero)?;
+        self.advance();
+        Ok(())
+    }
+
+    fn check(&self, hero: EntityId) -> Result<(), MontageError> {
+        if self.outcome.is_some() {
+            return Err(MontageError::Finished);
+        }
+        if !self.heroes.contains(&hero) {
+            return Err(MontageError::NotParticipating { hero });
+        }
+        if self.acted.contains(&hero) {
+            return Err(MontageError::AlreadyActed { hero, round: self.round });
+        }
+        Ok(())
+    }
+
+    fn act(&mut self, hero: EntityId) -> Result<(), MontageError> {
+        self.check(hero)?;
+        self.acted.insert(hero);
+        Ok(())
+    }
+
+    fn advance(&mut self) {
+        let (successes, failures) = (self.successes(), self.failures());
+        if successes >= self.definition.success_limit {
+            self.outcome = Some(MontageOutcome::TotalSuccess);
+            return;
+        }
+        if self.waiting().next().is_none() {
+            self.round += 1;
+            self.acted.clear();
+        }
+        if failures >= self.definition.failure_limit || self.round > self.definition.rounds {
+            self.round = self.round.min(self.definition.rounds);
+            self.outcome = Some(if successes >= failures + 2 { MontageOutcome::PartialSuccess } else { MontageOutcome::Failure });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dice::PowerRollResultTier;
+    use crate::entity::{Characteristic, Characteristics};
+    use crate::skill_test::TestOutcome;
+    use crate::testing::creature;
+
+    const ROGUE: EntityId = EntityId::new(1);
+    const FIGHTER: EntityId = EntityId::new(2);
+
+    fn result(outcome: TestOutcome) -> TestResult {
+        TestResult { tier: PowerRollResultTier::Tier2, bonus: 0, skilled: false, outcome }
+    }
+
+    fn montage(success_limit: u32, failure_limit: u32) -> Montage {
+        let definition = MontageDefinition::new("Escape the collapse", Difficulty::Medium, success_limit, failure_limit);
+        Montage::new(definition, [ROGUE, FIGHTER]).unwrap()
+    }
+
+    fn test() -> SkillTest {
+        SkillTest::new(Characteristic::Agility, Difficulty::Medium)
+    }
+
+    #[test]
+    fn test_reaching_the_success_limit_is_a_total_success() {
+        let mut montage = montage(3, 3);
+        montage.record(ROGUE, test(), result(TestOutcome::Success)).unwrap();
+        montage.record(FIGHTER, test(), result(TestOutcome::SuccessWithConsequence)).unwrap();
+        assert_eq!(montage.round(), 2);
+        assert_eq!(montage.outcome(), None);
+        montage.record(FIGHTER, test(), result(TestOutcome::Success)).unwrap();
+        assert_eq!(montage.outcome(), Some(MontageOutcome::TotalSuccess));
+        assert_eq!(montage.record(ROGUE, test(), result(TestOutcome::Success)).unwrap_err(), MontageError::Finished);
+    }
+
+    #[test]
+    fn test_running_out_of_rounds_can_still_partly_succeed() {
+        let mut montage = montage(5, 5);
+        montage.record(ROGUE, test(), result(TestOutcome::Success)).unwrap();
+        montage.record(FIGHTER, test(), result(TestOutcome::Success)).unwrap();
+        montage.record(ROGUE, test(), result(TestOutcome::Success)).unwrap();
+        montage.record(FIGHTER, test(), result(TestOutcome::Failure)).unwrap();
+        assert_eq!((montage.successes(), montage.failures()), (3, 1));
+        assert_eq!(montage.outcome(), Some(MontageOutcome::PartialSuccess));
+    }
+
+    #[test]
+    fn test_reaching_the_failure_limit_ends_it() {
+        let mut montage = montage(5, 2);
+        montage.record(ROGUE, test(), result(TestOutcome::FailureWithConsequence)).unwrap();
+        montage.pass(FIGHTER).unwrap();
+        montage.record(ROGUE, test(), result(TestOutcome::Failure)).unwrap();
+        assert_eq!(montage.outcome(), Some(MontageOutcome::Failure));
+        assert_eq!(montage.round(), 2);
+    }
+
+    #[test]
+    fn test_each_hero_tests_once_a_round() {
+        let mut montage = montage(5, 5);
+        let rogue = EntityDefinition { characteristics: Characteristics { agility: 2, ..Characteristics::default() }, ..creature("Rogue", 10, &[]) };
+        montage.attempt(&mut Dice::seeded(1), ROGUE, &rogue, test()).unwrap();
+        assert_eq!(
+            montage.record(ROGUE, test(), result(TestOutcome::Success)).unwrap_err(),
+            MontageError::AlreadyActed { hero: ROGUE, round: 1 }
+        );
+        let stranger = EntityId::new(9);
+        assert_eq!(montage.pass(stranger), Err(MontageError::NotParticipating { hero: stranger }));
+        assert_eq!(montage.waiting().collect::<Vec<_>>(), vec![FIGHTER]);
+        let zero = MontageDefinition::new("Nothing", Difficulty::Easy, 0, 1);
+        assert_eq!(Montage::new(zero, [ROGUE]), Err(MontageError::InvalidLimits));
+        let nobody = MontageDefinition::new("Nobody", Difficulty::Easy, 1, 1);
+        assert_eq!(Montage::new(nobody, []), Err(MontageError::NoHeroes));
+    }
+
+    #[test]
+    fn test_limits_follow_the_difficulty_table() {
+        assert_eq!(montage_limits(Difficulty::Medium, 5), (6, 4));
+        assert_eq!(montage_limits(Difficulty::Easy, 4), (4, 4));
+        assert_eq!(montage_limits(Difficulty::Hard, 6), (8, 4));
+        assert_eq!(montage_limits(Difficulty::Hard, 1), (3, 1));
+        let definition = MontageDefinition::for_party("Chase", Difficulty::Hard, 3);
+        assert_eq!((definition.success_limit, definition.failure_limit), (5, 1));
+    }
+}
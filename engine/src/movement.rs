@@ -0,0 +1,249 @@
+use std::collections::HashSet;
+use indexmap::IndexMap;
+use crate::error::ResolutionError;
+use crate::ability::{Ability, AbilityResolution, MELEE_FREE_STRIKE};
+use crate::dice::{BaneState, Dice, EdgeState, RollContext};
+use crate::edges::compute_edges;
+use crate::entity::EntityId;
+use crate::grid::{Battlefield, FallOutcome, Placement, Position};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovementKind {
+    Move,
+    /// Shifting never provokes opportunity attacks
+    Shift,
+}
+
+/// How far a creature may go: its speed when moving, or a single square when shifting to disengage
+pub fn movement_allowance(speed: i32, kind: MovementKind) -> i32 {
+    match kind {
+        MovementKind::Move => speed,
+        MovementKind::Shift => 1,
+    }
+}
+
+/// An enemy who may make a free strike against a creature moving away from them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpportunityAttack {
+    pub attacker: EntityId,
+    pub target: EntityId,
+}
+
+/// Enemies adjacent to the mover who would no longer be adjacent after it steps to `to`
+pub fn opportunity_attackers(battlefield: &Battlefield, mover: EntityId, to: Position, kind: MovementKind) -> Vec<EntityId> {
+    if kind == MovementKind::Shift {
+        return vec![];
+    }
+    let from = match battlefield.placement(mover) {
+        Some(placement) => *placement,
+        None => return vec![],
+    };
+    let destination = battlefield.settle(from.at(to));
+    opportunity_attackers_between(battlefield, mover, &from, &destination)
+}
+
+/// Enemies who would get a free strike as the mover steps between two spaces
+pub(crate) fn opportunity_attackers_between(battlefield: &Battlefield, mover: EntityId, from: &Placement, destination: &Placement) -> Vec<EntityId> {
+    battlefield
+        .placements()
+        .iter()
+        .filter(|(entity, placement)| {
+            **entity != mover
+                && placement.side != from.side
+                && placement.is_adjacent_to(from)
+                && !placement.is_adjacent_to(destination)
+        })
+        .map(|(entity, _)| *entity)
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MovementProgress {
+    /// These enemies may make a free strike before the mover leaves its square.
+    /// Resolve or decline them, then advance again.
+    OpportunityAttacks(Vec<OpportunityAttack>),
+    Moved(Position),
+    /// Stepped off a ledge to this square and fell. The damage is left for the caller to apply.
+    Fell(Position, FallOutcome),
+    Finished,
+}
+
+/**
+ * A creature moving along a path one square at a time, pausing whenever it provokes opportunity attacks.
+ */
+#[derive(Debug, Clone)]
+pub struct Movement {
+    mover: EntityId,
+    path: Vec<Position>,
+    kind: MovementKind,
+    next_step: usize,
+    offered: bool,
+    struck_by: HashSet<EntityId>,
+}
+
+impl Movement {
+    /// Start moving along a path costing no more than `allowance` squares of movement. The whole path is
+    /// checked up front.
+    pub fn start(battlefield: &Battlefield, mover: EntityId, path: Vec<Position>, kind: MovementKind, allowance: i32) -> Result<Self, ResolutionError> {
+        battlefield.validate_path(mover, &path)?;
+        let cost = battlefield.path_cost(mover, &path)?;
+        if cost > allowance {
+            return Err(ResolutionError::TooFar { entity: mover, cost, allowance });
+        }
+        Ok(Self {
+            mover,
+            path,
+            kind,
+            next_step: 0,
+            offered: false,
+            struck_by: HashSet::new(),
+        })
+    }
+
+    pub fn mover(&self) -> EntityId {
+        self.mover
+    }
+
+    pub fn kind(&self) -> MovementKind {
+        self.kind
+    }
+
+    /// Squares still to be moved through
+    pub fn remaining(&self) -> &[Position] {
+        &self.path[self.next_step..]
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_step >= self.path.len()
+    }
+
+    /// End the movement where the mover currently stands, for example when a free strike knocks them out
+    pub fn stop(&mut self) {
+        self.next_step = self.path.len();
+    }
+
+    pub fn advance(&mut self, battlefield: &mut Battlefield) -> Result<MovementProgress, ResolutionError> {
+        if self.is_finished() {
+            return Ok(MovementProgress::Finished);
+        }
+        let next = self.path[self.next_step];
+
+        // Offer the free strikes before the mover leaves its square. Each enemy gets at most one per movement.
+        if !self.offered {
+            self.offered = true;
+            let attacks: Vec<OpportunityAttack> = opportunity_attackers(battlefield, self.mover, next, self.kind)
+                .into_iter()
+                .filter(|attacker| !self.struck_by.contains(attacker))
+                .map(|attacker| OpportunityAttack { attacker, target: self.mover })
+                .collect();
+            if !attacks.is_empty() {
+                self.struck_by.extend(attacks.iter().map(|attack| attack.attacker));
+                return Ok(MovementProgress::OpportunityAttacks(attacks));
+            }
+        }
+
+        let fall = battlefield.step(self.mover, next)?;
+        self.next_step += 1;
+        self.offered = false;
+        Ok(match fall {
+            Some(fall) => MovementProgress::Fell(next, fall),
+            None => MovementProgress::Moved(next),
+        })
+    }
+}
+
+/// Resolve an opportunity attack with the melee free strike. The damage is left for the caller to
+/// apply, such as through `Encounter::damage`.
+/// Edges from flanking and high ground apply as for any other melee strike, and the damage rolls with
+/// the attacker's context.
+pub fn resolve_opportunity_attack(
+    battlefield: &Battlefield,
+    abilities: &IndexMap<String, Ability>,
+    attack: &OpportunityAttack,
+    context: &RollContext,
+    dice: &mut Dice,
+) -> Result<AbilityResolution, ResolutionError> {
+    let free_strike = match abilities.get(MELEE_FREE_STRIKE) {
+        Some(ability) => ability,
+        None => return Err(ResolutionError::AbilityNotFound { name: MELEE_FREE_STRIKE.to_string() }),
+    };
+    let edges = compute_edges(battlefield, attack.attacker, attack.target, true, EdgeState::None)?;
+    Ok(free_strike.resolve(dice, context, edges.edge, BaneState::None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::TurnSide;
+    use crate::entity::Size;
+    use crate::grid::Grid;
+
+    const PC1: EntityId = EntityId::new(1);
+    const PC2: EntityId = EntityId::new(2);
+    const GOBLIN: EntityId = EntityId::new(4);
+
+    fn battlefield() -> Battlefield {
+        let mut battlefield = Battlefield::new(Grid::new(8, 8));
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(2, 2)).unwrap();
+        battlefield.place(GOBLIN, TurnSide::NPC, Size::Small, Position::new(3, 2)).unwrap();
+        battlefield
+    }
+
+    #[test]
+    fn test_moving_away_provokes() {
+        let battlefield = battlefield();
+        // Still adjacent after the step
+        assert!(opportunity_attackers(&battlefield, PC1, Position::new(2, 3), MovementKind::Move).is_empty());
+        // Leaves adjacency
+        assert_eq!(
+            opportunity_attackers(&battlefield, PC1, Position::new(1, 2), MovementKind::Move),
+            vec![GOBLIN]
+        );
+        // Shifting never provokes
+        assert!(opportunity_attackers(&battlefield, PC1, Position::new(1, 2), MovementKind::Shift).is_empty());
+    }
+
+    #[test]
+    fn test_allies_do_not_provoke() {
+        let mut battlefield = battlefield();
+        battlefield.place(PC2, TurnSide::PC, Size::Medium, Position::new(2, 1)).unwrap();
+        assert_eq!(
+            opportunity_attackers(&battlefield, PC1, Position::new(1, 3), MovementKind::Move),
+            vec![GOBLIN]
+        );
+    }
+
+    #[test]
+    fn test_movement_pauses_for_opportunity_attacks() {
+        let mut battlefield = battlefield();
+        let path = vec![Position::new(2, 3), Position::new(1, 4), Position::new(0, 5)];
+        assert_eq!(
+            Movement::start(&battlefield, PC1, path.clone(), MovementKind::Move, 2).unwrap_err(),
+            ResolutionError::TooFar { entity: PC1, cost: 3, allowance: 2 }
+        );
+        let mut movement = Movement::start(&battlefield, PC1, path, MovementKind::Move, 3).unwrap();
+
+        assert_eq!(movement.advance(&mut battlefield).unwrap(), MovementProgress::Moved(Position::new(2, 3)));
+        assert_eq!(
+            movement.advance(&mut battlefield).unwrap(),
+            MovementProgress::OpportunityAttacks(vec![OpportunityAttack { attacker: GOBLIN, target: PC1 }])
+        );
+        // The mover hasn't left their square until the strike is resolved
+        assert_eq!(battlefield.position(PC1), Some(Position::new(2, 3)));
+        assert_eq!(movement.advance(&mut battlefield).unwrap(), MovementProgress::Moved(Position::new(1, 4)));
+        assert_eq!(movement.advance(&mut battlefield).unwrap(), MovementProgress::Moved(Position::new(0, 5)));
+        assert_eq!(movement.advance(&mut battlefield).unwrap(), MovementProgress::Finished);
+    }
+
+    #[test]
+    fn test_stopped_movement_stays_put() {
+        let mut battlefield = battlefield();
+        let path = vec![Position::new(1, 2), Position::new(0, 2)];
+        let mut movement = Movement::start(&battlefield, PC1, path, MovementKind::Move, 6).unwrap();
+
+        assert!(matches!(movement.advance(&mut battlefield).unwrap(), MovementProgress::OpportunityAttacks(_)));
+        movement.stop();
+        assert_eq!(movement.advance(&mut battlefield).unwrap(), MovementProgress::Finished);
+        assert_eq!(battlefield.position(PC1), Some(Position::new(2, 2)));
+    }
+}
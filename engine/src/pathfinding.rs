@@ -0,0 +1,390 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use indexmap::IndexMap;
+use crate::entity::EntityId;
+use crate::error::ResolutionError;
+use crate::grid::{Battlefield, Placement, Position, Terrain};
+use crate::movement::{opportunity_attackers_between, MovementKind};
+
+/// A square a creature can reach, and what the cheapest way there costs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReachableSquare {
+    pub position: Position,
+    /// Squares of movement spent getting here
+    pub cost: i32,
+    /// Enemies who get a free strike along the way
+    pub provokes: Vec<EntityId>,
+    /// False for squares the creature can only pass through, such as an ally's space
+    pub can_end: bool,
+    step: usize,
+}
+
+/**
+ * Every square a creature can reach from where it stands, found by a cheapest-first search.
+ * Ties in cost are broken in favour of paths that provoke fewer opportunity attacks.
+ * Routes that provoke different enemies are searched separately, since the enemies a
+ * route has already provoked can't strike again further along it.
+ */
+#[derive(Debug, Clone)]
+pub struct MovementMap {
+    mover: EntityId,
+    origin: Position,
+    squares: IndexMap<Position, ReachableSquare>,
+    /// Every step searched: where it ends and the step before it
+    steps: Vec<(Position, Option<usize>)>,
+}
+
+impl MovementMap {
+    pub fn mover(&self) -> EntityId {
+        self.mover
+    }
+
+    pub fn origin(&self) -> Position {
+        self.origin
+    }
+
+    pub fn get(&self, position: Position) -> Option<&ReachableSquare> {
+        self.squares.get(&position)
+    }
+
+    pub fn cost(&self, position: Position) -> Option<i32> {
+        self.get(position).map(|square| square.cost)
+    }
+
+    pub fn can_end_at(&self, position: Position) -> bool {
+        self.get(position).is_some_and(|square| square.can_end)
+    }
+
+    /// Squares the creature can end its movement in, cheapest first. Doesn't include where it stands.
+    pub fn destinations(&self) -> Vec<&ReachableSquare> {
+        self.squares
+            .values()
+            .filter(|square| square.can_end && square.position != self.origin)
+            .collect()
+    }
+
+    /// The cheapest path to a square, not including the starting square
+    pub fn path_to(&self, destination: Position) -> Option<Vec<Position>> {
+        if !self.can_end_at(destination) {
+            return None;
+        }
+        let mut path = vec![];
+        let mut step = self.squares.get(&destination).map(|square| square.step);
+        while let Some((position, Some(previous))) = step.map(|index| self.steps[index]) {
+            path.push(position);
+            step = Some(previous);
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+/// A way to a square while searching: placement on arrival, enemies provoked, and the step before it
+type Candidate = (Placement, Vec<EntityId>, Option<usize>);
+
+/// A square together with the enemies provoked getting there, sorted
+type SearchKey = (Position, Vec<EntityId>);
+
+fn search_key(position: Position, provokes: &[EntityId]) -> SearchKey {
+    let mut provokes = provokes.to_vec();
+    provokes.sort();
+    (position, provokes)
+}
+
+impl Battlefield {
+    /// What a single step costs: 1, plus 1 for entering difficult terrain or entering water without a swim
+    /// speed, plus 1 per square climbed without a climb speed. Flying creatures ignore the ground. Burrowers
+    /// tunnel beneath difficult terrain for free and through walls for 1 extra.
+    fn step_cost(&self, from: &Placement, to: &Placement) -> i32 {
+        if to.flying {
+            return 1;
+        }
+        let mut cost = 1;
+        let entering_difficult = to.squares().iter().any(|square| {
+            !from.occupies(*square)
+                && match self.grid().terrain(*square) {
+                    Terrain::Difficult => !to.modes.burrow,
+                    Terrain::Water => !to.modes.swim,
+                    Terrain::Wall => true,
+                    Terrain::Floor => false,
+                }
+        });
+        if entering_difficult {
+            cost += 1;
+        }
+        let climbed = to.elevation - from.elevation;
+        if climbed > 0 && !to.modes.climb {
+            cost += climbed;
+        }
+        cost
+    }
+
+    /// Search outward from a creature, up to `max_cost` squares of movement if given
+    pub fn movement_map(&self, entity: EntityId, max_cost: Option<i32>, kind: MovementKind) -> Result<MovementMap, ResolutionError> {
+        let start = match self.placement(entity) {
+            Some(placement) => *placement,
+            None => return Err(ResolutionError::NotOnBattlefield { entity }),
+        };
+
+        let mut squares: IndexMap<Position, ReachableSquare> = IndexMap::new();
+        let mut candidates: Vec<Candidate> = vec![(start, vec![], None)];
+        let mut steps = vec![(start.position, None)];
+        let mut best: HashMap<SearchKey, i32> = HashMap::new();
+        let mut searched: HashMap<Position, Vec<(i32, Vec<EntityId>)>> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        best.insert(search_key(start.position, &[]), 0);
+        queue.push(Reverse((0, 0, 0)));
+
+        // Whether a route already searched gets to the square for no more and provokes no one new
+        let dominated = |searched: &HashMap<Position, Vec<(i32, Vec<EntityId>)>>, position: Position, cost: i32, provokes: &[EntityId]| {
+            searched.get(&position).is_some_and(|routes| {
+                routes.iter().any(|(known_cost, known_provokes)| {
+                    *known_cost <= cost && known_provokes.iter().all(|attacker| provokes.contains(attacker))
+                })
+            })
+        };
+
+        while let Some(Reverse((cost, _, index))) = queue.pop() {
+            let (placement, provokes, previous) = candidates[index].clone();
+            let position = placement.position;
+            let key = search_key(position, &provokes);
+            if best.get(&key).is_some_and(|known| *known < cost) || dominated(&searched, position, cost, &provokes) {
+                continue;
+            }
+            searched.entry(position).or_default().push((cost, provokes.clone()));
+            let step = steps.len();
+            steps.push((position, previous));
+            squares.entry(position).or_insert_with(|| ReachableSquare {
+                position,
+                cost,
+                provokes: provokes.clone(),
+                can_end: self.overlapping(entity, &placement).is_empty() && self.in_bounds(&placement),
+                step,
+            });
+
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    let next = position.offset(dx, dy);
+                    if next == position {
+                        continue;
+                    }
+                    let moved = match self.check_step(entity, &placement, next) {
+                        Ok(moved) => moved,
+                        Err(_) => continue,
+                    };
+                    let next_cost = cost + self.step_cost(&placement, &moved);
+                    if max_cost.is_some_and(|max| next_cost > max) {
+                        continue;
+                    }
+                    let mut next_provokes = provokes.clone();
+                    if kind == MovementKind::Move {
+                        for attacker in opportunity_attackers_between(self, entity, &placement, &moved) {
+                            if !next_provokes.contains(&attacker) {
+                                next_provokes.push(attacker);
+                            }
+                        }
+                    }
+                    if dominated(&searched, next, next_cost, &next_provokes) {
+                        continue;
+                    }
+                    let next_key = search_key(next, &next_provokes);
+                    if best.get(&next_key).is_some_and(|known| *known <= next_cost) {
+                        continue;
+                    }
+                    best.insert(next_key, next_cost);
+                    queue.push(Reverse((next_cost, next_provokes.len(), candidates.len())));
+                    candidates.push((moved, next_provokes, Some(step)));
+                }
+            }
+        }
+
+        Ok(MovementMap {
+            mover: entity,
+            origin: start.position,
+            squares,
+            steps,
+        })
+    }
+
+    /// Squares of movement a path costs the creature, from where it stands
+    pub fn path_cost(&self, entity: EntityId, path: &[Position]) -> Result<i32, ResolutionError> {
+        let mut current = *self.placement(entity).ok_or(ResolutionError::NotOnBattlefield { entity })?;
+        let mut cost = 0;
+        for step in path {
+            let next = self.check_step(entity, &current, *step)?;
+            cost += self.step_cost(&current, &next);
+            current = next;
+        }
+        Ok(cost)
+    }
+
+    /// Squares a creature can end its movement in with the given speed
+    pub fn reachable(&self, entity: EntityId, speed: i32, kind: MovementKind) -> Result<Vec<ReachableSquare>, ResolutionError> {
+        let map = self.movement_map(entity, Some(speed), kind)?;
+        Ok(map.destinations().into_iter().cloned().collect())
+    }
+
+    /// The cheapest path to a destination and its cost, searching no further than `max_cost`.
+    /// `None` if the creature can't get there within it or can't end its movement there.
+    pub fn cheapest_path(&self, entity: EntityId, destination: Position, kind: MovementKind, max_cost: i32) -> Result<Option<(Vec<Position>, i32)>, ResolutionError> {
+        let map = self.movement_map(entity, Some(max_cost), kind)?;
+        Ok(map.path_to(destination).zip(map.cost(destination)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::combat::TurnSide;
+    use crate::entity::{EntityDefinition, MovementMode, Size};
+    use crate::grid::Grid;
+    use crate::testing::creature;
+    use super::*;
+
+    const PC1: EntityId = EntityId::new(1);
+    const PC2: EntityId = EntityId::new(2);
+    const GOBLIN: EntityId = EntityId::new(4);
+    const GOBLIN2: EntityId = EntityId::new(5);
+    const OGRE: EntityId = EntityId::new(6);
+
+    fn battlefield(grid: Grid) -> Battlefield {
+        let mut battlefield = Battlefield::new(grid);
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(0, 0)).unwrap();
+        battlefield
+    }
+
+    #[test]
+    fn test_reachable_within_speed() {
+        let battlefield = battlefield(Grid::new(10, 10));
+        let reachable = battlefield.reachable(PC1, 2, MovementKind::Move).unwrap();
+        // A 3x3 area from the corner, minus the starting square
+        assert_eq!(reachable.len(), 8);
+        assert!(reachable.iter().all(|square| square.cost <= 2));
+        assert!(reachable.iter().any(|square| square.position == Position::new(2, 2) && square.cost == 2));
+    }
+
+    #[test]
+    fn test_difficult_terrain_costs_extra() {
+        let mut grid = Grid::new(5, 1);
+        grid.set_terrain(Position::new(1, 0), Terrain::Difficult).unwrap();
+        let battlefield = battlefield(grid);
+        let (path, cost) = battlefield.cheapest_path(PC1, Position::new(3, 0), MovementKind::Move, 5).unwrap().unwrap();
+        assert_eq!(path, vec![Position::new(1, 0), Position::new(2, 0), Position::new(3, 0)]);
+        assert_eq!(cost, 4);
+        // Out of reach once the search is limited to less movement than the path costs
+        assert!(battlefield.cheapest_path(PC1, Position::new(3, 0), MovementKind::Move, 3).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_walls_and_enemies_are_routed_around() {
+        let mut grid = Grid::new(5, 3);
+        grid.set_terrain(Position::new(1, 0), Terrain::Wall).unwrap();
+        grid.set_terrain(Position::new(1, 1), Terrain::Wall).unwrap();
+        let mut battlefield = battlefield(grid);
+        battlefield.place(OGRE, TurnSide::NPC, Size::Large, Position::new(2, 2)).unwrap();
+
+        let (path, cost) = battlefield.cheapest_path(PC1, Position::new(3, 0), MovementKind::Shift, 10).unwrap().unwrap();
+        assert_eq!(path.first(), Some(&Position::new(0, 1)));
+        assert!(!path.contains(&Position::new(2, 2)));
+        assert_eq!(cost, path.len() as i32);
+        assert!(battlefield.validate_path(PC1, &path).is_ok());
+    }
+
+    #[test]
+    fn test_allies_can_be_passed_but_not_ended_on() {
+        let mut battlefield = battlefield(Grid::new(3, 1));
+        battlefield.place(PC2, TurnSide::PC, Size::Medium, Position::new(1, 0)).unwrap();
+        let map = battlefield.movement_map(PC1, None, MovementKind::Move).unwrap();
+        assert_eq!(map.cost(Position::new(1, 0)), Some(1));
+        assert!(!map.can_end_at(Position::new(1, 0)));
+        assert_eq!(map.path_to(Position::new(2, 0)), Some(vec![Position::new(1, 0), Position::new(2, 0)]));
+    }
+
+    #[test]
+    fn test_prefers_paths_without_opportunity_attacks() {
+        let mut battlefield = battlefield(Grid::new(5, 5));
+        battlefield.place(GOBLIN, TurnSide::NPC, Size::Small, Position::new(1, 0)).unwrap();
+
+        let map = battlefield.movement_map(PC1, Some(5), MovementKind::Move).unwrap();
+        assert_eq!(map.get(Position::new(0, 3)).unwrap().provokes, vec![GOBLIN]);
+        // Staying next to the goblin provokes nothing
+        assert!(map.get(Position::new(2, 1)).unwrap().provokes.is_empty());
+
+        let shifting = battlefield.movement_map(PC1, Some(5), MovementKind::Shift).unwrap();
+        assert!(shifting.get(Position::new(0, 3)).unwrap().provokes.is_empty());
+    }
+
+    #[test]
+    fn test_equal_routes_keep_the_enemies_already_provoked() {
+        // Two corridors of equal length meet at (3, 2). The upper one provokes the first goblin and the lower
+        // one the second, who gets another chance at the mover on the way to (5, 2).
+        let open = [(0, 2), (1, 1), (2, 1), (1, 3), (2, 3), (3, 2), (4, 3), (5, 2), (1, 0), (3, 4)];
+        let mut grid = Grid::new(6, 5);
+        for x in 0..6 {
+            for y in 0..5 {
+                if !open.contains(&(x, y)) {
+                    grid.set_terrain(Position::new(x, y), Terrain::Wall).unwrap();
+                }
+            }
+        }
+        let mut battlefield = Battlefield::new(grid);
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(0, 2)).unwrap();
+        battlefield.place(GOBLIN, TurnSide::NPC, Size::Small, Position::new(1, 0)).unwrap();
+        battlefield.place(GOBLIN2, TurnSide::NPC, Size::Small, Position::new(3, 4)).unwrap();
+
+        let map = battlefield.movement_map(PC1, None, MovementKind::Move).unwrap();
+        assert_eq!(map.cost(Position::new(3, 2)), Some(3));
+        assert_eq!(map.get(Position::new(3, 2)).unwrap().provokes.len(), 1);
+        let square = map.get(Position::new(5, 2)).unwrap();
+        assert_eq!((square.cost, square.provokes.clone()), (5, vec![GOBLIN2]));
+        assert_eq!(map.path_to(Position::new(5, 2)).unwrap()[..2], [Position::new(1, 3), Position::new(2, 3)]);
+    }
+
+    #[test]
+    fn test_climbing_costs_extra_without_climb_speed() {
+        let mut grid = Grid::new(3, 1);
+        grid.set_elevation(Position::new(1, 0), 2).unwrap();
+        let mut battlefield = battlefield(grid);
+        assert_eq!(battlefield.movement_map(PC1, None, MovementKind::Move).unwrap().cost(Position::new(1, 0)), Some(3));
+
+        let climber = EntityDefinition { size: Size::Small, movement: vec![MovementMode::Climb], ..creature("Goblin", 5, &[]) };
+        battlefield.remove(PC1);
+        battlefield.place_entity(GOBLIN, TurnSide::NPC, &climber, Position::new(0, 0)).unwrap();
+        assert_eq!(battlefield.movement_map(GOBLIN, None, MovementKind::Move).unwrap().cost(Position::new(1, 0)), Some(1));
+    }
+
+    #[test]
+    fn test_water_costs_extra_without_swim_speed() {
+        let mut grid = Grid::new(3, 1);
+        grid.set_terrain(Position::new(1, 0), Terrain::Water).unwrap();
+        let mut battlefield = battlefield(grid);
+        assert_eq!(battlefield.movement_map(PC1, None, MovementKind::Move).unwrap().cost(Position::new(1, 0)), Some(2));
+
+        let swimmer = EntityDefinition { movement: vec![MovementMode::Swim], ..creature("Goblin", 5, &[]) };
+        battlefield.remove(PC1);
+        battlefield.place_entity(GOBLIN, TurnSide::NPC, &swimmer, Position::new(0, 0)).unwrap();
+        assert_eq!(battlefield.movement_map(GOBLIN, None, MovementKind::Move).unwrap().cost(Position::new(1, 0)), Some(1));
+    }
+
+    #[test]
+    fn test_burrowers_tunnel_through_walls_and_under_difficult_terrain() {
+        let mut grid = Grid::new(4, 1);
+        grid.set_terrain(Position::new(1, 0), Terrain::Wall).unwrap();
+        grid.set_terrain(Position::new(2, 0), Terrain::Difficult).unwrap();
+        let mut battlefield = battlefield(grid);
+        assert_eq!(battlefield.movement_map(PC1, None, MovementKind::Move).unwrap().cost(Position::new(3, 0)), None);
+
+        let burrower = EntityDefinition { movement: vec![MovementMode::Burrow], ..creature("Goblin", 5, &[]) };
+        battlefield.remove(PC1);
+        battlefield.place_entity(GOBLIN, TurnSide::NPC, &burrower, Position::new(0, 0)).unwrap();
+        let map = battlefield.movement_map(GOBLIN, None, MovementKind::Move).unwrap();
+        // Two to tunnel into the wall, one to come up under the difficult terrain and one more to the end
+        assert_eq!(map.cost(Position::new(3, 0)), Some(4));
+        assert!(!map.can_end_at(Position::new(1, 0)));
+        assert!(map.can_end_at(Position::new(2, 0)));
+        assert!(matches!(
+            battlefield.validate_path(GOBLIN, &[Position::new(1, 0)]),
+            Err(ResolutionError::DoesNotFit { .. })
+        ));
+        assert!(battlefield.validate_path(GOBLIN, &[Position::new(1, 0), Position::new(2, 0)]).is_ok());
+    }
+}
@@ -0,0 +1,206 @@
+use std::{fs, path::{Path, PathBuf}, sync::OnceLock};
+use indexmap::IndexMap;
+use jsonschema::Validator;
+use schemars::schema_for;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use crate::ability::Ability;
+use crate::content::ContentSet;
+use crate::entity::EntityDefinition;
+use crate::error::ContentError;
+use crate::fs::{Parser, load_files, locate, structured_parsers};
+use crate::map::MapDefinition;
+
+/// JSON Schema for the definitions of a content set, for editor validation and autocomplete
+pub fn content_schema(set: ContentSet) -> Value {
+    let schema = match set {
+        ContentSet::Heroes | ContentSet::Monsters => schema_for!(EntityDefinition),
+        ContentSet::Abilities => schema_for!(Ability),
+        ContentSet::Maps => schema_for!(MapDefinition),
+    };
+    serde_json::to_value(schema).expect("schemas serialize to JSON")
+}
+
+/// File name a set's schema is written to, such as `monsters.schema.json`
+pub fn schema_file_name(set: ContentSet) -> String {
+    format!("{}.schema.json", set.dir_name())
+}
+
+/// Write the schema of every set into a directory, returning the files written
+pub fn write_schemas(dir: &Path) -> Result<Vec<PathBuf>, ContentError> {
+    fs::create_dir_all(dir).map_err(|e| ContentError::Io { path: dir.to_path_buf(), source: e })?;
+    let mut written = vec![];
+    for set in ContentSet::ALL {
+        let path = dir.join(schema_file_name(set));
+        let json = serde_json::to_string_pretty(&content_schema(set)).expect("schemas serialize to JSON");
+        fs::write(&path, json + "\n").map_err(|e| ContentError::Io { path: path.clone(), source: e })?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// The compiled schema of a set. Each is compiled once, the first time it's needed.
+fn validator(set: ContentSet) -> &'static Validator {
+    static VALIDATORS: [OnceLock<Validator>; ContentSet::ALL.len()] = [const { OnceLock::new() }; ContentSet::ALL.len()];
+    VALIDATORS[set as usize].get_or_init(|| jsonschema::validator_for(&content_schema(set)).expect("generated schemas are valid"))
+}
+
+/// Check one parsed definition against its set's schema, reporting every mismatch
+pub fn validate_value(set: ContentSet, value: &Value, path: &Path) -> Vec<ContentError> {
+    validator(set)
+        .iter_errors(value)
+        .map(|error| ContentError::Schema {
+            path: path.to_path_buf(),
+            pointer: error.instance_path.to_string(),
+            line: 0,
+            column: 0,
+            message: error.to_string(),
+        })
+        .collect()
+}
+
+/**
+ * Load every file of a set like `load_set_with`, checking JSON, YAML and TOML files against the
+ * set's schema to catch what serde lets through, such as misspelt optional fields. Once a file
+ * matches, the definition is read from its text, so problems the schema can't see are reported where
+ * they are written too. Every problem gives the pointer to the value and its line and column. Files
+ * handled by `other_parsers`, such as ASCII maps, have no schema and are parsed as they are.
+ */
+pub fn load_checked_set<T>(dir: &Path, set: ContentSet, other_parsers: &[(&str, Parser<T>)]) -> Result<IndexMap<String, T>, ContentError>
+where
+    T: DeserializeOwned,
+{
+    let structured = structured_parsers::<Value>();
+    let typed = structured_parsers::<T>();
+    let extensions: Vec<&str> =
+        structured.iter().map(|(extension, _)| *extension).chain(other_parsers.iter().map(|(extension, _)| *extension)).collect();
+    load_files(dir, &extensions, |extension, content, path| {
+        if let Some((_, parser)) = other_parsers.iter().find(|(ext, _)| *ext == extension) {
+            return parser(content, path).map_err(|e| vec![e]);
+        }
+        let (_, parser) = structured.iter().find(|(ext, _)| *ext == extension).expect("extension has a parser");
+        let value = parser(content, path).map_err(|e| vec![e])?;
+        let problems = validate_value(set, &value, path);
+        if !problems.is_empty() {
+            return Err(problems.into_iter().map(|problem| positioned(problem, extension, content)).collect());
+        }
+        // The schema can't express everything, such as the grammar of a dice expression
+        let (_, parser) = typed.iter().find(|(ext, _)| *ext == extension).expect("extension has a parser");
+        parser(content, path).map_err(|e| vec![pointed(e, &value, extension, content)])
+    })
+}
+
+/// Add where a schema mismatch is written in the file
+fn positioned(problem: ContentError, extension: &str, content: &str) -> ContentError {
+    match problem {
+        ContentError::Schema { path, pointer, message, .. } => {
+            let (line, column) = locate(extension, content, &pointer).unwrap_or((0, 0));
+            ContentError::Schema { path, pointer, line, column, message }
+        }
+        problem => problem,
+    }
+}
+
+/// Turn an error reading a definition from its text into a schema problem pointing at the value it was
+/// reading: the last value the file's parser finds at or before the error's position
+fn pointed(problem: ContentError, value: &Value, extension: &str, content: &str) -> ContentError {
+    let ContentError::Parse { path, line, column, message } = problem else { return problem };
+    let mut pointers = vec![];
+    value_pointers(value, String::new(), &mut pointers);
+    let found = pointers
+        .into_iter()
+        .filter_map(|pointer| locate(extension, content, &pointer).map(|position| (position, pointer)))
+        .filter(|(position, _)| *position <= (line, column))
+        .max_by_key(|(position, _)| *position);
+    match found {
+        Some((_, pointer)) => ContentError::Schema { path, pointer, line, column, message },
+        None => ContentError::Parse { path, line, column, message },
+    }
+}
+
+/// The pointer of every value in a document, each container before what it holds
+fn value_pointers(value: &Value, pointer: String, pointers: &mut Vec<String>) {
+    let children: Vec<(String, &Value)> = match value {
+        Value::Object(fields) => fields.iter().map(|(key, child)| (key.replace('~', "~0").replace('/', "~1"), child)).collect(),
+        Value::Array(items) => items.iter().enumerate().map(|(index, child)| (index.to_string(), child)).collect(),
+        _ => vec![],
+    };
+    pointers.push(pointer.clone());
+    for (segment, child) in children {
+        value_pointers(child, format!("{}/{}", pointer, segment), pointers);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+
+    #[test]
+    fn test_schemas_describe_content_fields() {
+        let schema = content_schema(ContentSet::Monsters);
+        let properties = schema["properties"].as_object().unwrap();
+        assert!(properties.contains_key("max_stamina"));
+        assert!(properties.contains_key("abilities"));
+        assert_eq!(schema["additionalProperties"], json!(false));
+        assert!(content_schema(ContentSet::Abilities)["properties"].get("power_roll_3").is_some());
+    }
+
+    #[test]
+    fn test_validation_reports_mistakes_with_their_location() {
+        let goblin = json!({"name": "Goblin", "max_stamina": "ten", "size": "1S", "abilities": [], "speeed": 6});
+        let errors = validate_value(ContentSet::Monsters, &goblin, Path::new("goblin.json"));
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().any(|e| matches!(e, ContentError::Schema { pointer, .. } if pointer == "/max_stamina")));
+        assert!(errors.iter().any(|e| e.to_string().contains("speeed")));
+
+        let hero = json!({"name": "Hero", "max_stamina": 10, "size": 2, "movement": ["fly"], "abilities": ["melee_free_strike"]});
+        assert!(validate_value(ContentSet::Heroes, &hero, Path::new("hero.json")).is_empty());
+        for size in [json!("0"), json!(0)] {
+            let hero = json!({"name": "Hero", "max_stamina": 10, "size": size, "abilities": []});
+            assert_eq!(validate_value(ContentSet::Heroes, &hero, Path::new("hero.json")).len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_loading_reports_the_pointer_and_position_of_mistakes() {
+        let dir = std::env::temp_dir().join(format!("odsr-schema-positions-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("goblin.json"), "{\n  \"name\": \"Goblin\",\n  \"max_stamina\": \"ten\",\n  \"abilities\": []\n}").unwrap();
+        fs::write(dir.join("stab.yaml"), "name: Stab\npower_roll_1:\n  damage: 1 + Q\npower_roll_2:\n  damage: 2\npower_roll_3:\n  damage: 3\n").unwrap();
+        let monsters = load_checked_set::<EntityDefinition>(&dir, ContentSet::Monsters, &[]).unwrap_err();
+        fs::remove_file(dir.join("goblin.json")).unwrap();
+        let bash = r#"{"name": "Bash", "power_roll_1": {"damage": 1}, "power_roll_2": {"damage": "2 +"}, "power_roll_3": {"damage": 3}}"#;
+        fs::write(dir.join("bash.json"), bash).unwrap();
+        let abilities = load_checked_set::<Ability>(&dir, ContentSet::Abilities, &[]).unwrap_err();
+        fs::remove_dir_all(&dir).ok();
+
+        let problem = &monsters.problems()[0];
+        assert!(matches!(problem, ContentError::Schema { pointer, line: 3, .. } if pointer == "/max_stamina"), "{}", problem);
+        assert!(problem.to_string().contains("at /max_stamina (line 3, column"), "{}", problem);
+        let problem = &abilities.problems()[1];
+        assert!(matches!(problem, ContentError::Schema { pointer, line: 3, column: 11, .. } if pointer == "/power_roll_1/damage"), "{}", problem);
+        assert!(problem.to_string().contains("Unexpected 'Q' at column 5"), "{}", problem);
+        let problem = &abilities.problems()[0];
+        assert!(matches!(problem, ContentError::Schema { pointer, line: 1, .. } if pointer == "/power_roll_2/damage"), "{}", problem);
+    }
+
+    #[test]
+    fn test_bundled_content_matches_schemas() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../content");
+        for set in ContentSet::ALL {
+            let files = load_checked_set::<Value>(&dir.join(set.dir_name()), set, &[]);
+            assert!(files.is_ok_and(|files| !files.is_empty()), "{} don't match their schema", set.dir_name());
+        }
+    }
+
+    #[test]
+    fn test_checked_in_schemas_are_current() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../schemas");
+        for set in ContentSet::ALL {
+            let text = fs::read_to_string(dir.join(schema_file_name(set))).unwrap();
+            let checked_in: Value = serde_json::from_str(&text).unwrap();
+            assert_eq!(checked_in, content_schema(set), "{} is out of date", schema_file_name(set));
+        }
+    }
+}
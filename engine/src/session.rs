@@ -0,0 +1,177 @@
+use std::{fs, path::Path};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::combat::CombatState;
+use crate::entity::{Entity, EntityId};
+use crate::error::SessionError;
+use crate::events::replay_roster;
+use crate::grid::Battlefield;
+
+/// Version written into every save file. Only saves of this version can be loaded.
+pub const SESSION_VERSION: u32 = 1;
+
+/**
+ * Everything needed to pick a combat back up later: the turn order, every entity with its stamina,
+ * and where everyone stands.
+ */
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CombatSession {
+    pub state: CombatState,
+    pub entities: IndexMap<EntityId, Entity>,
+    /// The ID the next creature to join will get, so IDs of creatures that left are never reused
+    pub next_id: EntityId,
+    #[serde(default)]
+    pub battlefield: Option<Battlefield>,
+}
+
+#[derive(Serialize)]
+struct SavedSession<'a> {
+    version: u32,
+    session: &'a CombatSession,
+}
+
+pub fn session_to_json(session: &CombatSession) -> Result<String, SessionError> {
+    let saved = SavedSession { version: SESSION_VERSION, session };
+    Ok(serde_json::to_string_pretty(&saved)?)
+}
+
+pub fn session_from_json(content: &str) -> Result<CombatSession, SessionError> {
+    let mut saved: Value = serde_json::from_str(content)?;
+    let version = match saved.get("version").and_then(Value::as_u64) {
+        Some(version) => version,
+        None => return Err(SessionError::MissingVersion),
+    };
+    if version != SESSION_VERSION as u64 {
+        return Err(SessionError::UnsupportedVersion { found: version, expected: SESSION_VERSION });
+    }
+    let session = match saved.get_mut("session") {
+        Some(session) => session.take(),
+        None => return Err(SessionError::MissingSession),
+    };
+    let mut session: CombatSession = serde_json::from_value(session)?;
+
+    // The event log is the source of truth
+    let events = session.state.events().to_vec();
+    session.state = CombatState::replay(&events)?;
+    session.entities = replay_roster(&events)?;
+    Ok(session)
+}
+
+/// Write a session to a file, creating its directory if needed
+pub fn save_session(session: &CombatSession, path: &Path) -> Result<(), SessionError> {
+    let json = session_to_json(session)?;
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| SessionError::Io { path: parent.to_path_buf(), source: e })?;
+    }
+    fs::write(path, json).map_err(|e| SessionError::Io { path: path.to_path_buf(), source: e })
+}
+
+pub fn load_session(path: &Path) -> Result<CombatSession, SessionError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => return Err(SessionError::Io { path: path.to_path_buf(), source: e }),
+    };
+    session_from_json(&content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::TurnSide;
+    use crate::encounter::Encounter;
+    use crate::entity::Size;
+    use crate::grid::{Grid, Position};
+    use crate::testing::entity;
+
+    const PC1: EntityId = EntityId::new(1);
+    const GOBLIN: EntityId = EntityId::new(2);
+
+    fn encounter() -> Encounter {
+        let mut encounter = Encounter::new();
+        encounter.add(TurnSide::PC, entity("PC1", 10)).unwrap();
+        encounter.add(TurnSide::NPC, entity("Goblin", 10)).unwrap();
+        encounter.start(TurnSide::PC).unwrap();
+        encounter.update(|state| state.start_turn(TurnSide::PC, PC1)?.commit_turn()).unwrap();
+        encounter.damage(GOBLIN, 4).unwrap();
+        encounter
+    }
+
+    fn session() -> CombatSession {
+        let mut battlefield = Battlefield::new(Grid::new(6, 6));
+        battlefield.place(PC1, TurnSide::PC, Size::Medium, Position::new(0, 0)).unwrap();
+        battlefield.place(GOBLIN, TurnSide::NPC, Size::Small, Position::new(3, 3)).unwrap();
+        let mut encounter = encounter();
+        encounter.set_battlefield(battlefield);
+        encounter.to_session().unwrap()
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let json = session_to_json(&session()).unwrap();
+        let loaded = session_from_json(&json).unwrap();
+
+        let turn = loaded.state.current_turn().unwrap();
+        assert_eq!(turn.entity, PC1);
+        assert!(turn.committed);
+        assert_eq!(loaded.state.round(), 1);
+        assert_eq!(loaded.state.events().len(), 6);
+        assert_eq!(loaded.state.current_side(), TurnSide::PC);
+        assert_eq!(loaded.entities[&GOBLIN].current_health(), 6);
+        assert_eq!(loaded.next_id, EntityId::new(3));
+        assert_eq!(loaded.battlefield.unwrap().position(GOBLIN), Some(Position::new(3, 3)));
+    }
+
+    #[test]
+    fn test_session_version_is_checked() {
+        let json = session_to_json(&session()).unwrap();
+        let current = format!("\"version\": {}", SESSION_VERSION);
+        assert!(json.contains(&current));
+        let newer = json.replacen(&current, "\"version\": 99", 1);
+        assert!(matches!(session_from_json(&newer), Err(SessionError::UnsupportedVersion { found: 99, .. })));
+        let older = json.replacen(&current, "\"version\": 0", 1);
+        assert!(matches!(session_from_json(&older), Err(SessionError::UnsupportedVersion { found: 0, .. })));
+        assert!(matches!(session_from_json("{}"), Err(SessionError::MissingVersion)));
+    }
+
+    #[test]
+    fn test_state_is_rebuilt_from_event_log() {
+        let json = session_to_json(&session()).unwrap().replacen("\"round\": 1", "\"round\": 5", 1);
+        let loaded = session_from_json(&json).unwrap();
+        assert_eq!(loaded.state.round(), 1);
+    }
+
+    #[test]
+    fn test_entities_are_rebuilt_from_event_log() {
+        let mut session = session();
+        session.entities[&GOBLIN].heal(4);
+        session.entities.shift_remove(&PC1);
+        let loaded = session_from_json(&session_to_json(&session).unwrap()).unwrap();
+        assert_eq!(loaded.entities.keys().copied().collect::<Vec<_>>(), vec![PC1, GOBLIN]);
+        assert_eq!(loaded.entities[&GOBLIN].current_health(), 6);
+    }
+
+    #[test]
+    fn test_creatures_joining_mid_combat_are_rebuilt() {
+        let mut encounter = encounter();
+        let wolf = encounter.add(TurnSide::NPC, entity("Wolf", 10)).unwrap();
+        encounter.damage(wolf, 3).unwrap();
+        encounter.rename(wolf, "Wolf 2".to_string()).unwrap();
+        encounter.update(CombatState::end_turn).unwrap();
+        encounter.remove(PC1).unwrap();
+        let session = encounter.to_session().unwrap();
+        let loaded = session_from_json(&session_to_json(&session).unwrap()).unwrap();
+        assert_eq!(loaded.entities, session.entities);
+        assert_eq!(loaded.entities[&wolf].name(), "Wolf 2");
+        assert_eq!(loaded.entities[&wolf].current_health(), 7);
+    }
+
+    #[test]
+    fn test_save_and_load_file() {
+        let path = std::env::temp_dir().join(format!("odsr-session-{}.json", std::process::id()));
+        save_session(&session(), &path).unwrap();
+        let loaded = load_session(&path).unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(loaded.entities.len(), 2);
+    }
+}
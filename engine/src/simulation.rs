@@ -0,0 +1,414 @@
+use indexmap::IndexMap;
+use serde::Serialize;
+use crate::ability::Ability;
+use crate::ai::{NearestTarget, Policy, play_turn};
+use crate::combat::TurnSide;
+use crate::dice::Dice;
+use crate::encounter::Encounter;
+use crate::entity::{Entity, EntityDefinition, EntityId};
+use crate::error::{ContentError, SimulationError};
+use crate::grid::{Battlefield, Grid, Position};
+use crate::map::MapDefinition;
+
+/// Battlefield used when a simulation isn't given a map
+pub const DEFAULT_SIMULATION_GRID: (i32, i32) = (12, 8);
+
+/// Rounds after which a combat neither side has won is called a stalemate
+pub const DEFAULT_MAX_ROUNDS: i16 = 20;
+
+/// Heroes gain 1d3 of their Heroic Resource at the start of each of their turns
+pub const HEROIC_RESOURCE_DIE: u32 = 3;
+
+/// Who won a simulated combat
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Victory {
+    Heroes,
+    Monsters,
+    /// Both sides were still standing after the last round
+    Stalemate,
+}
+
+/// How one simulated combat ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CombatOutcome {
+    pub victory: Victory,
+    /// The round the combat ended in
+    pub rounds: i16,
+    /// Stamina of each hero at the end, in party order
+    pub hero_stamina: Vec<i32>,
+    /// Stamina of each monster at the end, in the order they were listed
+    pub monster_stamina: Vec<i32>,
+    /// Heroic Resource each hero gained over the combat, in party order
+    pub hero_resources: Vec<i32>,
+}
+
+impl CombatOutcome {
+    /// Heroes reduced to 0 stamina or less
+    pub fn hero_deaths(&self) -> usize {
+        self.hero_stamina.iter().filter(|stamina| **stamina <= 0).count()
+    }
+}
+
+/// Summary of a set of samples, such as the rounds each run lasted
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct Distribution {
+    pub min: i32,
+    pub max: i32,
+    pub mean: f64,
+    pub p10: i32,
+    pub median: i32,
+    pub p90: i32,
+}
+
+impl Distribution {
+    /// All zeros when there are no samples
+    pub fn of(samples: &[i32]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        let percentile = |p: usize| sorted[(sorted.len() - 1) * p / 100];
+        Self {
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean: sorted.iter().map(|sample| *sample as f64).sum::<f64>() / sorted.len() as f64,
+            p10: percentile(10),
+            median: percentile(50),
+            p90: percentile(90),
+        }
+    }
+}
+
+/// How one creature fared across every run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CreatureReport {
+    pub name: String,
+    pub max_stamina: i32,
+    /// Runs in which the creature was reduced to 0 stamina or less
+    pub deaths: u32,
+    /// Stamina left at the end of each run
+    pub stamina: Distribution,
+    /// Heroic Resource gained in each run. Only heroes have one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heroic_resource: Option<Distribution>,
+}
+
+/// The results of running a simulation many times
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SimulationReport {
+    pub runs: u32,
+    pub seed: u64,
+    pub hero_wins: u32,
+    pub monster_wins: u32,
+    pub stalemates: u32,
+    pub rounds: Distribution,
+    /// Heroes reduced to 0 stamina or less in each run
+    pub hero_deaths: Distribution,
+    pub heroes: Vec<CreatureReport>,
+    pub monsters: Vec<CreatureReport>,
+}
+
+impl SimulationReport {
+    /// Share of runs the heroes won, from 0 to 1
+    pub fn win_rate(&self) -> f64 {
+        if self.runs == 0 {
+            return 0.0;
+        }
+        self.hero_wins as f64 / self.runs as f64
+    }
+}
+
+/**
+ * Plays an encounter out with both sides controlled automatically, using the same turn order, movement,
+ * free strikes and edges as the TUI. Each side's turns are planned by a policy, by default closing with
+ * the nearest enemy and striking it with the hardest-hitting ability.
+ * Heroes gain Heroic Resource at the start of their turns as at the table. Abilities don't cost any yet,
+ * so the report shows how much each hero would have had to spend.
+ */
+pub struct Simulation<'a> {
+    heroes: Vec<EntityDefinition>,
+    monsters: Vec<EntityDefinition>,
+    abilities: &'a IndexMap<String, Ability>,
+    grid: Grid,
+    hero_start: Vec<Position>,
+    monster_start: Vec<Position>,
+    max_rounds: i16,
+    hero_policy: Box<dyn Policy>,
+    monster_policy: Box<dyn Policy>,
+}
+
+impl<'a> Simulation<'a> {
+    /// A simulation on an empty battlefield, with heroes starting on the left and monsters on the right
+    pub fn new(heroes: Vec<EntityDefinition>, monsters: Vec<EntityDefinition>, abilities: &'a IndexMap<String, Ability>) -> Self {
+        Self {
+            heroes,
+            monsters,
+            abilities,
+            grid: Grid::new(DEFAULT_SIMULATION_GRID.0, DEFAULT_SIMULATION_GRID.1),
+            hero_start: vec![],
+            monster_start: vec![],
+            max_rounds: DEFAULT_MAX_ROUNDS,
+            hero_policy: Box::new(NearestTarget),
+            monster_policy: Box::new(NearestTarget),
+        }
+    }
+
+    /// Fight on a map, starting in its start zones
+    pub fn with_map(mut self, map: &MapDefinition) -> Result<Self, ContentError> {
+        self.grid = map.to_grid()?;
+        self.hero_start = map.start_zone(TurnSide::PC).to_vec();
+        self.monster_start = map.start_zone(TurnSide::NPC).to_vec();
+        Ok(self)
+    }
+
+    pub fn with_max_rounds(mut self, max_rounds: i16) -> Self {
+        self.max_rounds = max_rounds;
+        self
+    }
+
+    /// Plan the heroes' and the monsters' turns with these policies
+    pub fn with_policies(mut self, hero_policy: Box<dyn Policy>, monster_policy: Box<dyn Policy>) -> Self {
+        self.hero_policy = hero_policy;
+        self.monster_policy = monster_policy;
+        self
+    }
+
+    /// Play the encounter out `runs` times. The same seed always gives the same report.
+    pub fn run(&self, runs: u32, seed: u64) -> Result<SimulationReport, SimulationError> {
+        let mut dice = Dice::seeded(seed);
+        let mut outcomes = Vec::with_capacity(runs as usize);
+        for _ in 0..runs {
+            outcomes.push(self.run_once(&mut dice)?);
+        }
+
+        let count = |victory: Victory| outcomes.iter().filter(|outcome| outcome.victory == victory).count() as u32;
+        let rounds: Vec<i32> = outcomes.iter().map(|outcome| outcome.rounds as i32).collect();
+        let hero_deaths: Vec<i32> = outcomes.iter().map(|outcome| outcome.hero_deaths() as i32).collect();
+        let mut heroes = creature_reports(&self.heroes, outcomes.iter().map(|outcome| &outcome.hero_stamina));
+        for (index, hero) in heroes.iter_mut().enumerate() {
+            let gained: Vec<i32> = outcomes.iter().map(|outcome| outcome.hero_resources[index]).collect();
+            hero.heroic_resource = Some(Distribution::of(&gained));
+        }
+        let monsters = creature_reports(&self.monsters, outcomes.iter().map(|outcome| &outcome.monster_stamina));
+        Ok(SimulationReport {
+            runs,
+            seed,
+            hero_wins: count(Victory::Heroes),
+            monster_wins: count(Victory::Monsters),
+            stalemates: count(Victory::Stalemate),
+            rounds: Distribution::of(&rounds),
+            hero_deaths: Distribution::of(&hero_deaths),
+            heroes,
+            monsters,
+        })
+    }
+
+    /// Play the encounter out once
+    pub fn run_once(&self, dice: &mut Dice) -> Result<CombatOutcome, SimulationError> {
+        let mut encounter = Encounter::new();
+        encounter.set_battlefield(Battlefield::new(self.grid.clone()));
+        let heroes = self.deploy(&mut encounter, TurnSide::PC)?;
+        let monsters = self.deploy(&mut encounter, TurnSide::NPC)?;
+
+        // The same roll for the first side as at the table
+        let starting_side = if dice.d10s(1)[0] > 5 { TurnSide::PC } else { TurnSide::NPC };
+        encounter.start(starting_side)?;
+
+        // Stamina of creatures that dropped, as they leave the encounter
+        let mut fallen: IndexMap<EntityId, i32> = IndexMap::new();
+        let mut resources: IndexMap<EntityId, i32> = heroes.iter().map(|hero| (*hero, 0)).collect();
+        let victory = loop {
+            if encounter.combatants(TurnSide::NPC).is_empty() {
+                break Victory::Heroes;
+            }
+            if encounter.combatants(TurnSide::PC).is_empty() {
+                break Victory::Monsters;
+            }
+            let state = encounter.state().expect("the encounter has started");
+            let side = state.current_side();
+            match state.available().first().copied() {
+                Some(creature) => {
+                    encounter.update(|state| state.start_turn(side, creature))?;
+                    if let Some(resource) = resources.get_mut(&creature) {
+                        *resource += dice.roll(1, HEROIC_RESOURCE_DIE)[0];
+                    }
+                    let policy = match side {
+                        TurnSide::PC => &self.hero_policy,
+                        TurnSide::NPC => &self.monster_policy,
+                    };
+                    // Simulated creatures take every free strike they are offered
+                    play_turn(policy.as_ref(), &mut encounter, self.abilities, creature, dice, &mut |_, _| true)?;
+                    encounter.update(|state| state.end_turn())?;
+                    remove_fallen(&mut encounter, &mut fallen)?;
+                }
+                None if state.round() >= self.max_rounds => break Victory::Stalemate,
+                None => encounter.update(|state| state.complete_round())?,
+            }
+        };
+
+        let rounds = encounter.state().map_or(0, |state| state.round());
+        let stamina = |ids: &[EntityId]| -> Vec<i32> {
+            ids.iter()
+                .map(|id| match encounter.entity(*id) {
+                    Some(entity) => entity.current_health(),
+                    None => fallen.get(id).copied().unwrap_or(0),
+                })
+                .collect()
+        };
+        Ok(CombatOutcome {
+            victory,
+            rounds,
+            hero_stamina: stamina(&heroes),
+            monster_stamina: stamina(&monsters),
+            hero_resources: resources.into_values().collect(),
+        })
+    }
+
+    /// Add one side to the encounter and place it in its start zone, or wherever there is room
+    fn deploy(&self, encounter: &mut Encounter, side: TurnSide) -> Result<Vec<EntityId>, SimulationError> {
+        let (definitions, zone) = match side {
+            TurnSide::PC => (&self.heroes, &self.hero_start),
+            TurnSide::NPC => (&self.monsters, &self.monster_start),
+        };
+        let (width, height) = (self.grid.width(), self.grid.height());
+        let mut fallback: Vec<Position> = (0..width)
+            .flat_map(|x| (0..height).map(move |y| Position::new(x, y)))
+            .collect();
+        if side == TurnSide::NPC {
+            fallback.reverse();
+        }
+
+        let mut ids = vec![];
+        for definition in definitions {
+            let id = encounter.add(side, Entity::new(definition.name.clone(), definition.clone()))?;
+            let placed = zone
+                .iter()
+                .chain(&fallback)
+                .any(|position| encounter.place(id, *position).is_ok());
+            if !placed {
+                return Err(SimulationError::NoRoom { name: definition.name.clone() });
+            }
+            ids.push(id);
+        }
+        Ok(ids)
+    }
+}
+
+/// Take creatures at 0 stamina or less out of the combat, remembering their stamina
+fn remove_fallen(encounter: &mut Encounter, fallen: &mut IndexMap<EntityId, i32>) -> Result<(), SimulationError> {
+    let dropped: Vec<(EntityId, i32)> = encounter
+        .entities()
+        .iter()
+        .filter(|(_, entity)| entity.current_health() <= 0)
+        .map(|(id, entity)| (*id, entity.current_health()))
+        .collect();
+    for (id, stamina) in dropped {
+        encounter.remove(id)?;
+        fallen.insert(id, stamina);
+    }
+    Ok(())
+}
+
+/// Per-creature results, from the stamina each creature had at the end of every run
+fn creature_reports<'o>(definitions: &[EntityDefinition], outcomes: impl Iterator<Item = &'o Vec<i32>> + Clone) -> Vec<CreatureReport> {
+    definitions
+        .iter()
+        .enumerate()
+        .map(|(index, definition)| {
+            let stamina: Vec<i32> = outcomes.clone().map(|staminas| staminas[index]).collect();
+            CreatureReport {
+                name: definition.name.clone(),
+                max_stamina: definition.max_stamina,
+                deaths: stamina.iter().filter(|stamina| **stamina <= 0).count() as u32,
+                stamina: Distribution::of(&stamina),
+                heroic_resource: None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ability::MELEE_FREE_STRIKE;
+    use crate::ai::RoleAware;
+    use crate::entity::Role;
+    use crate::testing::{creature, strike};
+
+    fn abilities() -> IndexMap<String, Ability> {
+        let mut abilities = IndexMap::new();
+        abilities.insert(MELEE_FREE_STRIKE.to_string(), strike(1, [2, 4, 6]));
+        abilities.insert("smash".to_string(), strike(1, [10, 15, 20]));
+        abilities
+    }
+
+    #[test]
+    fn test_distribution_summarizes_samples() {
+        let distribution = Distribution::of(&[5, 1, 4, 2, 3, 6, 7, 8, 9, 10, 11]);
+        assert_eq!((distribution.min, distribution.max), (1, 11));
+        assert_eq!((distribution.p10, distribution.median, distribution.p90), (2, 6, 10));
+        assert_eq!(distribution.mean, 6.0);
+        assert_eq!(Distribution::of(&[]), Distribution::default());
+    }
+
+    #[test]
+    fn test_same_seed_gives_same_report() {
+        let abilities = abilities();
+        let heroes = vec![creature("Fighter", 20, &["melee_free_strike"]), creature("Rogue", 15, &["melee_free_strike"])];
+        let monsters = vec![creature("Goblin", 10, &["melee_free_strike"]); 3];
+        let simulation = Simulation::new(heroes, monsters, &abilities);
+
+        let report = simulation.run(50, 42).unwrap();
+        assert_eq!(report, simulation.run(50, 42).unwrap());
+        assert_eq!(report.hero_wins + report.monster_wins + report.stalemates, 50);
+        assert_eq!(report.heroes.len(), 2);
+        assert_eq!(report.monsters[2].max_stamina, 10);
+        assert!(report.rounds.min >= 1);
+        assert!(report.heroes[0].heroic_resource.is_some_and(|resource| resource.min >= 1));
+        assert_eq!(report.monsters[0].heroic_resource, None);
+    }
+
+    #[test]
+    fn test_overwhelming_party_always_wins() {
+        let abilities = abilities();
+        let heroes = vec![creature("Titan", 200, &["smash"])];
+        let monsters = vec![creature("Goblin", 5, &["melee_free_strike"]); 2];
+        let report = Simulation::new(heroes, monsters, &abilities).run(20, 1).unwrap();
+
+        assert_eq!(report.win_rate(), 1.0);
+        assert_eq!(report.hero_deaths.max, 0);
+        assert_eq!(report.monsters[0].deaths, 20);
+        assert!(report.monsters[0].stamina.max <= 0);
+        assert!(report.heroes[0].stamina.min > 0);
+    }
+
+    #[test]
+    fn test_harmless_sides_reach_a_stalemate() {
+        let abilities = abilities();
+        let heroes = vec![creature("Pacifist", 10, &[])];
+        let monsters = vec![creature("Scarecrow", 10, &[])];
+        let simulation = Simulation::new(heroes, monsters, &abilities).with_max_rounds(3);
+
+        let outcome = simulation.run_once(&mut Dice::seeded(0)).unwrap();
+        assert_eq!(outcome.victory, Victory::Stalemate);
+        assert_eq!(outcome.rounds, 3);
+        assert_eq!(outcome.hero_stamina, vec![10]);
+        assert_eq!(outcome.hero_deaths(), 0);
+        // One turn a round, gaining 1 to 3 each time
+        assert!((3..=9).contains(&outcome.hero_resources[0]), "{:?}", outcome.hero_resources);
+    }
+
+    #[test]
+    fn test_artillery_outshoots_a_slow_brute() {
+        let mut abilities = abilities();
+        abilities.insert("bow".to_string(), strike(10, [3, 3, 3]));
+        let heroes = vec![EntityDefinition { speed: 1, ..creature("Knight", 12, &["melee_free_strike"]) }];
+        let monsters = vec![EntityDefinition { role: Some(Role::Artillery), ..creature("Archer", 10, &["bow"]) }];
+        let simulation = Simulation::new(heroes, monsters, &abilities).with_policies(Box::new(RoleAware), Box::new(RoleAware));
+
+        let outcome = simulation.run_once(&mut Dice::seeded(7)).unwrap();
+        assert_eq!(outcome.victory, Victory::Monsters);
+        assert_eq!(outcome.monster_stamina, vec![10]);
+    }
+}
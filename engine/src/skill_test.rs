@@ -0,0 +1,168 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::dice::{BaneState, Dice, EdgeState, PowerRollResultTier, power_roll_odds};
+use crate::entity::{Characteristic, EntityDefinition};
+
+/// What a relevant skill adds to a test
+pub const SKILL_BONUS: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+/// How a test turned out, from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestOutcome {
+    SuccessWithReward,
+    Success,
+    SuccessWithConsequence,
+    Failure,
+    FailureWithConsequence,
+}
+
+impl TestOutcome {
+    pub fn is_success(&self) -> bool {
+        matches!(self, TestOutcome::SuccessWithReward | TestOutcome::Success | TestOutcome::SuccessWithConsequence)
+    }
+}
+
+impl fmt::Display for TestOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            TestOutcome::SuccessWithReward => "success with a reward",
+            TestOutcome::Success => "success",
+            TestOutcome::SuccessWithConsequence => "success with a consequence",
+            TestOutcome::Failure => "failure",
+            TestOutcome::FailureWithConsequence => "failure with a consequence",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+    /**
+     * The outcome of a power roll tier at this difficulty. Harder tests need a higher tier to succeed
+     * cleanly. A natural 19 or 20 succeeds with a reward whatever the difficulty.
+     */
+    pub fn outcome(&self, tier: PowerRollResultTier) -> TestOutcome {
+        match (self, tier) {
+            (_, PowerRollResultTier::Tier3Critical) => TestOutcome::SuccessWithReward,
+            (Difficulty::Easy, PowerRollResultTier::Tier1) => TestOutcome::SuccessWithConsequence,
+            (Difficulty::Easy, PowerRollResultTier::Tier2) => TestOutcome::Success,
+            (Difficulty::Easy, PowerRollResultTier::Tier3) => TestOutcome::SuccessWithReward,
+            (Difficulty::Medium, PowerRollResultTier::Tier1) => TestOutcome::Failure,
+            (Difficulty::Medium, PowerRollResultTier::Tier2) => TestOutcome::SuccessWithConsequence,
+            (Difficulty::Medium, PowerRollResultTier::Tier3) => TestOutcome::Success,
+            (Difficulty::Hard, PowerRollResultTier::Tier1) => TestOutcome::FailureWithConsequence,
+            (Difficulty::Hard, PowerRollResultTier::Tier2) => TestOutcome::Failure,
+            (Difficulty::Hard, PowerRollResultTier::Tier3) => TestOutcome::Success,
+        }
+    }
+}
+
+/// A test the Director calls for: which characteristic it uses, how hard it is and which skill would help
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkillTest {
+    pub characteristic: Characteristic,
+    pub difficulty: Difficulty,
+    pub skill: Option<String>,
+}
+
+/// How a hero fared on a test
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResult {
+    pub tier: PowerRollResultTier,
+    /// The characteristic, plus the skill bonus if it applied
+    pub bonus: i32,
+    /// The hero has the skill the test called for
+    pub skilled: bool,
+    pub outcome: TestOutcome,
+}
+
+impl SkillTest {
+    pub fn new(characteristic: Characteristic, difficulty: Difficulty) -> Self {
+        Self { characteristic, difficulty, skill: None }
+    }
+
+    pub fn with_skill(mut self, skill: &str) -> Self {
+        self.skill = Some(skill.to_string());
+        self
+    }
+
+    /// The hero's characteristic, plus 2 if they have the skill. Returns the bonus and whether the skill applied.
+    pub fn bonus_for(&self, hero: &EntityDefinition) -> (i32, bool) {
+        let skilled = self.skill.as_ref().is_some_and(|skill| hero.has_skill(skill));
+        let bonus = hero.characteristics.get(self.characteristic) + if skilled { SKILL_BONUS } else { 0 };
+        (bonus, skilled)
+    }
+
+    /// Make the power roll for the test and read its outcome at the test's difficulty
+    pub fn resolve(&self, dice: &mut Dice, hero: &EntityDefinition, edge: EdgeState, bane: BaneState) -> TestResult {
+        let (bonus, skilled) = self.bonus_for(hero);
+        let tier = dice.power_roll(bonus, edge, bane);
+        TestResult { tier, bonus, skilled, outcome: self.difficulty.outcome(tier) }
+    }
+
+    /// The exact chance the hero succeeds, counting successes with a consequence
+    pub fn success_chance(&self, hero: &EntityDefinition, edge: EdgeState, bane: BaneState) -> f64 {
+        let odds = power_roll_odds(self.bonus_for(hero).0, edge, bane);
+        [PowerRollResultTier::Tier1, PowerRollResultTier::Tier2, PowerRollResultTier::Tier3, PowerRollResultTier::Tier3Critical]
+            .into_iter()
+            .filter(|tier| self.difficulty.outcome(*tier).is_success())
+            .map(|tier| odds.of(tier))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::Characteristics;
+    use crate::testing::creature;
+
+    fn hero(agility: i32, skills: &[&str]) -> EntityDefinition {
+        EntityDefinition {
+            characteristics: Characteristics { agility, ..Characteristics::default() },
+            skills: skills.iter().map(|skill| skill.to_string()).collect(),
+            ..creature("Rogue", 10, &[])
+        }
+    }
+
+    #[test]
+    fn test_difficulty_decides_what_each_tier_means() {
+        assert_eq!(Difficulty::Easy.outcome(PowerRollResultTier::Tier1), TestOutcome::SuccessWithConsequence);
+        assert_eq!(Difficulty::Medium.outcome(PowerRollResultTier::Tier2), TestOutcome::SuccessWithConsequence);
+        assert_eq!(Difficulty::Hard.outcome(PowerRollResultTier::Tier2), TestOutcome::Failure);
+        assert_eq!(Difficulty::Hard.outcome(PowerRollResultTier::Tier3Critical), TestOutcome::SuccessWithReward);
+    }
+
+    #[test]
+    fn test_relevant_skills_add_two() {
+        let sneak = SkillTest::new(Characteristic::Agility, Difficulty::Medium).with_skill("Sneak");
+        assert_eq!(sneak.bonus_for(&hero(2, &["sneak"])), (4, true));
+        assert_eq!(sneak.bonus_for(&hero(2, &["climb"])), (2, false));
+        assert_eq!(SkillTest::new(Characteristic::Might, Difficulty::Easy).bonus_for(&hero(2, &["sneak"])), (0, false));
+
+        // The same seed lands the same tier, so only the bonus differs
+        let result = sneak.resolve(&mut Dice::seeded(3), &hero(2, &["sneak"]), EdgeState::None, BaneState::None);
+        assert!(result.skilled);
+        assert_eq!(result.outcome, Difficulty::Medium.outcome(result.tier));
+    }
+
+    #[test]
+    fn test_success_chance_counts_every_roll() {
+        let test = SkillTest::new(Characteristic::Agility, Difficulty::Medium);
+        // With no bonus a medium test needs 12 or more: 45 of the 100 rolls
+        assert!((test.success_chance(&hero(0, &[]), EdgeState::None, BaneState::None) - 0.45).abs() < 1e-9);
+        assert_eq!(SkillTest::new(Characteristic::Agility, Difficulty::Easy).success_chance(&hero(0, &[]), EdgeState::None, BaneState::None), 1.0);
+        let skilled = test.clone().with_skill("sneak");
+        assert!(skilled.success_chance(&hero(0, &["Sneak"]), EdgeState::None, BaneState::None) > 0.45);
+    }
+}
@@ -0,0 +1,29 @@
+use crate::ability::Ability;
+use crate::entity::{Entity, EntityDefinition};
+
+/// A medium creature with the given stamina and ability IDs
+pub fn creature(name: &str, max_stamina: i32, abilities: &[&str]) -> EntityDefinition {
+    EntityDefinition {
+        name: name.to_string(),
+        max_stamina,
+        abilities: abilities.iter().map(|ability| ability.to_string()).collect(),
+        ..EntityDefinition::default()
+    }
+}
+
+/// A creature of that name with the given stamina and no abilities
+pub fn entity(name: &str, max_stamina: i32) -> Entity {
+    Entity::new(name.to_string(), creature(name, max_stamina, &[]))
+}
+
+/// A strike with the given range dealing fixed damage on each tier
+pub fn strike(range: i32, damage: [i32; 3]) -> Ability {
+    serde_json::from_value(serde_json::json!({
+        "name": "Strike",
+        "range": range,
+        "power_roll_1": { "damage": damage[0] },
+        "power_roll_2": { "damage": damage[1] },
+        "power_roll_3": { "damage": damage[2] },
+    }))
+    .unwrap()
+}
@@ -0,0 +1,193 @@
+use std::{fs, path::{Path, PathBuf}, process::ExitCode, time::{SystemTime, UNIX_EPOCH}};
+use clap::{Parser, builder::PossibleValuesParser};
+use indexmap::IndexMap;
+use odsr_engine::{
+    Content, ContentError, ContentLibrary, CreatureReport, Distribution, EntityDefinition, Simulation, SimulationReport,
+    ai::{POLICY_NAMES, policy},
+    content::{CONFIG_FILE, CONTENT_PATH_VAR, resolve_roots},
+    fs::structured_parsers,
+    simulation::DEFAULT_MAX_ROUNDS,
+};
+use serde::Deserialize;
+
+/// Play an encounter out many times to see how likely the party is to win
+#[derive(Parser)]
+#[command(name = "odsr-sim")]
+struct Cli {
+    /// A content pack to load. Repeat for layered packs, later ones overriding earlier ones.
+    /// Defaults to `ODSR_CONTENT`, then `odsr.json`, then `content`.
+    #[arg(long = "content")]
+    roots: Vec<PathBuf>,
+    /// File listing the party's heroes under `heroes`, in JSON, YAML or TOML
+    party: PathBuf,
+    /// Monsters to fight, by ID or name. Add `:N` for N of the same monster, as in `goblin:4`.
+    #[arg(required = true)]
+    monsters: Vec<String>,
+    /// How many times to play the encounter
+    #[arg(long, default_value_t = 1000)]
+    runs: u32,
+    /// Seed for the dice. A random seed is used and printed if omitted.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Rounds after which the combat is called a stalemate
+    #[arg(long, default_value_t = DEFAULT_MAX_ROUNDS)]
+    max_rounds: i16,
+    /// Fight on this map instead of an empty battlefield
+    #[arg(long)]
+    map: Option<String>,
+    /// How the monsters choose their moves and targets: nearest, lowest-stamina or role-aware
+    #[arg(long, default_value = "role-aware", value_parser = PossibleValuesParser::new(POLICY_NAMES))]
+    policy: String,
+    /// How the heroes choose their moves and targets
+    #[arg(long, default_value = "nearest", value_parser = PossibleValuesParser::new(POLICY_NAMES))]
+    hero_policy: String,
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+/// The heroes in a party file
+#[derive(Deserialize)]
+struct Party {
+    heroes: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn content_roots(args: Vec<PathBuf>) -> Result<Vec<PathBuf>, ContentError> {
+    let roots = resolve_roots(args, std::env::var_os(CONTENT_PATH_VAR), Path::new(CONFIG_FILE))?;
+    if roots.is_empty() {
+        return Ok(vec![PathBuf::from("content")]);
+    }
+    Ok(roots)
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    let roots = content_roots(cli.roots).map_err(|e| e.to_string())?;
+    let content = ContentLibrary::load(&roots).map(ContentLibrary::into_content).map_err(|e| e.to_string())?;
+
+    let party = load_party(&cli.party)?;
+    let heroes = party
+        .heroes
+        .iter()
+        .map(|id| find(&content.heroes, id).ok_or_else(|| format!("No hero '{}'", id)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let monsters = monster_list(&content, &cli.monsters)?;
+
+    // Clap only accepts the built-in names
+    let hero_policy = policy(&cli.hero_policy).ok_or_else(|| format!("No policy '{}'", cli.hero_policy))?;
+    let monster_policy = policy(&cli.policy).ok_or_else(|| format!("No policy '{}'", cli.policy))?;
+    let mut simulation = Simulation::new(heroes, monsters, &content.abilities)
+        .with_max_rounds(cli.max_rounds)
+        .with_policies(hero_policy, monster_policy);
+    if let Some(id) = &cli.map {
+        let map = content.maps.get(id).ok_or_else(|| format!("No map '{}'", id))?;
+        simulation = simulation.with_map(map).map_err(|e| e.to_string())?;
+    }
+
+    let seed = cli.seed.unwrap_or_else(random_seed);
+    let report = simulation.run(cli.runs, seed).map_err(|e| e.to_string())?;
+    if cli.json {
+        println!("{}", serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?);
+    } else {
+        print_report(&report);
+    }
+    Ok(())
+}
+
+fn load_party(path: &Path) -> Result<Party, String> {
+    let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+    let parser = structured_parsers::<Party>()
+        .into_iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, parser)| parser)
+        .ok_or_else(|| format!("{} is not a JSON, YAML or TOML file", path.display()))?;
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parser(&text, path).map_err(|e| e.to_string())
+}
+
+/// A definition by ID, or by name ignoring case
+fn find(definitions: &IndexMap<String, EntityDefinition>, id: &str) -> Option<EntityDefinition> {
+    definitions
+        .get(id)
+        .or_else(|| definitions.values().find(|definition| definition.name.eq_ignore_ascii_case(id)))
+        .cloned()
+}
+
+/// Expand `goblin:3` into three goblins
+fn monster_list(content: &Content, args: &[String]) -> Result<Vec<EntityDefinition>, String> {
+    let mut monsters = vec![];
+    for arg in args {
+        let (id, count) = match arg.rsplit_once(':') {
+            Some((id, count)) => (id, count.parse::<usize>().map_err(|_| format!("Invalid monster count in '{}'", arg))?),
+            None => (arg.as_str(), 1),
+        };
+        let monster = find(&content.monsters, id).ok_or_else(|| format!("No monster '{}'", id))?;
+        monsters.extend(std::iter::repeat_n(monster, count));
+    }
+    Ok(monsters)
+}
+
+fn random_seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_nanos() as u64)
+}
+
+fn percent(count: u32, runs: u32) -> String {
+    if runs == 0 {
+        return "0.0%".to_string();
+    }
+    format!("{:.1}%", count as f64 * 100.0 / runs as f64)
+}
+
+fn describe(distribution: &Distribution) -> String {
+    format!(
+        "mean {:.1}, median {}, 10-90% {} to {}, range {} to {}",
+        distribution.mean, distribution.median, distribution.p10, distribution.p90, distribution.min, distribution.max
+    )
+}
+
+fn print_creatures(title: &str, creatures: &[CreatureReport], runs: u32) {
+    println!("{}:", title);
+    for creature in creatures {
+        println!(
+            "  {:<20} stamina {:>3}  down in {:>6}  stamina left: {}",
+            creature.name,
+            creature.max_stamina,
+            percent(creature.deaths, runs),
+            describe(&creature.stamina)
+        );
+        if let Some(resource) = &creature.heroic_resource {
+            println!("  {:<20} heroic resource gained: {}", "", describe(resource));
+        }
+    }
+}
+
+fn print_report(report: &SimulationReport) {
+    println!(
+        "{} heroes against {} monsters, {} runs (seed {})",
+        report.heroes.len(),
+        report.monsters.len(),
+        report.runs,
+        report.seed
+    );
+    println!(
+        "Heroes won {}, monsters won {}, stalemates {}",
+        percent(report.hero_wins, report.runs),
+        percent(report.monster_wins, report.runs),
+        percent(report.stalemates, report.runs)
+    );
+    println!("Rounds: {}", describe(&report.rounds));
+    println!("Heroes down per run: {}", describe(&report.hero_deaths));
+    println!();
+    print_creatures("Heroes", &report.heroes, report.runs);
+    println!();
+    print_creatures("Monsters", &report.monsters, report.runs);
+}
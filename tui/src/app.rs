@@ -1,43 +1,71 @@
-use std::path::Path;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 use indexmap::{IndexMap};
-use odsr_engine::{Ability, CombatParameters, CombatState, TurnSide, entity::{Entity, EntityDefinition}, fs::load_set};
+use odsr_engine::{Ability, Characteristic, CombatSession, ContentError, ContentLibrary, CreatureMove, Dice, Difficulty, Encounter, EntityId, MapDefinition, Montage, MontageDefinition, MovementKind, Policy, Position, RoleAware, SessionHistory, TurnPlan, entity::EntityDefinition, ai::{POLICY_NAMES, policy}, content::{CONFIG_FILE, CONTENT_PATH_VAR, MANIFEST_FILE, resolve_roots}};
 
-pub enum CombatMode {
-    Setup(CombatParameters),
-    Active(CombatState),
+/// Content shipped with the repository, used when no content roots are configured
+const BUNDLED_CONTENT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../content");
+
+/// Content roots from `--content <dir>` arguments, `ODSR_CONTENT` or `odsr.json`.
+/// Falls back to `content` in the working directory, then to the bundled content.
+pub fn content_roots(args: &[String]) -> Result<Vec<PathBuf>, ContentError> {
+    let mut cli = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--content" {
+            if let Some(root) = args.next() {
+                cli.push(PathBuf::from(root));
+            }
+        } else if let Some(root) = arg.strip_prefix("--content=") {
+            cli.push(PathBuf::from(root));
+        }
+    }
+    let roots = resolve_roots(cli, std::env::var_os(CONTENT_PATH_VAR), Path::new(CONFIG_FILE))?;
+    if !roots.is_empty() {
+        return Ok(roots);
+    }
+    if Path::new("content").join(MANIFEST_FILE).is_file() {
+        return Ok(vec![PathBuf::from("content")]);
+    }
+    Ok(vec![PathBuf::from(BUNDLED_CONTENT)])
 }
 
 pub struct Definitions {
+    /// Where the content was loaded from, so it can be reloaded
+    pub roots: Vec<PathBuf>,
     pub monsters: IndexMap<String, EntityDefinition>,
     pub heroes: IndexMap<String, EntityDefinition>,
     pub abilities: IndexMap<String, Ability>,
+    pub maps: IndexMap<String, MapDefinition>,
 }
 
 impl Definitions {
-    pub fn new() -> Result<Self, String> {
-        
-        let monster_definitions = match load_set::<EntityDefinition>(Path::new("content/monsters")) {
-            Ok(definitions) => definitions,
-            Err(e) => return Err(e),
-        };
-        let hero_definitions = match load_set::<EntityDefinition>(Path::new("content/heroes")) {
-            Ok(definitions) => definitions,
-            Err(e) => return Err(e),
-        };
-        let abilities = match load_set::<Ability>(Path::new("content/abilities")) {
-            Ok(abilities) => abilities,
-            Err(e) => return Err(e),
-        };
-        Ok(Self { monsters: monster_definitions, heroes: hero_definitions, abilities: abilities })
-    
+    pub fn new(roots: Vec<PathBuf>) -> Result<Self, ContentError> {
+        let content = ContentLibrary::load(&roots)?.into_content();
+        Ok(Self { roots, monsters: content.monsters, heroes: content.heroes, abilities: content.abilities, maps: content.maps })
+    }
+
+    /// A hero or monster by its `<set>/<id>`, as entities remember it
+    pub fn creature(&self, definition_id: &str) -> Option<&EntityDefinition> {
+        match definition_id.split_once('/') {
+            Some(("heroes", id)) => self.heroes.get(id),
+            Some(("monsters", id)) => self.monsters.get(id),
+            _ => None,
+        }
     }
 }
 
 pub struct App {
     pub definitions: Definitions,
-    pub entities: IndexMap<String, Entity>,
-    pub state: Option<CombatMode>,
-    pub log: Vec<String>,
+    pub encounter: Encounter,
+    pub selected_map: Option<String>,
+    pub dice: Dice,
+    /// Plans the turns of creatures played automatically
+    pub policy: Box<dyn Policy>,
+    pub history: SessionHistory,
+    /// The montage test being run, kept after it ends so its result stays on screen
+    pub montage: Option<Montage>,
+    pub log: VecDeque<String>,
     pub log_view_expanded: bool,
     pub input_mode: InputMode,
 }
@@ -47,60 +75,204 @@ pub enum InputMode {
     TakingTurn,
     TextInput(TextInput),
     RemovingEntity,
+    RenamingEntity,
     SelectingHeroDefinition,
     SelectingMonsterDefinition,
     SelectingAbility,
-    SelectingTarget { ability_name: String },
+    /// Picking who to use an ability on, with the surges to spend on its damage
+    SelectingTarget { ability_name: String, surges: i32 },
+    Moving { cursor: Position, kind: MovementKind },
+    /// A creature partway along its path, waiting for an enemy to take or decline a free strike
+    OfferingFreeStrike(Box<PendingMove>),
+    CallingTest(TestCall),
+    SettingUpMontage(MontageDefinition),
+    RunningMontage(MontageStep),
+}
+
+/// A move under way, kept while free strikes are offered
+pub struct PendingMove {
+    pub movement: CreatureMove,
+    /// The combat before the move, so that the whole move is undone as one action
+    pub before: Option<CombatSession>,
+    /// The rest of an automated turn, carried out once the creature has moved
+    pub plan: Option<TurnPlan>,
+}
+
+/// The next montage test, filled in like a `TestCall`. Its difficulty is the montage's.
+#[derive(Clone, Copy, Default)]
+pub struct MontageStep {
+    pub hero: Option<EntityId>,
+    pub characteristic: Option<Characteristic>,
+}
+
+/// A test the Director is calling for, filled in one choice at a time: the hero, the characteristic,
+/// the difficulty, then any skill
+#[derive(Clone, Copy, Default)]
+pub struct TestCall {
+    pub hero: Option<EntityId>,
+    pub characteristic: Option<Characteristic>,
+    pub difficulty: Option<Difficulty>,
 }
 
 #[derive(Copy, Clone)]
 pub enum TextInputType {
     NPCName,
     PCName,
+    Rename(EntityId),
+    SavePath,
+    LoadPath,
+    /// A dice expression for the Director to roll
+    Roll,
 }
 
+/// File offered when saving or resuming a session
+pub const DEFAULT_SAVE_PATH: &str = "saves/session.json";
+
 pub struct TextInput {
     pub buffer: String,
     pub input_type: TextInputType,
     pub selected_definition: Option<String>,
 }
 
-
 impl App {
     /// Append a message to the log buffer
     pub fn log(&mut self, message: String) {
-        self.log.push(message);
+        self.log.push_back(message);
         // Keep log size reasonable (last 1000 messages)
         if self.log.len() > 1000 {
-            self.log.remove(0);
+            self.log.pop_front();
         }
     }
     
+    /// Select the next map in the definitions, or no map after the last one
+    pub fn cycle_map(&mut self) {
+        let keys: Vec<&String> = self.definitions.maps.keys().collect();
+        self.selected_map = match &self.selected_map {
+            None => keys.first().map(|key| (*key).clone()),
+            Some(current) => match keys.iter().position(|key| *key == current) {
+                Some(index) if index + 1 < keys.len() => Some(keys[index + 1].clone()),
+                _ => None,
+            },
+        };
+    }
+
+    /// Switch to the next built-in policy for automated turns
+    pub fn cycle_policy(&mut self) {
+        let index = POLICY_NAMES.iter().position(|name| *name == self.policy.name()).map_or(0, |index| index + 1);
+        let name = POLICY_NAMES[index % POLICY_NAMES.len()];
+        if let Some(policy) = policy(name) {
+            self.policy = policy;
+        }
+    }
+
+    /// A copy of the active combat, taken before an action so that it can be undone
+    pub fn snapshot(&self) -> Option<CombatSession> {
+        self.encounter.to_session()
+    }
+
+    /// Make an action undoable by remembering the snapshot taken before it
+    pub fn remember(&mut self, before: Option<CombatSession>) {
+        if let Some(before) = before {
+            self.history.record(before);
+        }
+    }
+
+    /// Replace the active combat with a saved or remembered session
+    pub fn restore(&mut self, session: CombatSession) {
+        self.encounter = Encounter::from_session(session);
+    }
+
+    /// The mode to return to after a prompt is finished or cancelled
+    pub fn idle_mode(&self) -> InputMode {
+        if self.encounter.is_active() {
+            InputMode::TakingTurn
+        } else {
+            InputMode::CreatingCombat
+        }
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let previous = self.snapshot().and_then(|current| self.history.undo(&current));
+        match previous {
+            Some(previous) => {
+                self.restore(previous);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let next = self.snapshot().and_then(|current| self.history.redo(&current));
+        match next {
+            Some(next) => {
+                self.restore(next);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Get the last N log messages
     pub fn last_log_messages(&self, n: usize) -> Vec<String> {
         let start = self.log.len().saturating_sub(n);
-        self.log[start..].to_vec()
+        self.log.range(start..).cloned().collect()
     }
 }
 
 impl App {
-    pub fn new() -> Result<App, String> {
-        let combat_params = CombatParameters::new(
-            Vec::<String>::new(),
-            Vec::<String>::new(),
-            TurnSide::PC,
-        );
-        
-        let definitions = match Definitions::new() {
+    /// Load the content again, keeping the old definitions if it has errors. Entities pick up
+    /// their updated definitions and keep their current stamina.
+    pub fn reload_content(&mut self) {
+        let definitions = match Definitions::new(self.definitions.roots.clone()) {
             Ok(definitions) => definitions,
-            Err(e) => return Err(e),
+            Err(e) => {
+                let problems = e.problems();
+                self.log(format!("Content not reloaded, {} problem(s):", problems.len()));
+                for problem in problems {
+                    self.log(format!("  {}", problem));
+                }
+                return;
+            }
         };
+
+        let mut replacements = vec![];
+        for (id, entity) in self.encounter.entities() {
+            let replacement = entity.definition_id().and_then(|definition_id| definitions.creature(definition_id));
+            if let Some(replacement) = replacement
+                && replacement != entity.definition() {
+                replacements.push((*id, replacement.clone()));
+            }
+        }
+        let mut updated = 0;
+        for (id, definition) in replacements {
+            // A creature whose new size doesn't fit where it stands keeps its old stat block
+            match self.encounter.redefine(id, definition) {
+                Ok(()) => updated += 1,
+                Err(e) => self.log(format!("{} not updated: {}", self.encounter.name_of(id), e)),
+            }
+        }
+
+        self.definitions = definitions;
+        if self.selected_map.as_ref().is_some_and(|map| !self.definitions.maps.contains_key(map)) {
+            self.selected_map = self.definitions.maps.keys().next().cloned();
+        }
+        self.log(format!("Content reloaded, {} entities updated", updated));
+    }
+
+    pub fn new(roots: Vec<PathBuf>) -> Result<App, ContentError> {
+        let definitions = Definitions::new(roots)?;
+        let selected_map = definitions.maps.keys().next().cloned();
         
         let app = App {
-            definitions: definitions,
-            entities: IndexMap::new(),
-            state: Some(CombatMode::Setup(combat_params)),
-            log: vec!["Welcome! Press 'n' to start combat, or 'q' to quit.".to_string()],
+            definitions,
+            encounter: Encounter::new(),
+            selected_map,
+            dice: Dice::new(),
+            policy: Box::new(RoleAware),
+            history: SessionHistory::new(),
+            montage: None,
+            log: VecDeque::from(["Welcome! Press 'n' to start combat, or 'q' to quit.".to_string()]),
             log_view_expanded: false,
             input_mode: InputMode::CreatingCombat,
         };
@@ -1,11 +1,18 @@
 use crossterm::event::KeyCode;
-use odsr_engine::{CombatParameters, CombatState, TurnSide, Entity};
+use indexmap::IndexSet;
+use odsr_engine::{AbilityResolution, AbilityUse, MAX_SURGES, BaneState, Battlefield, Characteristic, ContentSet, DiceExpression, Difficulty, EdgeState, Entity, EntityId, Montage, MontageDefinition, Grid, CombatSession, CreatureMove, MoveOutcome, MovementKind, Position, RollContext, SKILL_BONUS, SkillTest, TurnContext, TurnPlan, TurnSide, finish_planned_turn, movement_allowance, start_planned_move, teleport_creature, use_ability};
 use odsr_engine::dice::rolld10s;
-use crate::app::{App, CombatMode, InputMode, TextInput, TextInputType};
+use odsr_engine::{load_session, save_session};
+use std::path::Path;
+use crate::app::{App, DEFAULT_SAVE_PATH, InputMode, MontageStep, PendingMove, TestCall, TextInput, TextInputType};
+
+/// Battlefield size used when no map is selected
+const DEFAULT_GRID_SIZE: (i32, i32) = (12, 8);
 
 /// Counts how many entities exist with the given definition name
 fn count_instances_of_definition(app: &App, definition_name: &String) -> usize {
-    app.entities
+    app.encounter
+        .entities()
         .values()
         .filter(|entity| entity.definition_name() == definition_name)
         .count()
@@ -16,10 +23,9 @@ fn generate_default_entity_name(definition_name: &String, instance_count: usize)
     format!("{} {}", definition_name, instance_count + 1)
 }
 
-/// Ensures an entity instance exists with the given instance name, using the specified definition.
+/// Creates an entity instance with the given instance name from the specified definition.
 /// Requires definition_name to be provided - no ad-hoc definitions are created.
-/// Creates the entity instance if it doesn't exist.
-fn ensure_entity_exists(app: &mut App, instance_name: &String, definition_name: &String, is_hero: bool) {
+fn create_entity(app: &App, instance_name: &str, definition_name: &str, is_hero: bool) -> Entity {
     // Get the definition from the appropriate map
     let definition = if is_hero {
         app.definitions.heroes.get(definition_name)
@@ -30,12 +36,8 @@ fn ensure_entity_exists(app: &mut App, instance_name: &String, definition_name:
             .expect("Monster definition must exist")
             .clone()
     };
-    
-    // Create entity instance if it doesn't exist
-    if !app.entities.contains_key(instance_name) {
-        let entity = Entity::new(instance_name.clone(), definition);
-        app.entities.insert(instance_name.clone(), entity);
-    }
+    let set = if is_hero { ContentSet::Heroes } else { ContentSet::Monsters };
+    Entity::new(instance_name.to_string(), definition).with_definition_id(format!("{}/{}", set.dir_name(), definition_name))
 }
 
 pub fn handle_creation_input(app: &mut App, key: KeyCode) -> bool {
@@ -44,6 +46,15 @@ pub fn handle_creation_input(app: &mut App, key: KeyCode) -> bool {
         KeyCode::Char('n') => {
             create_combat(app);
         }
+        KeyCode::Char('m') => {
+            app.cycle_map();
+            let map_name = app.selected_map
+                .as_ref()
+                .and_then(|key| app.definitions.maps.get(key))
+                .map(|map| map.name.clone())
+                .unwrap_or_else(|| "none".to_string());
+            app.log(format!("Map: {}", map_name));
+        }
         KeyCode::Char('b') => {
             // Enter monster definition selection mode for NPC
             if app.definitions.monsters.is_empty() {
@@ -62,12 +73,21 @@ pub fn handle_creation_input(app: &mut App, key: KeyCode) -> bool {
                 app.log("Select hero definition (press number, or 'x' to cancel):".to_string());
             }
         }
-        KeyCode::Char('x') => {
+        KeyCode::Char('l') => {
+            app.input_mode = InputMode::TextInput(TextInput {
+                buffer: DEFAULT_SAVE_PATH.to_string(),
+                input_type: TextInputType::LoadPath,
+                selected_definition: None,
+            });
+            app.log("Enter the file to resume from (press Enter to confirm, Esc to cancel):".to_string());
+        }
+        KeyCode::Char('d') => start_roll(app),
+        KeyCode::Char('t') => start_test(app),
+        KeyCode::Char('g') => start_montage(app),
+        KeyCode::Char('x') if !app.encounter.is_active() => {
             // Enter removal mode during setup
-            if let Some(CombatMode::Setup(_)) = app.state {
-                app.input_mode = InputMode::RemovingEntity;
-                app.log("Select entity to remove (press number, or 'x' to cancel):".to_string());
-            }
+            app.input_mode = InputMode::RemovingEntity;
+            app.log("Select entity to remove (press number, or 'x' to cancel):".to_string());
         }
         _ => {}
     }
@@ -85,94 +105,64 @@ pub fn handle_text_input(app: &mut App, key: KeyCode) -> bool {
                 if name.is_empty() {
                     // Cancel input if empty or only whitespace
                     // Return to appropriate mode based on current state
-                    app.input_mode = match app.state {
-                        Some(CombatMode::Setup(_)) => InputMode::CreatingCombat,
-                        Some(CombatMode::Active(_)) => InputMode::TakingTurn,
-                        None => InputMode::CreatingCombat,
-                    };
+                    app.input_mode = app.idle_mode();
                     app.log("Input cancelled".to_string());
                     return false;
                 }
                 
-                // Extract selected definition before borrowing app mutably
-                let selected_def = text_input.selected_definition.clone()
-                    .expect("Definition must be selected");
-                
-                // Ensure entity exists before checking combat state
                 match input_type {
-                    TextInputType::NPCName => {
-                        ensure_entity_exists(app, &name, &selected_def, false); // false = monster
+                    TextInputType::SavePath => {
+                        save_to_file(app, &name);
+                        return false;
                     }
-                    TextInputType::PCName => {
-                        ensure_entity_exists(app, &name, &selected_def, true); // true = hero
+                    TextInputType::LoadPath => {
+                        resume_from_file(app, &name);
+                        return false;
+                    }
+                    TextInputType::Rename(id) => {
+                        rename_entity(app, id, name);
+                        return false;
                     }
+                    TextInputType::Roll => {
+                        roll_expression(app, &name);
+                        return false;
+                    }
+                    _ => {}
                 }
 
-                match app.state {
-                    Some(CombatMode::Setup(ref mut params)) => {
-                        // Adding during setup
-                        match input_type {
-                            TextInputType::NPCName => {
-                                if params.npcs().contains(&name) {
-                                    app.log(format!("NPC '{}' already added", name));
-                                } else {
-                                    params.add_npc(name.clone());
-                                    app.log(format!("Added NPC: {}", name));
-                                }
-                            }
-                            TextInputType::PCName => {
-                                if params.pcs().contains(&name) {
-                                    app.log(format!("PC '{}' already added", name));
-                                } else {
-                                    params.add_pc(name.clone());
-                                    app.log(format!("Added PC: {}", name));
-                                }
-                            }
-                        }
-                        app.input_mode = InputMode::CreatingCombat;
-                    }
-                    Some(CombatMode::Active(ref state)) => {
-                        // Adding/removing during combat
-                        match input_type {
-                            TextInputType::NPCName => {
-                                match state.add_npc(name.clone()) {
-                                    Ok(new_state) => {
-                                        app.state = Some(CombatMode::Active(new_state));
-                                        app.log(format!("Added NPC: {} (reinforcement)", name));
-                                    }
-                                    Err(e) => {
-                                        app.log(format!("Error: {}", e));
-                                    }
-                                }
-                            }
-                            TextInputType::PCName => {
-                                match state.add_pc(name.clone()) {
-                                    Ok(new_state) => {
-                                        app.state = Some(CombatMode::Active(new_state));
-                                        app.log(format!("Added PC: {} (reinforcement)", name));
-                                    }
-                                    Err(e) => {
-                                        app.log(format!("Error: {}", e));
-                                    }
-                                }
-                            }
+                // Extract selected definition before borrowing app mutably
+                let selected_def = text_input.selected_definition.clone()
+                    .expect("Definition must be selected");
+                
+                let (side, is_hero) = match input_type {
+                    TextInputType::NPCName => (TurnSide::NPC, false),
+                    TextInputType::PCName => (TurnSide::PC, true),
+                    // File names and renames are handled before any entity is created
+                    TextInputType::SavePath | TextInputType::LoadPath | TextInputType::Rename(_) | TextInputType::Roll => return false,
+                };
+                let entity = create_entity(app, &name, &selected_def, is_hero);
+                let before = app.snapshot();
+                match app.encounter.add(side, entity) {
+                    Ok(id) => {
+                        if app.encounter.is_active() {
+                            // Adding during combat
+                            app.remember(before);
+                            place_on_battlefield(app, id, side);
+                            app.log(format!("Added {:?}: {} (reinforcement)", side, name));
+                        } else {
+                            app.log(format!("Added {:?}: {}", side, name));
                         }
-                        app.input_mode = InputMode::TakingTurn;
                     }
-                    None => {
-                        app.log("No combat state available".to_string());
-                        app.input_mode = InputMode::CreatingCombat;
+                    Err(e) => {
+                        app.log(format!("Error: {}", e));
                     }
                 }
+                app.input_mode = app.idle_mode();
             }
             KeyCode::Esc => {
                 // Cancel text input
                 // Return to appropriate mode based on current state
-                app.input_mode = match app.state {
-                    Some(CombatMode::Setup(_)) => InputMode::CreatingCombat,
-                    Some(CombatMode::Active(_)) => InputMode::TakingTurn,
-                    None => InputMode::CreatingCombat,
-                };
+                app.input_mode = app.idle_mode();
                 app.log("Input cancelled".to_string());
             }
             KeyCode::Backspace => {
@@ -190,109 +180,154 @@ pub fn handle_text_input(app: &mut App, key: KeyCode) -> bool {
 pub fn handle_turn_input(app: &mut App, key: KeyCode) -> bool {
     match key {
         KeyCode::Char('q') => return true,
-        KeyCode::Char('b') => {
+        KeyCode::Char('b') if app.encounter.is_active() => {
             // Enter monster definition selection mode for NPC (during combat)
-            if let Some(CombatMode::Active(_)) = app.state {
-                if app.definitions.monsters.is_empty() {
-                    app.log("No monster definitions available. Add some to content/monsters/ first.".to_string());
-                } else {
-                    app.input_mode = InputMode::SelectingMonsterDefinition;
-                    app.log("Select monster definition (press number, or 'x' to cancel):".to_string());
-                }
+            if app.definitions.monsters.is_empty() {
+                app.log("No monster definitions available. Add some to content/monsters/ first.".to_string());
+            } else {
+                app.input_mode = InputMode::SelectingMonsterDefinition;
+                app.log("Select monster definition (press number, or 'x' to cancel):".to_string());
             }
         }
-            KeyCode::Char('p') => {
+        KeyCode::Char('p') if app.encounter.is_active() => {
             // Enter hero definition selection mode for PC (during combat)
-            if let Some(CombatMode::Active(_)) = app.state {
-                if app.definitions.heroes.is_empty() {
-                    app.log("No hero definitions available. Add some to content/heroes/ first.".to_string());
-                } else {
-                    app.input_mode = InputMode::SelectingHeroDefinition;
-                    app.log("Select hero definition (press number, or 'x' to cancel):".to_string());
-                }
+            if app.definitions.heroes.is_empty() {
+                app.log("No hero definitions available. Add some to content/heroes/ first.".to_string());
+            } else {
+                app.input_mode = InputMode::SelectingHeroDefinition;
+                app.log("Select hero definition (press number, or 'x' to cancel):".to_string());
             }
         }
-        KeyCode::Char('x') => {
+        KeyCode::Char('x') if app.encounter.is_active() => {
             // Enter removal mode
-            if let Some(CombatMode::Active(_)) = app.state {
-                    app.input_mode = InputMode::RemovingEntity;
-                    app.log("Select entity to remove (press number, or 'x' to cancel):".to_string());
-            }
+            app.input_mode = InputMode::RemovingEntity;
+            app.log("Select entity to remove (press number, or 'x' to cancel):".to_string());
         }
-        KeyCode::Char('r') => {
-            if let Some(CombatMode::Active(ref state)) = app.state {
-                let new_state = state.complete_round();
-                app.state = match new_state {
-                    Ok(new_state) => Some(CombatMode::Active(new_state)),
-                    Err(e) => {
-                        app.log(format!("Error: {}", e));
-                        return false;
-                    }
-                };
-                app.log("Round completed!".to_string());
+        KeyCode::Char('n') if app.encounter.is_active() => {
+            app.input_mode = InputMode::RenamingEntity;
+            app.log("Select entity to rename (press number, or 'x' to cancel):".to_string());
+        }
+        KeyCode::Char('r') if app.encounter.is_active() => {
+            let before = app.snapshot();
+            if let Err(e) = app.encounter.update(|state| state.complete_round()) {
+                app.log(format!("Error: {}", e));
+                return false;
             }
+            app.remember(before);
+            app.log("Round completed!".to_string());
         }
-        KeyCode::Char('e') => {
-            if let Some(CombatMode::Active(ref state)) = app.state {
-                match state.end_turn() {
-                    Ok(new_state) => {
-                        app.state = Some(CombatMode::Active(new_state));
-                        // Clear any ability selection state
-                        if let InputMode::SelectingTarget { .. } = app.input_mode {
-                            app.input_mode = InputMode::TakingTurn;
-                        }
-                        app.log("Turn ended".to_string());
-                    }
-                    Err(e) => {
-                        app.log(format!("Error: {}", e));
+        KeyCode::Char('e') if app.encounter.is_active() => {
+            let before = app.snapshot();
+            match app.encounter.update(|state| state.end_turn()) {
+                Ok(()) => {
+                    app.remember(before);
+                    // Clear any ability selection state
+                    if let InputMode::SelectingTarget { .. } = app.input_mode {
+                        app.input_mode = InputMode::TakingTurn;
                     }
+                    app.log("Turn ended".to_string());
+                }
+                Err(e) => {
+                    app.log(format!("Error: {}", e));
                 }
             }
         }
         KeyCode::Char('a') => {
             // Enter ability selection mode
-            if let Some(CombatMode::Active(ref state)) = app.state {
+            if let Some(state) = app.encounter.state()
+                && let Some(turn) = state.current_turn()
+                && let Some(entity) = app.encounter.entity(turn.entity) {
+                let ability_names = &entity.definition().abilities;
+                if ability_names.is_empty() {
+                    app.log("No abilities available for this entity.".to_string());
+                } else {
+                    app.input_mode = InputMode::SelectingAbility;
+                    app.log("Select ability (press number, or 'x' to cancel):".to_string());
+                }
+            }
+        }
+        KeyCode::Char('u') => {
+            if app.undo() {
+                app.log("Undid the last action".to_string());
+            } else {
+                app.log("Nothing to undo".to_string());
+            }
+        }
+        KeyCode::Char('y') => {
+            if app.redo() {
+                app.log("Redid the last undone action".to_string());
+            } else {
+                app.log("Nothing to redo".to_string());
+            }
+        }
+        KeyCode::Char('d') => start_roll(app),
+        KeyCode::Char('t') => start_test(app),
+        KeyCode::Char('g') => start_montage(app),
+        KeyCode::Char('s') if app.encounter.is_active() => {
+            app.input_mode = InputMode::TextInput(TextInput {
+                buffer: DEFAULT_SAVE_PATH.to_string(),
+                input_type: TextInputType::SavePath,
+                selected_definition: None,
+            });
+            app.log("Enter the file to save to (press Enter to confirm, Esc to cancel):".to_string());
+        }
+        KeyCode::Char('m') => {
+            // Enter movement mode for the creature taking its turn
+            if let Some(state) = app.encounter.state() {
                 if let Some(turn) = state.current_turn() {
-                    if let Some(entity) = app.entities.get(&turn.entity_name) {
-                        let ability_names = &entity.definition().abilities;
-                        if ability_names.is_empty() {
-                            app.log("No abilities available for this entity.".to_string());
-                        } else {
-                            app.input_mode = InputMode::SelectingAbility;
-                            app.log("Select ability (press number, or 'x' to cancel):".to_string());
+                    let mover = turn.entity;
+                    match app.encounter.battlefield().and_then(|battlefield| battlefield.position(mover)) {
+                        Some(_) if turn.move_actions == 0 && turn.main_actions == 0 => {
+                            app.log(format!("{} has no move or main action left to move with", app.encounter.name_of(mover)));
+                        }
+                        Some(cursor) => {
+                            app.input_mode = InputMode::Moving { cursor, kind: MovementKind::Move };
+                            app.log("Choose a destination (arrows or hjkl, 's' to toggle shifting, Enter to move, 't' to teleport, 'x' to cancel):".to_string());
+                        }
+                        None => {
+                            app.log(format!("{} is not on the battlefield", app.encounter.name_of(mover)));
                         }
                     }
+                } else {
+                    app.log("Start a turn before moving.".to_string());
                 }
             }
         }
-        KeyCode::Char('c') => {
-            if let Some(CombatMode::Active(ref state)) = app.state {
-                match state.cancel_turn() {
-                    Ok(new_state) => {
-                        app.state = Some(CombatMode::Active(new_state));
-                        app.log("Turn cancelled".to_string());
-                    }
-                    Err(e) => {
-                        app.log(format!("Error: {}", e));
-                    }
+        KeyCode::Char('A') if app.encounter.is_active() => {
+            auto_play_turn(app);
+        }
+        KeyCode::Char('i') if app.encounter.is_active() => {
+            app.cycle_policy();
+            app.log(format!("Automated turns use the '{}' policy", app.policy.name()));
+        }
+        KeyCode::Char('c') if app.encounter.is_active() => {
+            let before = app.snapshot();
+            match app.encounter.update(|state| state.cancel_turn()) {
+                Ok(()) => {
+                    app.remember(before);
+                    app.log("Turn cancelled".to_string());
+                }
+                Err(e) => {
+                    app.log(format!("Error: {}", e));
                 }
             }
         }
         KeyCode::Char(c) => {
-            if let Some(CombatMode::Active(ref state)) = app.state {
+            if let Some(state) = app.encounter.state() {
                 // Check if it's a digit (1-9)
                 if let Some(digit) = c.to_digit(10) {
                     // IndexSet preserves insertion order, so we can index directly
-                    let available: Vec<String> = state.available().into_iter().collect();
+                    let available: Vec<EntityId> = state.available().into_iter().collect();
                     let index = (digit as usize).saturating_sub(1); // Convert 1-9 to 0-8
                     
                     if index < available.len() {
-                        let entity = &available[index];
+                        let entity = available[index];
                         let side = state.current_side();
-                        match state.start_turn(side, entity.clone()) {
-                            Ok(new_state) => {
-                                app.state = Some(CombatMode::Active(new_state));
-                                app.log(format!("{} started their turn", entity));
+                        let before = app.snapshot();
+                        match app.encounter.update(|state| state.start_turn(side, entity)) {
+                            Ok(()) => {
+                                app.remember(before);
+                                app.log(format!("{} started their turn", app.encounter.name_of(entity)));
                             }
                             Err(e) => {
                                 app.log(format!("Error: {}", e));
@@ -310,19 +345,6 @@ pub fn handle_turn_input(app: &mut App, key: KeyCode) -> bool {
 }
 
 pub fn create_combat(app: &mut App) {
-    let combat_params = match &app.state {
-        Some(CombatMode::Setup(params)) => params,
-        _ => {
-            app.log("combat setup not found!".to_string());
-            return;
-        }
-    };
-    
-    if combat_params.pcs().is_empty() || combat_params.npcs().is_empty() {
-        app.log("Please add at least one PC and one NPC first!".to_string());
-        return;
-    }
-
     let starting_roll:i32 = rolld10s(1).iter().sum();
     let starting_side = if starting_roll > 5 {
         TurnSide::PC
@@ -330,14 +352,14 @@ pub fn create_combat(app: &mut App) {
         TurnSide::NPC
     };
 
-    // Create new CombatParameters with updated starting side
-    let combat_parameters = CombatParameters::new(
-        combat_params.pcs().clone(),
-        combat_params.npcs().clone(),
-        starting_side,
-    );
-
-    app.state = Some(CombatMode::Active(CombatState::new(combat_parameters)));
+    if let Err(e) = app.encounter.start(starting_side) {
+        app.log(format!("Error: {}", e));
+        return;
+    }
+    app.history.clear();
+    let pcs = app.encounter.combatants(TurnSide::PC).clone();
+    let npcs = app.encounter.combatants(TurnSide::NPC).clone();
+    build_battlefield(app, &pcs, &npcs);
     app.input_mode = InputMode::TakingTurn;
     app.log(format!(
         "combat created! Starting side: {:?} (rolled {})",
@@ -345,96 +367,164 @@ pub fn create_combat(app: &mut App) {
     ));
 }
 
+/// Lay out the battlefield for a new combat, on the selected map if there is one
+fn build_battlefield(app: &mut App, pcs: &IndexSet<EntityId>, npcs: &IndexSet<EntityId>) {
+    let map_grid = app.selected_map
+        .as_ref()
+        .and_then(|key| app.definitions.maps.get(key))
+        .map(|map| map.to_grid());
+    let grid = match map_grid {
+        Some(Ok(grid)) => grid,
+        Some(Err(e)) => {
+            app.log(format!("Error: {}. Using an empty battlefield.", e));
+            Grid::new(DEFAULT_GRID_SIZE.0, DEFAULT_GRID_SIZE.1)
+        }
+        None => Grid::new(DEFAULT_GRID_SIZE.0, DEFAULT_GRID_SIZE.1),
+    };
+    app.encounter.set_battlefield(Battlefield::new(grid));
+
+    for pc in pcs {
+        place_on_battlefield(app, *pc, TurnSide::PC);
+    }
+    for npc in npcs {
+        place_on_battlefield(app, *npc, TurnSide::NPC);
+    }
+}
+
+/// Place a creature in its side's start zone, or in the first free square if the zone is full.
+/// Heroes fill in from the left edge and monsters from the right.
+fn place_on_battlefield(app: &mut App, id: EntityId, side: TurnSide) {
+    let zone: Vec<Position> = app.selected_map
+        .as_ref()
+        .and_then(|key| app.definitions.maps.get(key))
+        .map(|map| map.start_zone(side).to_vec())
+        .unwrap_or_default();
+    let (width, height) = match app.encounter.battlefield() {
+        Some(battlefield) => (battlefield.grid().width(), battlefield.grid().height()),
+        None => return,
+    };
+    let mut fallback: Vec<Position> = (0..width)
+        .flat_map(|x| (0..height).map(move |y| Position::new(x, y)))
+        .collect();
+    if side == TurnSide::NPC {
+        fallback.reverse();
+    }
+
+    let placed = zone
+        .into_iter()
+        .chain(fallback)
+        .any(|position| app.encounter.place(id, position).is_ok());
+    if !placed {
+        app.log(format!("No room on the battlefield for {}", app.encounter.name_of(id)));
+    }
+}
+
+fn save_to_file(app: &mut App, path: &str) {
+    app.input_mode = InputMode::TakingTurn;
+    let session = match app.snapshot() {
+        Some(session) => session,
+        None => {
+            app.input_mode = InputMode::CreatingCombat;
+            app.log("Only an active combat can be saved".to_string());
+            return;
+        }
+    };
+
+    match save_session(&session, Path::new(path)) {
+        Ok(()) => app.log(format!("Session saved to {}", path)),
+        Err(e) => app.log(format!("Error: {}", e)),
+    }
+}
+
+fn resume_from_file(app: &mut App, path: &str) {
+    match load_session(Path::new(path)) {
+        Ok(session) => {
+            app.restore(session);
+            app.history.clear();
+            app.input_mode = InputMode::TakingTurn;
+            app.log(format!("Resumed session from {}", path));
+        }
+        Err(e) => {
+            app.input_mode = InputMode::CreatingCombat;
+            app.log(format!("Error: {}", e));
+        }
+    }
+}
+
 pub fn handle_removal_input(app: &mut App, key: KeyCode) -> bool {
     match key {
         KeyCode::Char('q') => return true,
         KeyCode::Char('x') => {
             // Cancel removal mode - return to appropriate mode
-            app.input_mode = match app.state {
-                Some(CombatMode::Setup(_)) => InputMode::CreatingCombat,
-                Some(CombatMode::Active(_)) => InputMode::TakingTurn,
-                None => InputMode::CreatingCombat,
-            };
+            app.input_mode = app.idle_mode();
             app.log("Removal cancelled".to_string());
         }
         KeyCode::Char(c) => {
             // Check if it's a digit (1-9)
             if let Some(digit) = c.to_digit(10) {
-                match app.state {
-                    Some(CombatMode::Setup(ref mut params)) => {
-                        // Removal during setup
-                        let all_pcs: Vec<String> = params.pcs().iter().cloned().collect();
-                        let all_npcs: Vec<String> = params.npcs().iter().cloned().collect();
-                        let mut all_entities: Vec<(String, bool)> = Vec::new(); // (name, is_pc)
-                        
-                        for pc in all_pcs {
-                            all_entities.push((pc, true));
-                        }
-                        for npc in all_npcs {
-                            all_entities.push((npc, false));
-                        }
-                        
-                        let index = (digit as usize).saturating_sub(1); // Convert 1-9 to 0-8
-                        
-                        if index < all_entities.len() {
-                            let (entity_name, is_pc) = &all_entities[index];
-                            let removed = if *is_pc {
-                                params.remove_pc(entity_name)
-                            } else {
-                                params.remove_npc(entity_name)
-                            };
-                            
-                            if removed {
-                                let entity_type = if *is_pc { "PC" } else { "NPC" };
-                                app.log(format!("Removed {}: {}", entity_type, entity_name));
-                                app.input_mode = InputMode::CreatingCombat;
+                let index = (digit as usize).saturating_sub(1); // Convert 1-9 to 0-8
+
+                if let Some(id) = numbered_entity(app, index) {
+                    let entity_name = app.encounter.name_of(id);
+                    let before = app.snapshot();
+                    match app.encounter.remove(id) {
+                        Ok(side) => {
+                            if app.encounter.is_active() {
+                                // Removal during combat
+                                app.remember(before);
+                                app.log(format!("Removed {:?}: {} (death)", side, entity_name));
                             } else {
-                                app.log(format!("Entity '{}' not found", entity_name));
+                                app.log(format!("Removed {:?}: {}", side, entity_name));
                             }
-                        } else {
-                            app.log(format!("No entity at position {}", digit));
-                        }
-                    }
-                    Some(CombatMode::Active(ref state)) => {
-                        // Removal during combat
-                        let all_pcs: Vec<String> = state.all_pcs().iter().cloned().collect();
-                        let all_npcs: Vec<String> = state.all_npcs().iter().cloned().collect();
-                        let mut all_entities: Vec<(String, bool)> = Vec::new(); // (name, is_pc)
-                        
-                        for pc in all_pcs {
-                            all_entities.push((pc, true));
+                            app.input_mode = app.idle_mode();
                         }
-                        for npc in all_npcs {
-                            all_entities.push((npc, false));
-                        }
-                        
-                        let index = (digit as usize).saturating_sub(1); // Convert 1-9 to 0-8
-                        
-                        if index < all_entities.len() {
-                            let (entity_name, is_pc) = &all_entities[index];
-                            let result = if *is_pc {
-                                state.remove_pc(entity_name)
-                            } else {
-                                state.remove_npc(entity_name)
-                            };
-                            
-                            match result {
-                                Ok(new_state) => {
-                                    app.state = Some(CombatMode::Active(new_state));
-                                    let entity_type = if *is_pc { "PC" } else { "NPC" };
-                                    app.log(format!("Removed {}: {} (death)", entity_type, entity_name));
-                                    app.input_mode = InputMode::TakingTurn;
-                                }
-                                Err(e) => {
-                                    app.log(format!("Error: {}", e));
-                                }
-                            }
-                        } else {
-                            app.log(format!("No entity at position {}", digit));
+                        Err(e) => {
+                            app.log(format!("Error: {}", e));
                         }
                     }
+                } else {
+                    app.log(format!("No entity at position {}", digit));
+                }
+            }
+        }
+        _ => {}
+    }
+    false
+}
+
+/// The creature listed under a number in the removal and rename lists: heroes first, then monsters
+fn numbered_entity(app: &App, index: usize) -> Option<EntityId> {
+    app.encounter.combatants(TurnSide::PC)
+        .iter()
+        .chain(app.encounter.combatants(TurnSide::NPC))
+        .nth(index)
+        .copied()
+}
+
+pub fn handle_rename_selection(app: &mut App, key: KeyCode) -> bool {
+    match key {
+        KeyCode::Char('q') => return true,
+        KeyCode::Char('x') => {
+            app.input_mode = app.idle_mode();
+            app.log("Rename cancelled".to_string());
+        }
+        KeyCode::Char(c) => {
+            // Check if it's a digit (1-9)
+            if let Some(digit) = c.to_digit(10) {
+                let index = (digit as usize).saturating_sub(1); // Convert 1-9 to 0-8
+
+                match numbered_entity(app, index) {
+                    Some(id) => {
+                        // Enter text input mode with the current name pre-filled
+                        app.input_mode = InputMode::TextInput(TextInput {
+                            buffer: app.encounter.name_of(id),
+                            input_type: TextInputType::Rename(id),
+                            selected_definition: None,
+                        });
+                        app.log("Enter the new name (press Enter to confirm, Esc to cancel):".to_string());
+                    }
                     None => {
-                        app.log("No combat state available".to_string());
+                        app.log(format!("No entity at position {}", digit));
                     }
                 }
             }
@@ -444,16 +534,27 @@ pub fn handle_removal_input(app: &mut App, key: KeyCode) -> bool {
     false
 }
 
+fn rename_entity(app: &mut App, id: EntityId, name: String) {
+    app.input_mode = app.idle_mode();
+    let old_name = app.encounter.name_of(id);
+    let before = app.snapshot();
+    match app.encounter.rename(id, name.clone()) {
+        Ok(()) => {
+            app.remember(before);
+            app.log(format!("Renamed {} to {}", old_name, name));
+        }
+        Err(e) => {
+            app.log(format!("Error: {}", e));
+        }
+    }
+}
+
 pub fn handle_monster_selection(app: &mut App, key: KeyCode) -> bool {
     match key {
         KeyCode::Char('q') => return true,
         KeyCode::Char('x') => {
             // Cancel selection - return to creation mode
-            app.input_mode = match app.state {
-                Some(CombatMode::Setup(_)) => InputMode::CreatingCombat,
-                Some(CombatMode::Active(_)) => InputMode::TakingTurn,
-                None => InputMode::CreatingCombat,
-            };
+            app.input_mode = app.idle_mode();
             app.log("Monster selection cancelled".to_string());
         }
         KeyCode::Char(c) => {
@@ -489,11 +590,7 @@ pub fn handle_hero_selection(app: &mut App, key: KeyCode) -> bool {
         KeyCode::Char('q') => return true,
         KeyCode::Char('x') => {
             // Cancel selection - return to creation mode
-            app.input_mode = match app.state {
-                Some(CombatMode::Setup(_)) => InputMode::CreatingCombat,
-                Some(CombatMode::Active(_)) => InputMode::TakingTurn,
-                None => InputMode::CreatingCombat,
-            };
+            app.input_mode = app.idle_mode();
             app.log("Hero selection cancelled".to_string());
         }
         KeyCode::Char(c) => {
@@ -534,27 +631,25 @@ pub fn handle_ability_selection(app: &mut App, key: KeyCode) -> bool {
         }
         KeyCode::Char(c) => {
             // Check if it's a digit (1-9)
-            if let Some(digit) = c.to_digit(10) {
-                if let Some(CombatMode::Active(ref state)) = app.state {
-                    if let Some(turn) = state.current_turn() {
-                        if let Some(entity) = app.entities.get(&turn.entity_name) {
-                            let ability_names: Vec<&String> = entity.definition().abilities.iter().collect();
-                            let index = (digit as usize).saturating_sub(1); // Convert 1-9 to 0-8
-                            
-                            if index < ability_names.len() {
-                                let ability_name = ability_names[index].clone();
-                                // Verify ability exists
-                                if app.definitions.abilities.contains_key(&ability_name) {
-                                    app.input_mode = InputMode::SelectingTarget { ability_name: ability_name.clone() };
-                                    app.log(format!("Selected ability: {}. Select target (press number, or 'x' to cancel):", ability_name));
-                                } else {
-                                    app.log(format!("Ability '{}' not found in definitions", ability_name));
-                                }
-                            } else {
-                                app.log(format!("No ability at position {}", digit));
-                            }
-                        }
+            if let Some(digit) = c.to_digit(10)
+                && let Some(state) = app.encounter.state()
+                && let Some(turn) = state.current_turn()
+                && let Some(entity) = app.encounter.entity(turn.entity) {
+                let ability_names: Vec<&String> = entity.definition().abilities.iter().collect();
+                let index = (digit as usize).saturating_sub(1); // Convert 1-9 to 0-8
+                
+                if index < ability_names.len() {
+                    let ability_name = ability_names[index].clone();
+                    // Verify ability exists
+                    if let Some(ability) = app.definitions.abilities.get(&ability_name) {
+                        let hint = if ability.uses_surges() { ", '+'/'-' to spend surges" } else { "" };
+                        app.log(format!("Selected ability: {}. Select target (press number{}, or 'x' to cancel):", ability_name, hint));
+                        app.input_mode = InputMode::SelectingTarget { ability_name, surges: 0 };
+                    } else {
+                        app.log(format!("Ability '{}' not found in definitions", ability_name));
                     }
+                } else {
+                    app.log(format!("No ability at position {}", digit));
                 }
             }
         }
@@ -571,39 +666,45 @@ pub fn handle_target_selection(app: &mut App, key: KeyCode) -> bool {
             app.input_mode = InputMode::SelectingAbility;
             app.log("Target selection cancelled. Select ability (press number, or 'x' to cancel):".to_string());
         }
+        KeyCode::Char(c @ ('+' | '=' | '-')) => {
+            if let InputMode::SelectingTarget { ability_name, surges } = &mut app.input_mode
+                && app.definitions.abilities.get(ability_name.as_str()).is_some_and(|ability| ability.uses_surges()) {
+                *surges = if c == '-' { *surges - 1 } else { *surges + 1 }.clamp(0, MAX_SURGES);
+                let spent = *surges;
+                app.log(format!("Spending {} surge(s)", spent));
+            }
+        }
         KeyCode::Char(c) => {
             // Check if it's a digit (1-9)
-            if let Some(digit) = c.to_digit(10) {
-                if let Some(CombatMode::Active(ref state)) = app.state {
-                    // Get all entities in combat (PCs and NPCs)
-                    let all_pcs: Vec<&String> = state.all_pcs().iter().collect();
-                    let all_npcs: Vec<&String> = state.all_npcs().iter().collect();
-                    let mut all_entities: Vec<&String> = Vec::new();
-                    
-                    for pc in all_pcs {
-                        all_entities.push(pc);
-                    }
-                    for npc in all_npcs {
-                        all_entities.push(npc);
-                    }
-                    
-                    let index = (digit as usize).saturating_sub(1); // Convert 1-9 to 0-8
+            if let Some(digit) = c.to_digit(10)
+                && let Some(state) = app.encounter.state() {
+                // Get all entities in combat (PCs and NPCs)
+                let all_pcs: Vec<&EntityId> = state.all_pcs().iter().collect();
+                let all_npcs: Vec<&EntityId> = state.all_npcs().iter().collect();
+                let mut all_entities: Vec<&EntityId> = Vec::new();
+                
+                for pc in all_pcs {
+                    all_entities.push(pc);
+                }
+                for npc in all_npcs {
+                    all_entities.push(npc);
+                }
+                
+                let index = (digit as usize).saturating_sub(1); // Convert 1-9 to 0-8
+                
+                if index < all_entities.len() {
+                    let target = *all_entities[index];
                     
-                    if index < all_entities.len() {
-                        let target_name = all_entities[index].clone();
-                        
-                        // Extract ability name before borrowing app mutably
-                        let ability_name = if let InputMode::SelectingTarget { ability_name } = &app.input_mode {
-                            ability_name.clone()
-                        } else {
-                            return false;
-                        };
-                        
-                        // Execute ability (stub)
-                        execute_ability(app, &ability_name, &target_name);
+                    // Extract ability name before borrowing app mutably
+                    let (ability_name, surges) = if let InputMode::SelectingTarget { ability_name, surges } = &app.input_mode {
+                        (ability_name.clone(), *surges)
                     } else {
-                        app.log(format!("No entity at position {}", digit));
-                    }
+                        return false;
+                    };
+                    
+                    execute_ability(app, &ability_name, target, surges);
+                } else {
+                    app.log(format!("No entity at position {}", digit));
                 }
             }
         }
@@ -612,23 +713,581 @@ pub fn handle_target_selection(app: &mut App, key: KeyCode) -> bool {
     false
 }
 
-fn execute_ability(app: &mut App, ability_name: &str, target_name: &str) {
-    // Stub: Just log the execution
-    app.log(format!("Executing ability '{}' on target '{}' (stub)", ability_name, target_name));
-    
-    // Mark turn as committed (cannot be cancelled after this)
-    if let Some(CombatMode::Active(ref state)) = app.state {
-        match state.commit_turn() {
-            Ok(new_state) => {
-                app.state = Some(CombatMode::Active(new_state));
+fn start_test(app: &mut App) {
+    if app.encounter.combatants(TurnSide::PC).is_empty() {
+        app.log("Add a hero before calling for a test".to_string());
+        return;
+    }
+    app.input_mode = InputMode::CallingTest(TestCall::default());
+    app.log("Call for a test: pick the hero (press number, or 'x' to cancel)".to_string());
+}
+
+/// Fill in the test being called for, one key per choice, and roll it once the skill is picked
+pub fn handle_test_input(app: &mut App, key: KeyCode) -> bool {
+    let call = match app.input_mode {
+        InputMode::CallingTest(call) => call,
+        _ => return false,
+    };
+    let c = match key {
+        KeyCode::Char('q') => return true,
+        KeyCode::Char('x') | KeyCode::Esc => {
+            app.input_mode = app.idle_mode();
+            app.log("Test cancelled".to_string());
+            return false;
+        }
+        KeyCode::Char(c) => c,
+        _ => return false,
+    };
+    let number = c.to_digit(10).map(|digit| digit as usize);
+
+    match (call.hero, call.characteristic, call.difficulty) {
+        (None, _, _) => {
+            let hero = number
+                .filter(|number| *number > 0)
+                .and_then(|number| app.encounter.combatants(TurnSide::PC).get_index(number - 1).copied());
+            if let Some(hero) = hero {
+                app.input_mode = InputMode::CallingTest(TestCall { hero: Some(hero), ..call });
+                app.log("Pick the characteristic: m, a, r, i or p".to_string());
+            }
+        }
+        (Some(_), None, _) => {
+            if let Some(characteristic) = Characteristic::parse(&c.to_string()) {
+                app.input_mode = InputMode::CallingTest(TestCall { characteristic: Some(characteristic), ..call });
+                app.log("Pick the difficulty: e for easy, m for medium or h for hard".to_string());
+            }
+        }
+        (Some(_), Some(_), None) => {
+            let difficulty = match c {
+                'e' => Difficulty::Easy,
+                'm' => Difficulty::Medium,
+                'h' => Difficulty::Hard,
+                _ => return false,
+            };
+            app.input_mode = InputMode::CallingTest(TestCall { difficulty: Some(difficulty), ..call });
+            app.log("Pick a relevant skill by number, or 0 for none".to_string());
+        }
+        (Some(hero), Some(characteristic), Some(difficulty)) => {
+            let skills = app.encounter.entity(hero).map_or(vec![], |entity| entity.definition().skills.clone());
+            let test = match number {
+                Some(0) => SkillTest::new(characteristic, difficulty),
+                Some(number) if number <= skills.len() => SkillTest::new(characteristic, difficulty).with_skill(&skills[number - 1]),
+                _ => return false,
+            };
+            roll_test(app, hero, &test);
+        }
+    }
+    false
+}
+
+fn roll_test(app: &mut App, hero: EntityId, test: &SkillTest) {
+    app.input_mode = app.idle_mode();
+    let definition = match app.encounter.entity(hero) {
+        Some(entity) => entity.definition().clone(),
+        None => return,
+    };
+    let result = test.resolve(&mut app.dice, &definition, EdgeState::None, BaneState::None);
+    let skill = match (&test.skill, result.skilled) {
+        (Some(skill), true) => format!(", including {} +{}", skill, SKILL_BONUS),
+        _ => String::new(),
+    };
+    app.log(format!(
+        "{} makes a {} {:?} test (bonus {:+}{}): {:?}, {}",
+        app.encounter.name_of(hero),
+        format!("{:?}", test.difficulty).to_lowercase(),
+        test.characteristic,
+        result.bonus,
+        skill,
+        result.tier,
+        result.outcome
+    ));
+}
+
+fn start_montage(app: &mut App) {
+    let heroes = app.encounter.combatants(TurnSide::PC).len() as u32;
+    if heroes == 0 {
+        app.log("Add a hero before running a montage test".to_string());
+        return;
+    }
+    app.input_mode = InputMode::SettingUpMontage(MontageDefinition::for_party("Montage", Difficulty::Medium, heroes));
+    app.log("Set up the montage, then press Enter to begin ('x' to cancel)".to_string());
+}
+
+/// Adjust the montage being set up, and start it with every hero in the encounter taking part
+pub fn handle_montage_setup(app: &mut App, key: KeyCode) -> bool {
+    let mut definition = match &app.input_mode {
+        InputMode::SettingUpMontage(definition) => definition.clone(),
+        _ => return false,
+    };
+    match key {
+        KeyCode::Char('q') => return true,
+        KeyCode::Char('x') | KeyCode::Esc => {
+            app.input_mode = app.idle_mode();
+            app.log("Montage cancelled".to_string());
+            return false;
+        }
+        KeyCode::Enter => {
+            let heroes: Vec<EntityId> = app.encounter.combatants(TurnSide::PC).iter().copied().collect();
+            match Montage::new(definition, heroes) {
+                Ok(montage) => {
+                    app.log(format!(
+                        "Montage begins: {} successes before {} failures, within {} rounds",
+                        montage.definition().success_limit,
+                        montage.definition().failure_limit,
+                        montage.definition().rounds
+                    ));
+                    app.montage = Some(montage);
+                    app.input_mode = InputMode::RunningMontage(MontageStep::default());
+                }
+                Err(e) => app.log(format!("Error: {}", e)),
+            }
+            return false;
+        }
+        // A new difficulty resets the limits to the table's, keeping the rounds
+        KeyCode::Char(c @ ('e' | 'm' | 'h')) => {
+            let difficulty = match c {
+                'e' => Difficulty::Easy,
+                'm' => Difficulty::Medium,
+                _ => Difficulty::Hard,
+            };
+            let heroes = app.encounter.combatants(TurnSide::PC).len() as u32;
+            definition = MontageDefinition::for_party(&definition.name, difficulty, heroes).with_rounds(definition.rounds);
+        }
+        KeyCode::Char('s') => definition.success_limit += 1,
+        KeyCode::Char('S') => definition.success_limit = definition.success_limit.saturating_sub(1).max(1),
+        KeyCode::Char('f') => definition.failure_limit += 1,
+        KeyCode::Char('F') => definition.failure_limit = definition.failure_limit.saturating_sub(1).max(1),
+        KeyCode::Char('r') => definition.rounds += 1,
+        KeyCode::Char('R') => definition.rounds = definition.rounds.saturating_sub(1).max(1),
+        _ => return false,
+    }
+    app.input_mode = InputMode::SettingUpMontage(definition);
+    false
+}
+
+/// Pick a hero, then a characteristic and any skill, and roll their test; or sit them out with 's'
+pub fn handle_montage_input(app: &mut App, key: KeyCode) -> bool {
+    let step = match app.input_mode {
+        InputMode::RunningMontage(step) => step,
+        _ => return false,
+    };
+    let montage = match app.montage.as_mut() {
+        Some(montage) => montage,
+        None => {
+            app.input_mode = app.idle_mode();
+            return false;
+        }
+    };
+    let c = match key {
+        KeyCode::Char('q') => return true,
+        KeyCode::Char('x') | KeyCode::Esc => {
+            if montage.outcome().is_none() {
+                app.log("Montage abandoned".to_string());
             }
-            Err(e) => {
-                app.log(format!("Warning: Could not commit turn: {}", e));
+            app.montage = None;
+            app.input_mode = app.idle_mode();
+            return false;
+        }
+        KeyCode::Char(c) if montage.outcome().is_none() => c,
+        _ => return false,
+    };
+    let number = c.to_digit(10).map(|digit| digit as usize);
+
+    match (step.hero, step.characteristic) {
+        (None, _) => {
+            let hero = number.filter(|number| *number > 0).and_then(|number| montage.heroes().get_index(number - 1).copied());
+            if let Some(hero) = hero {
+                app.input_mode = InputMode::RunningMontage(MontageStep { hero: Some(hero), characteristic: None });
+            }
+        }
+        (Some(hero), None) if c == 's' => {
+            let result = montage.pass(hero);
+            app.input_mode = InputMode::RunningMontage(MontageStep::default());
+            match result {
+                Ok(()) => app.log(format!("{} sits this round out", app.encounter.name_of(hero))),
+                Err(e) => app.log(format!("Error: {}", e)),
+            }
+            log_montage_end(app);
+        }
+        (Some(hero), None) => {
+            if let Some(characteristic) = Characteristic::parse(&c.to_string()) {
+                app.input_mode = InputMode::RunningMontage(MontageStep { hero: Some(hero), characteristic: Some(characteristic) });
             }
         }
+        (Some(hero), Some(characteristic)) => {
+            let definition = match app.encounter.entity(hero) {
+                Some(entity) => entity.definition().clone(),
+                None => return false,
+            };
+            let test = SkillTest::new(characteristic, montage.definition().difficulty);
+            let test = match number {
+                Some(0) => test,
+                Some(number) if number <= definition.skills.len() => test.with_skill(&definition.skills[number - 1]),
+                _ => return false,
+            };
+            let result = montage.attempt(&mut app.dice, hero, &definition, test).map(|attempt| attempt.result.clone());
+            app.input_mode = InputMode::RunningMontage(MontageStep::default());
+            match result {
+                Ok(result) => app.log(format!(
+                    "{} tests {:?} (bonus {:+}): {:?}, {}",
+                    app.encounter.name_of(hero), characteristic, result.bonus, result.tier, result.outcome
+                )),
+                Err(e) => app.log(format!("Error: {}", e)),
+            }
+            log_montage_end(app);
+        }
+    }
+    false
+}
+
+fn log_montage_end(app: &mut App) {
+    if let Some((outcome, successes, failures)) = app.montage.as_ref().and_then(|montage| {
+        montage.outcome().map(|outcome| (outcome, montage.successes(), montage.failures()))
+    }) {
+        app.log(format!("Montage over: {} ({} successes, {} failures). Press 'x' to close it", outcome, successes, failures));
+    }
+}
+
+fn start_roll(app: &mut App) {
+    app.input_mode = InputMode::TextInput(TextInput {
+        buffer: String::new(),
+        input_type: TextInputType::Roll,
+        selected_definition: None,
+    });
+    app.log("Enter dice to roll, such as 1d10 + 2 or 2d6 + M (press Enter to roll, Esc to cancel):".to_string());
+}
+
+/// Roll a dice expression for the Director. Characteristics are those of the creature taking its turn.
+fn roll_expression(app: &mut App, text: &str) {
+    app.input_mode = app.idle_mode();
+    let expression: DiceExpression = match text.parse() {
+        Ok(expression) => expression,
+        Err(e) => {
+            app.log(format!("Can't roll '{}': {}", text, e));
+            return;
+        }
+    };
+    let roller = app.encounter.state().and_then(|state| state.current_turn()).map(|turn| turn.entity);
+    let context = roller.map_or_else(RollContext::default, |id| app.encounter.roll_context(id));
+    let breakdown = expression.roll(&mut app.dice, &context);
+    let who = roller.map_or_else(|| "Director".to_string(), |id| app.encounter.name_of(id));
+    app.log(format!("{} rolls {}: {}", who, expression, breakdown));
+}
+
+fn execute_ability(app: &mut App, ability_name: &str, target: EntityId, surges: i32) {
+    let actor = match app.encounter.state().and_then(|state| state.current_turn()) {
+        Some(turn) => turn.entity,
+        None => {
+            app.input_mode = InputMode::TakingTurn;
+            return;
+        }
+    };
+    let before = app.snapshot();
+    let used = AbilityUse::new(actor, ability_name, target).with_surges(surges);
+    match use_ability(&mut app.encounter, &app.definitions.abilities, used, &mut app.dice) {
+        Ok(resolution) => {
+            app.remember(before);
+            log_strike(app, actor, ability_name, target, &resolution);
+            // After execution, return to turn mode
+            app.input_mode = InputMode::TakingTurn;
+            app.log("Ability executed. Press 'e' to end turn, or 'a' to use another ability.".to_string());
+        }
+        Err(e) => {
+            app.log(format!("Error: {}", e));
+        }
     }
-    
-    // After execution, return to turn mode
+}
+
+fn log_strike(app: &mut App, actor: EntityId, ability_name: &str, target: EntityId, resolution: &AbilityResolution) {
+    let ability = app.definitions.abilities.get(ability_name).map_or(ability_name.to_string(), |ability| ability.name.clone());
+    app.log(format!(
+        "{} uses {} on {}: {:?}, {} damage",
+        app.encounter.name_of(actor), ability, app.encounter.name_of(target), resolution.tier, resolution.damage
+    ));
+    if !resolution.roll.is_fixed() {
+        app.log(format!("  damage roll: {}", resolution.roll));
+    }
+    if resolution.extra_main_action {
+        app.log(format!("Critical hit! {} gains an extra main action", app.encounter.name_of(actor)));
+    }
+    if app.encounter.entity(target).is_some_and(|entity| entity.current_health() <= 0) {
+        app.log(format!("{} drops", app.encounter.name_of(target)));
+    }
+}
+
+/// Let the policy play the current monster's turn, starting it for the next monster if none has started,
+/// then end it. Free strikes the monster provokes on the way are offered as it moves. The whole turn is
+/// undone as one action.
+fn auto_play_turn(app: &mut App) {
+    let state = match app.encounter.state() {
+        Some(state) => state,
+        None => return,
+    };
+    let creature = match state.current_turn() {
+        Some(turn) if turn.side == TurnSide::NPC => turn.entity,
+        Some(_) => {
+            app.log("Only monster turns can be played automatically".to_string());
+            return;
+        }
+        None if state.current_side() != TurnSide::NPC => {
+            app.log("It is the heroes' side; only monster turns can be played automatically".to_string());
+            return;
+        }
+        None => match state.available().first().copied() {
+            Some(creature) => creature,
+            None => {
+                app.log("Every monster has acted this round. Press 'r' to complete the round.".to_string());
+                return;
+            }
+        },
+    };
+    let before = app.snapshot();
+    let starting = app.encounter.state().is_some_and(|state| state.current_turn().is_none());
+    match plan_automatically(app, creature, starting) {
+        Ok((plan, Some(movement))) => continue_move(app, PendingMove { movement, before, plan: Some(plan) }),
+        Ok((plan, None)) => finish_automated_turn(app, creature, plan, None, before),
+        Err(e) => abandon_turn(app, before, e),
+    }
+}
+
+fn plan_automatically(app: &mut App, creature: EntityId, starting: bool) -> Result<(TurnPlan, Option<CreatureMove>), String> {
+    if starting {
+        app.encounter.update(|state| state.start_turn(TurnSide::NPC, creature)).map_err(|e| e.to_string())?;
+    }
+    let context = TurnContext::new(&app.encounter, &app.definitions.abilities, creature).map_err(|e| e.to_string())?;
+    let plan = app.policy.plan(&context, creature).map_err(|e| e.to_string())?;
+    let movement = start_planned_move(&mut app.encounter, creature, &plan).map_err(|e| e.to_string())?;
+    Ok((plan, movement))
+}
+
+/// Use the monster's ability once it has moved, log the turn and end it
+fn finish_automated_turn(app: &mut App, creature: EntityId, plan: TurnPlan, movement: Option<MoveOutcome>, before: Option<CombatSession>) {
+    let report = match finish_planned_turn(&mut app.encounter, &app.definitions.abilities, creature, plan, movement, &mut app.dice) {
+        Ok(report) => report,
+        Err(e) => return abandon_turn(app, before, e.to_string()),
+    };
+    let name = app.encounter.name_of(creature);
+    app.log(format!("{} plays their turn ({})", name, app.policy.name()));
+    if let Some(movement) = &report.movement {
+        app.log(format!("{} moved to ({}, {})", name, movement.position.x, movement.position.y));
+    }
+    match (&report.plan.strike, &report.strike) {
+        (Some(planned), Some(resolution)) => {
+            log_strike(app, creature, &planned.ability, planned.target, resolution);
+            for (target, resolution) in &report.extra_strikes {
+                log_strike(app, creature, &planned.ability, *target, resolution);
+            }
+        }
+        (Some(planned), None) => app.log(format!("{} could not reach {}", name, app.encounter.name_of(planned.target))),
+        (None, _) if report.movement.is_none() => app.log(format!("{} holds their position", name)),
+        (None, _) => {}
+    }
+    match app.encounter.update(|state| state.end_turn()) {
+        Ok(()) => app.log("Turn ended".to_string()),
+        Err(e) => app.log(format!("Error: {}", e)),
+    }
+    app.remember(before);
+    app.input_mode = InputMode::TakingTurn;
+}
+
+/// Put the combat back as it was before an action that failed partway, leaving nothing half done
+fn abandon_turn(app: &mut App, before: Option<CombatSession>, error: String) {
+    if let Some(before) = before {
+        app.restore(before);
+    }
+    app.log(format!("Error: {}", error));
     app.input_mode = InputMode::TakingTurn;
-    app.log("Ability executed. Press 'e' to end turn, or 'a' to use another ability.".to_string());
+}
+
+/// Move on until the next free strike is offered, prompting for it, or until the move is over
+fn continue_move(app: &mut App, mut pending: PendingMove) {
+    let offer = match pending.movement.advance(&mut app.encounter) {
+        Ok(offer) => offer.cloned(),
+        Err(e) => return abandon_turn(app, pending.before, e.to_string()),
+    };
+    match offer {
+        Some(attack) => {
+            app.log(format!(
+                "{} can make a free strike against {}. Take it? (y/n)",
+                app.encounter.name_of(attack.attacker),
+                app.encounter.name_of(attack.target)
+            ));
+            app.input_mode = InputMode::OfferingFreeStrike(Box::new(pending));
+        }
+        None => finish_move(app, pending),
+    }
+}
+
+fn finish_move(app: &mut App, pending: PendingMove) {
+    let PendingMove { movement, before, plan } = pending;
+    let mover = movement.mover();
+    let outcome = match movement.finish(&mut app.encounter) {
+        Ok(outcome) => outcome,
+        Err(e) => return abandon_turn(app, before, e.to_string()),
+    };
+    for fall in &outcome.falls {
+        app.log(format!("{} falls {} squares and takes {} damage", app.encounter.name_of(mover), fall.distance, fall.damage));
+    }
+    if outcome.dropped {
+        app.log(format!("{} drops and stops moving", app.encounter.name_of(mover)));
+    }
+    match plan {
+        Some(plan) => finish_automated_turn(app, mover, plan, Some(outcome), before),
+        None => {
+            app.remember(before);
+            app.log(format!("{} moved to ({}, {})", app.encounter.name_of(mover), outcome.position.x, outcome.position.y));
+            app.input_mode = InputMode::TakingTurn;
+        }
+    }
+}
+
+/// Take ('y') or decline ('n') the free strike on offer, then carry on moving
+pub fn handle_free_strike_input(app: &mut App, key: KeyCode) -> bool {
+    let take = match key {
+        KeyCode::Char('q') => return true,
+        KeyCode::Char('y') => true,
+        KeyCode::Char('n') | KeyCode::Esc => false,
+        _ => return false,
+    };
+    let mut pending = match std::mem::replace(&mut app.input_mode, InputMode::TakingTurn) {
+        InputMode::OfferingFreeStrike(pending) => *pending,
+        other => {
+            app.input_mode = other;
+            return false;
+        }
+    };
+    let attack = match pending.movement.offer() {
+        Some(attack) => attack.clone(),
+        None => {
+            continue_move(app, pending);
+            return false;
+        }
+    };
+    match pending.movement.answer(&mut app.encounter, &app.definitions.abilities, take, &mut app.dice) {
+        Ok(Some(strike)) => {
+            let message = format!(
+                "{} makes a free strike against {}: {:?}, {} damage",
+                app.encounter.name_of(strike.attacker),
+                app.encounter.name_of(strike.target),
+                strike.resolution.tier,
+                strike.resolution.damage
+            );
+            app.log(message);
+        }
+        Ok(None) => app.log(format!("{} lets {} go", app.encounter.name_of(attack.attacker), app.encounter.name_of(attack.target))),
+        Err(e) => {
+            abandon_turn(app, pending.before, e.to_string());
+            return false;
+        }
+    }
+    continue_move(app, pending);
+    false
+}
+
+pub fn handle_movement_input(app: &mut App, key: KeyCode) -> bool {
+    let (cursor, kind) = match app.input_mode {
+        InputMode::Moving { cursor, kind } => (cursor, kind),
+        _ => return false,
+    };
+    let (dx, dy) = match key {
+        KeyCode::Char('q') => return true,
+        KeyCode::Char('x') | KeyCode::Esc => {
+            app.input_mode = InputMode::TakingTurn;
+            app.log("Movement cancelled".to_string());
+            return false;
+        }
+        KeyCode::Char('s') => {
+            let kind = match kind {
+                MovementKind::Move => MovementKind::Shift,
+                MovementKind::Shift => MovementKind::Move,
+            };
+            app.input_mode = InputMode::Moving { cursor, kind };
+            app.log(format!("Movement: {:?}", kind));
+            return false;
+        }
+        KeyCode::Enter => {
+            move_current_creature(app, cursor, kind);
+            return false;
+        }
+        KeyCode::Char('t') => {
+            teleport_current_creature(app, cursor);
+            return false;
+        }
+        KeyCode::Left | KeyCode::Char('h') => (-1, 0),
+        KeyCode::Right | KeyCode::Char('l') => (1, 0),
+        KeyCode::Up | KeyCode::Char('k') => (0, -1),
+        KeyCode::Down | KeyCode::Char('j') => (0, 1),
+        _ => return false,
+    };
+
+    let next = cursor.offset(dx, dy);
+    if app.encounter.battlefield().is_some_and(|battlefield| battlefield.grid().contains(next)) {
+        app.input_mode = InputMode::Moving { cursor: next, kind };
+    }
+    false
+}
+
+fn move_current_creature(app: &mut App, destination: Position, kind: MovementKind) {
+    let mover = app.encounter.state().and_then(|state| state.current_turn()).map(|turn| turn.entity);
+    let mover = match mover {
+        Some(mover) => mover,
+        None => {
+            app.input_mode = InputMode::TakingTurn;
+            return;
+        }
+    };
+    let before = app.snapshot();
+    match start_along_cheapest_path(app, mover, destination, kind) {
+        Ok(movement) => continue_move(app, PendingMove { movement, before, plan: None }),
+        Err(e) => app.log(format!("Error: {}", e)),
+    }
+}
+
+/// Teleport the creature taking its turn to the cursor, for creatures with a teleport speed
+fn teleport_current_creature(app: &mut App, destination: Position) {
+    let mover = match app.encounter.state().and_then(|state| state.current_turn()) {
+        Some(turn) => turn.entity,
+        None => {
+            app.input_mode = InputMode::TakingTurn;
+            return;
+        }
+    };
+    let before = app.snapshot();
+    match teleport_creature(&mut app.encounter, mover, destination) {
+        Ok(()) => {
+            app.remember(before);
+            app.log(format!("{} teleported to ({}, {})", app.encounter.name_of(mover), destination.x, destination.y));
+            app.input_mode = InputMode::TakingTurn;
+        }
+        Err(e) => app.log(format!("Error: {}", e)),
+    }
+}
+
+/// Walk the cheapest path to a destination, resolving any free strikes it provokes along the way
+fn start_along_cheapest_path(
+    app: &mut App,
+    mover: EntityId,
+    destination: Position,
+    kind: MovementKind,
+) -> Result<CreatureMove, String> {
+    let speed = match app.encounter.entity(mover) {
+        Some(entity) => entity.definition().speed,
+        None => return Err(format!("Entity {} not found", mover)),
+    };
+    let name = app.encounter.name_of(mover);
+    let allowance = movement_allowance(speed, kind);
+
+    let battlefield = app.encounter.battlefield().ok_or_else(|| format!("{} is not on the battlefield", name))?;
+    let path = match battlefield.cheapest_path(mover, destination, kind, allowance).map_err(|e| e.to_string())? {
+        Some((path, _)) if path.is_empty() => return Err(format!("{} is already there", name)),
+        Some((path, _)) => path,
+        None => {
+            return Err(format!(
+                "{} cannot end their movement at ({}, {}) with {} movement",
+                name, destination.x, destination.y, allowance
+            ));
+        }
+    };
+
+    // Moving spends the move action, or a main action once it is gone, and refuses paths beyond the mover's speed
+    CreatureMove::start(&mut app.encounter, mover, path, kind).map_err(|e| e.to_string())
 }
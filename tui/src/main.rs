@@ -1,8 +1,9 @@
 mod app;
 mod handlers;
 mod ui;
+mod watch;
 
-use std::{io::{self, Stdout, stdout}};
+use std::{io::{self, Stdout, stdout}, time::Duration};
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyEventKind},
     execute,
@@ -13,9 +14,24 @@ use ratatui::{
     Terminal,
 };
 use app::{App, InputMode};
-use handlers::{handle_creation_input, handle_turn_input, handle_text_input, handle_removal_input, handle_monster_selection, handle_hero_selection, handle_ability_selection, handle_target_selection};
+use watch::ContentWatcher;
+
+/// How often the content is checked for changes when watching
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+use handlers::{handle_creation_input, handle_turn_input, handle_text_input, handle_removal_input, handle_rename_selection, handle_monster_selection, handle_hero_selection, handle_ability_selection, handle_target_selection, handle_movement_input, handle_free_strike_input, handle_test_input, handle_montage_setup, handle_montage_input};
 
 fn main() -> io::Result<()> {
+    // Load and check content before taking over the terminal, so every problem stays readable
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let watch = args.iter().any(|arg| arg == "--watch");
+    let app = match app::content_roots(&args).and_then(App::new) {
+        Ok(app) => app,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = stdout();
@@ -23,7 +39,7 @@ fn main() -> io::Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    match run(&mut terminal) {
+    match run(&mut terminal, app, watch) {
         Ok(()) => (),
         Err(e) => {
             eprintln!("Error: {}", e);
@@ -42,51 +58,77 @@ fn main() -> io::Result<()> {
     Ok(())
 }
 
-fn run(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<(), String> {
-    let mut app = match App::new() {
-        Ok(app) => app,
-        Err(e) => {
-            return Err(e);
-        }
-    };
+fn run(terminal: &mut Terminal<CrosstermBackend<Stdout>>, mut app: App, watch: bool) -> Result<(), String> {
     let mut should_quit = false;
+    let mut watcher = watch.then(|| ContentWatcher::new(app.definitions.roots.clone()));
 
     while !should_quit {
         terminal.draw(|f| ui::render_ui(f, &app)).map_err(|e| e.to_string())?;
 
-        if let Event::Key(key) = event::read().map_err(|e| e.to_string())? {
-            if key.kind == KeyEventKind::Press {
-                // Handle log view toggle (works in any mode)
-                if let crossterm::event::KeyCode::Char('`') = key.code {
-                    app.log_view_expanded = !app.log_view_expanded;
-                    continue;
+        if let Some(watcher) = watcher.as_mut()
+            && !event::poll(WATCH_INTERVAL).map_err(|e| e.to_string())? {
+            if watcher.changed() {
+                app.reload_content();
+            }
+            continue;
+        }
+
+        if let Event::Key(key) = event::read().map_err(|e| e.to_string())?
+            && key.kind == KeyEventKind::Press {
+            // Handle log view toggle (works in any mode)
+            if let crossterm::event::KeyCode::Char('`') = key.code {
+                app.log_view_expanded = !app.log_view_expanded;
+                continue;
+            }
+
+            // Reload content (works in any mode, and doesn't clash with typing)
+            if let crossterm::event::KeyCode::F(5) = key.code {
+                app.reload_content();
+                continue;
+            }
+            
+            match app.input_mode {
+                InputMode::CreatingCombat => {
+                    should_quit = handle_creation_input(&mut app, key.code);
+                }
+                InputMode::TakingTurn => {
+                    should_quit = handle_turn_input(&mut app, key.code);
+                }
+                InputMode::TextInput(_) => {
+                    should_quit = handle_text_input(&mut app, key.code);
+                }
+                InputMode::RemovingEntity => {
+                    should_quit = handle_removal_input(&mut app, key.code);
+                }
+                InputMode::RenamingEntity => {
+                    should_quit = handle_rename_selection(&mut app, key.code);
+                }
+                InputMode::SelectingMonsterDefinition => {
+                    should_quit = handle_monster_selection(&mut app, key.code);
+                }
+                InputMode::SelectingHeroDefinition => {
+                    should_quit = handle_hero_selection(&mut app, key.code);
+                }
+                InputMode::SelectingAbility => {
+                    should_quit = handle_ability_selection(&mut app, key.code);
+                }
+                InputMode::SelectingTarget { .. } => {
+                    should_quit = handle_target_selection(&mut app, key.code);
+                }
+                InputMode::Moving { .. } => {
+                    should_quit = handle_movement_input(&mut app, key.code);
+                }
+                InputMode::OfferingFreeStrike(_) => {
+                    should_quit = handle_free_strike_input(&mut app, key.code);
+                }
+                InputMode::CallingTest(_) => {
+                    should_quit = handle_test_input(&mut app, key.code);
+                }
+                InputMode::SettingUpMontage(_) => {
+                    should_quit = handle_montage_setup(&mut app, key.code);
                 }
-                
-                match app.input_mode {
-                    InputMode::CreatingCombat => {
-                        should_quit = handle_creation_input(&mut app, key.code);
-                    }
-                    InputMode::TakingTurn => {
-                        should_quit = handle_turn_input(&mut app, key.code);
-                    }
-                    InputMode::TextInput(_) => {
-                        should_quit = handle_text_input(&mut app, key.code);
-                    }
-                    InputMode::RemovingEntity => {
-                        should_quit = handle_removal_input(&mut app, key.code);
-                    }
-                    InputMode::SelectingMonsterDefinition => {
-                        should_quit = handle_monster_selection(&mut app, key.code);
-                    }
-                    InputMode::SelectingHeroDefinition => {
-                        should_quit = handle_hero_selection(&mut app, key.code);
-                    }
-                    InputMode::SelectingAbility => {
-                        should_quit = handle_ability_selection(&mut app, key.code);
-                    }
-                    InputMode::SelectingTarget { .. } => {
-                        should_quit = handle_target_selection(&mut app, key.code);
-                    }
+                InputMode::RunningMontage(_) => {
+                    should_quit = handle_montage_input(&mut app, key.code);
                 }
             }
         }
@@ -1,4 +1,4 @@
-use odsr_engine::CombatState;
+use odsr_engine::{Ability, BaneState, CombatState, EdgeState, EntityId, RollContext, SURGES, TierOdds, strike_odds};
 use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span},
@@ -9,6 +9,55 @@ use crate::app::App;
 
 use super::entities::render_available_entities;
 
+/// Tier chances as percentages, critical hits apart from the rest of tier 3
+fn describe_odds(odds: &TierOdds) -> String {
+    format!(
+        "T1 {:.0}% / T2 {:.0}% / T3 {:.0}% / crit {:.0}%",
+        odds.tier1 * 100.0, odds.tier2 * 100.0, odds.tier3 * 100.0, odds.critical * 100.0
+    )
+}
+
+/// Range, roll bonus, damage by tier and average damage of an ability used without edges or banes
+fn ability_summary(ability: &Ability, context: &RollContext) -> String {
+    let damage: Vec<String> = ability.power_rolls().iter().map(|roll| roll.damage.to_string()).collect();
+    let odds = ability.odds(context, EdgeState::None, BaneState::None);
+    format!(
+        "  range {}, roll {:+}, damage {}, avg {:.1}",
+        ability.range,
+        ability.roll_bonus(&context.characteristics),
+        damage.join(" / "),
+        ability.expected_damage(&odds, context)
+    )
+}
+
+/// The expected damage of the creature taking its turn against a target, with the edges the battlefield gives
+/// and the surges being spent
+pub fn strike_note(app: &App, state: &CombatState, ability_name: &str, surges: i32, target: EntityId) -> String {
+    let actor = match state.current_turn() {
+        Some(turn) if turn.entity != target => turn.entity,
+        _ => return String::new(),
+    };
+    let (ability, battlefield) = match (app.definitions.abilities.get(ability_name), app.encounter.battlefield()) {
+        (Some(ability), Some(battlefield)) => (ability, battlefield),
+        _ => return String::new(),
+    };
+    let in_range = match (battlefield.placement(actor), battlefield.placement(target)) {
+        (Some(placement), Some(other)) => placement.distance_to(other) <= ability.range,
+        _ => false,
+    };
+    if !in_range {
+        return "  out of range".to_string();
+    }
+    let context = app.encounter.roll_context(actor).with_count(SURGES, surges);
+    match strike_odds(battlefield, ability, actor, target, &context) {
+        Ok(odds) => {
+            let damage = ability.expected_damage(&odds, &context);
+            format!("  avg {:.1} damage ({})", damage, describe_odds(&odds))
+        }
+        Err(_) => String::new(),
+    }
+}
+
 
 pub fn render_abilities(state: &CombatState, app: &App) -> Paragraph<'static> {
     use crate::app::InputMode;
@@ -20,8 +69,8 @@ pub fn render_abilities(state: &CombatState, app: &App) -> Paragraph<'static> {
     let current_turn = state.current_turn();
     
     if let Some(turn) = current_turn {
-        let entity_name = &turn.entity_name;
-        let entity = match app.entities.get(entity_name){
+        let entity_name = app.encounter.name_of(turn.entity);
+        let entity = match app.encounter.entity(turn.entity){
             Some(entity) => entity,
             None => {
                 return Paragraph::new(Line::from(format!("Error: Entity {} not found", entity_name)));
@@ -49,9 +98,10 @@ pub fn render_abilities(state: &CombatState, app: &App) -> Paragraph<'static> {
                 if let Some(ability) = app.definitions.abilities.get(ability_name) {
                     text.push(Line::from(vec![
                         Span::styled(
-                            format!("{}", ability.name),
+                            ability.name.to_string(),
                             Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
                         ),
+                        Span::styled(ability_summary(ability, &app.encounter.roll_context(turn.entity)), Style::default().fg(Color::DarkGray)),
                     ]));
                 } else {
                     text.push(Line::from(vec![
@@ -79,7 +129,7 @@ pub fn render_abilities(state: &CombatState, app: &App) -> Paragraph<'static> {
             .wrap(Wrap { trim: true })
     } else {
         // Fallback (shouldn't happen, but just in case)
-        render_available_entities(state)
+        render_available_entities(&app.encounter, state)
     }
 }
 
@@ -87,8 +137,8 @@ fn render_ability_selection(state: &CombatState, app: &App) -> Paragraph<'static
     let current_turn = state.current_turn();
     
     if let Some(turn) = current_turn {
-        let entity_name = &turn.entity_name;
-        let entity = match app.entities.get(entity_name){
+        let entity_name = app.encounter.name_of(turn.entity);
+        let entity = match app.encounter.entity(turn.entity){
             Some(entity) => entity,
             None => {
                 return Paragraph::new(Line::from(format!("Error: Entity {} not found", entity_name)));
@@ -120,9 +170,10 @@ fn render_ability_selection(state: &CombatState, app: &App) -> Paragraph<'static
                             Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
                         ),
                         Span::styled(
-                            format!("{}", ability.name),
+                            ability.name.to_string(),
                             Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
                         ),
+                        Span::styled(ability_summary(ability, &app.encounter.roll_context(turn.entity)), Style::default().fg(Color::DarkGray)),
                     ]));
                 } else {
                     text.push(Line::from(vec![
@@ -149,6 +200,6 @@ fn render_ability_selection(state: &CombatState, app: &App) -> Paragraph<'static
             .block(Block::default().borders(Borders::ALL).title("Select Ability"))
             .wrap(Wrap { trim: true })
     } else {
-        render_available_entities(state)
+        render_available_entities(&app.encounter, state)
     }
 }
\ No newline at end of file
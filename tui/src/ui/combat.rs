@@ -1,11 +1,11 @@
-use odsr_engine::{CombatState, TurnSide};
+use odsr_engine::{CombatState, Encounter, EntityId, TurnSide};
 use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph, Wrap},
 };
 
-pub fn render_combat_state(state: &CombatState) -> Paragraph<'static> {
+pub fn render_combat_state(encounter: &Encounter, state: &CombatState) -> Paragraph<'static> {
     let current_side = state.current_side();
     let round = state.round();
     let all_pcs = state.all_pcs();
@@ -34,7 +34,7 @@ pub fn render_combat_state(state: &CombatState) -> Paragraph<'static> {
     // Show current turn in progress
     if let Some(turn) = current_turn {
         let side = turn.side;
-        let name = &turn.entity_name;
+        let name = encounter.name_of(turn.entity);
         text.push(Line::from(vec![
             Span::styled("Turn in progress: ", Style::default().fg(Color::White)),
             Span::styled(
@@ -42,6 +42,20 @@ pub fn render_combat_state(state: &CombatState) -> Paragraph<'static> {
                 Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
             ),
         ]));
+        // An extra main action from a critical hit stands out
+        let main_style = if turn.granted_main_actions > 0 {
+            Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Cyan)
+        };
+        text.push(Line::from(vec![
+            Span::styled("Main actions: ", Style::default().fg(Color::White)),
+            Span::styled(turn.main_actions.to_string(), main_style),
+            Span::styled("  Maneuvers: ", Style::default().fg(Color::White)),
+            Span::styled(turn.maneuvers.to_string(), Style::default().fg(Color::Cyan)),
+            Span::styled("  Moves: ", Style::default().fg(Color::White)),
+            Span::styled(turn.move_actions.to_string(), Style::default().fg(Color::Cyan)),
+        ]));
     }
     else {
         text.push(Line::from(Span::styled(
@@ -57,11 +71,11 @@ pub fn render_combat_state(state: &CombatState) -> Paragraph<'static> {
     )));
 
     // Display all PCs with their status
-    let mut pc_vec: Vec<&String> = all_pcs.iter().collect();
-    pc_vec.sort();
+    let mut pc_vec: Vec<&EntityId> = all_pcs.iter().collect();
+    pc_vec.sort_by_key(|pc| encounter.name_of(**pc));
     for pc in pc_vec {
         let style = if let Some(turn) = current_turn {
-            if turn.side == TurnSide::PC && &turn.entity_name == pc {
+            if turn.side == TurnSide::PC && turn.entity == *pc {
                 Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
             } else if pc_taken.contains(pc) {
                 Style::default().fg(Color::DarkGray)
@@ -74,7 +88,7 @@ pub fn render_combat_state(state: &CombatState) -> Paragraph<'static> {
             Style::default().fg(Color::White)
         };
         text.push(Line::from(
-            Span::styled(format!("  • {}", pc), style),
+            Span::styled(format!("  • {}", encounter.name_of(*pc)), style),
         ));
     }
 
@@ -85,11 +99,11 @@ pub fn render_combat_state(state: &CombatState) -> Paragraph<'static> {
     )));
 
     // Display all NPCs with their status
-    let mut npc_vec: Vec<&String> = all_npcs.iter().collect();
-    npc_vec.sort();
+    let mut npc_vec: Vec<&EntityId> = all_npcs.iter().collect();
+    npc_vec.sort_by_key(|npc| encounter.name_of(**npc));
     for npc in npc_vec {
         let style = if let Some(turn) = current_turn {
-            if turn.side == TurnSide::NPC && &turn.entity_name == npc {
+            if turn.side == TurnSide::NPC && turn.entity == *npc {
                 Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
             } else if npc_taken.contains(npc) {
                 Style::default().fg(Color::DarkGray)
@@ -102,7 +116,7 @@ pub fn render_combat_state(state: &CombatState) -> Paragraph<'static> {
             Style::default().fg(Color::White)
         };
         text.push(Line::from(
-            Span::styled(format!("  • {}", npc), style),
+            Span::styled(format!("  • {}", encounter.name_of(*npc)), style),
         ));
     }
 
@@ -116,47 +130,39 @@ pub fn render_combat_state(state: &CombatState) -> Paragraph<'static> {
             "Press 'c' to cancel the current turn",
             Style::default().fg(Color::Yellow),
         )));
+        text.push(Line::from(Span::styled(
+            "Press 'm' to move",
+            Style::default().fg(Color::Yellow),
+        )));
+        text.push(Line::from(Span::styled(
+            "Press 'n' to rename someone",
+            Style::default().fg(Color::Yellow),
+        )));
+        if current_turn.is_some_and(|turn| turn.side == TurnSide::NPC) {
+            text.push(Line::from(Span::styled(
+                "Press 'A' to play this turn automatically",
+                Style::default().fg(Color::Yellow),
+            )));
+        }
     } else {
         text.push(Line::from(Span::styled(
             "Press a number (1-9) to start a turn for that entity",
             Style::default().fg(Color::Gray),
         )));
+        if state.current_side() == TurnSide::NPC {
+            text.push(Line::from(Span::styled(
+                "Press 'A' to play the next monster's turn automatically",
+                Style::default().fg(Color::Gray),
+            )));
+        }
     }
+    text.push(Line::from(Span::styled(
+        "Press 'd' to roll dice, 't' to call for a test, 'g' for a montage",
+        Style::default().fg(Color::Gray),
+    )));
     
 
     Paragraph::new(text)
         .block(Block::default().borders(Borders::ALL).title("Combat State"))
         .wrap(Wrap { trim: true })
 }
-
-pub fn render_instructions_combat() -> Paragraph<'static> {
-    let text = vec![
-        Line::from(Span::styled(
-            "Combat Instructions",
-            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
-        )),
-        Line::from(""),
-        Line::from(Span::styled(
-            "Turn Management:",
-            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
-        )),
-        Line::from("• Press a number (1-9) to start a turn"),
-        Line::from("• Press 'e' to end the current turn"),
-        Line::from("• Press 'c' to cancel the current turn"),
-        Line::from("• Press 'r' to complete round"),
-        Line::from(""),
-        Line::from(Span::styled(
-            "Reinforcements/Deaths:",
-            Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
-        )),
-        Line::from("• Press 'p' to add a PC"),
-        Line::from("• Press 'b' to add an NPC"),
-        Line::from("• Press 'x' to remove an PC or NPC"),
-        Line::from(""),
-        Line::from("• Press 'q' to quit"),
-    ];
-
-    Paragraph::new(text)
-        .block(Block::default().borders(Borders::ALL).title("Controls"))
-        .wrap(Wrap { trim: true })
-}
\ No newline at end of file
@@ -1,11 +1,11 @@
-use odsr_engine::{CombatParameters, CombatState, TurnSide};
+use odsr_engine::{CombatParameters, CombatState, Encounter, EntityId, TurnSide};
 use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph, Wrap},
 };
 
-pub fn render_available_entities(state: &CombatState) -> Paragraph<'static> {
+pub fn render_available_entities(encounter: &Encounter, state: &CombatState) -> Paragraph<'static> {
     let available = state.available();
     let current_side = state.current_side();
     
@@ -40,7 +40,7 @@ pub fn render_available_entities(state: &CombatState) -> Paragraph<'static> {
                 format!("[{}] ", number),
                 Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
             ),
-            Span::styled(entity.clone(), Style::default().fg(Color::White)),
+            Span::styled(encounter.name_of(*entity), Style::default().fg(Color::White)),
         ]));
     }
 
@@ -49,13 +49,14 @@ pub fn render_available_entities(state: &CombatState) -> Paragraph<'static> {
         .wrap(Wrap { trim: true })
 }
 
-pub fn render_all_entities(state: &CombatState) -> Paragraph<'static> {
+/// Everyone in the combat, numbered for picking one to remove or rename
+pub fn render_all_entities(encounter: &Encounter, state: &CombatState, prompt: &'static str, title: &'static str) -> Paragraph<'static> {
     let all_pcs = state.all_pcs();
     let all_npcs = state.all_npcs();
     let current_turn = state.current_turn();
     
     let mut items: Vec<Line> = vec![Line::from(Span::styled(
-        "Select entity to remove:",
+        prompt,
         Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
     ))];
     
@@ -66,11 +67,11 @@ pub fn render_all_entities(state: &CombatState) -> Paragraph<'static> {
     )));
     
     let mut entity_index = 0;
-    let pc_vec: Vec<&String> = all_pcs.iter().collect();
+    let pc_vec: Vec<&EntityId> = all_pcs.iter().collect();
     for pc in &pc_vec {
         entity_index += 1;
         let style = if let Some(turn) = current_turn {
-            if turn.side == TurnSide::PC && turn.entity_name == **pc {
+            if turn.side == TurnSide::PC && turn.entity == **pc {
                 Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
             } else {
                 Style::default().fg(Color::White)
@@ -83,7 +84,7 @@ pub fn render_all_entities(state: &CombatState) -> Paragraph<'static> {
                 format!("[{}] ", entity_index),
                 Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
             ),
-            Span::styled((*pc).clone(), style),
+            Span::styled(encounter.name_of(**pc), style),
         ]));
     }
     
@@ -93,11 +94,11 @@ pub fn render_all_entities(state: &CombatState) -> Paragraph<'static> {
         Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
     )));
     
-    let npc_vec: Vec<&String> = all_npcs.iter().collect();
+    let npc_vec: Vec<&EntityId> = all_npcs.iter().collect();
     for npc in &npc_vec {
         entity_index += 1;
         let style = if let Some(turn) = current_turn {
-            if turn.side == TurnSide::NPC && turn.entity_name == **npc {
+            if turn.side == TurnSide::NPC && turn.entity == **npc {
                 Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
             } else {
                 Style::default().fg(Color::White)
@@ -110,7 +111,7 @@ pub fn render_all_entities(state: &CombatState) -> Paragraph<'static> {
                 format!("[{}] ", entity_index),
                 Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
             ),
-            Span::styled((*npc).clone(), style),
+            Span::styled(encounter.name_of(**npc), style),
         ]));
     }
     
@@ -121,11 +122,12 @@ pub fn render_all_entities(state: &CombatState) -> Paragraph<'static> {
     )));
     
     Paragraph::new(items)
-        .block(Block::default().borders(Borders::ALL).title("Remove Entity"))
+        .block(Block::default().borders(Borders::ALL).title(title))
         .wrap(Wrap { trim: true })
 }
 
-pub fn render_all_entities_for_target(state: &CombatState) -> Paragraph<'static> {
+/// Every combatant, numbered for targeting, each followed by a note such as the expected damage against them
+pub fn render_all_entities_for_target(encounter: &Encounter, state: &CombatState, note: impl Fn(EntityId) -> String) -> Paragraph<'static> {
     let all_pcs = state.all_pcs();
     let all_npcs = state.all_npcs();
     let current_turn = state.current_turn();
@@ -142,11 +144,11 @@ pub fn render_all_entities_for_target(state: &CombatState) -> Paragraph<'static>
     )));
     
     let mut entity_index = 0;
-    let pc_vec: Vec<&String> = all_pcs.iter().collect();
+    let pc_vec: Vec<&EntityId> = all_pcs.iter().collect();
     for pc in &pc_vec {
         entity_index += 1;
         let style = if let Some(turn) = current_turn {
-            if turn.side == TurnSide::PC && turn.entity_name == **pc {
+            if turn.side == TurnSide::PC && turn.entity == **pc {
                 Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
             } else {
                 Style::default().fg(Color::White)
@@ -159,7 +161,8 @@ pub fn render_all_entities_for_target(state: &CombatState) -> Paragraph<'static>
                 format!("[{}] ", entity_index),
                 Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
             ),
-            Span::styled((*pc).clone(), style),
+            Span::styled(encounter.name_of(**pc), style),
+            Span::styled(note(**pc), Style::default().fg(Color::DarkGray)),
         ]));
     }
     
@@ -169,11 +172,11 @@ pub fn render_all_entities_for_target(state: &CombatState) -> Paragraph<'static>
         Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
     )));
     
-    let npc_vec: Vec<&String> = all_npcs.iter().collect();
+    let npc_vec: Vec<&EntityId> = all_npcs.iter().collect();
     for npc in &npc_vec {
         entity_index += 1;
         let style = if let Some(turn) = current_turn {
-            if turn.side == TurnSide::NPC && turn.entity_name == **npc {
+            if turn.side == TurnSide::NPC && turn.entity == **npc {
                 Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
             } else {
                 Style::default().fg(Color::White)
@@ -186,7 +189,8 @@ pub fn render_all_entities_for_target(state: &CombatState) -> Paragraph<'static>
                 format!("[{}] ", entity_index),
                 Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
             ),
-            Span::styled((*npc).clone(), style),
+            Span::styled(encounter.name_of(**npc), style),
+            Span::styled(note(**npc), Style::default().fg(Color::DarkGray)),
         ]));
     }
     
@@ -201,7 +205,7 @@ pub fn render_all_entities_for_target(state: &CombatState) -> Paragraph<'static>
         .wrap(Wrap { trim: true })
 }
 
-pub fn render_all_entities_setup(params: &CombatParameters) -> Paragraph<'static> {
+pub fn render_all_entities_setup(encounter: &Encounter, params: &CombatParameters) -> Paragraph<'static> {
     let all_pcs = params.pcs();
     let all_npcs = params.npcs();
     
@@ -217,7 +221,7 @@ pub fn render_all_entities_setup(params: &CombatParameters) -> Paragraph<'static
     )));
     
     let mut entity_index = 0;
-    let pc_vec: Vec<&String> = all_pcs.iter().collect();
+    let pc_vec: Vec<&EntityId> = all_pcs.iter().collect();
     for pc in &pc_vec {
         entity_index += 1;
         items.push(Line::from(vec![
@@ -225,7 +229,7 @@ pub fn render_all_entities_setup(params: &CombatParameters) -> Paragraph<'static
                 format!("[{}] ", entity_index),
                 Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
             ),
-            Span::styled((*pc).clone(), Style::default().fg(Color::White)),
+            Span::styled(encounter.name_of(**pc), Style::default().fg(Color::White)),
         ]));
     }
     
@@ -235,7 +239,7 @@ pub fn render_all_entities_setup(params: &CombatParameters) -> Paragraph<'static
         Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
     )));
     
-    let npc_vec: Vec<&String> = all_npcs.iter().collect();
+    let npc_vec: Vec<&EntityId> = all_npcs.iter().collect();
     for npc in &npc_vec {
         entity_index += 1;
         items.push(Line::from(vec![
@@ -243,7 +247,7 @@ pub fn render_all_entities_setup(params: &CombatParameters) -> Paragraph<'static
                 format!("[{}] ", entity_index),
                 Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
             ),
-            Span::styled((*npc).clone(), Style::default().fg(Color::White)),
+            Span::styled(encounter.name_of(**npc), Style::default().fg(Color::White)),
         ]));
     }
     
@@ -0,0 +1,89 @@
+use odsr_engine::{Battlefield, Encounter, MovementKind, MovementMap, Position, Terrain, TurnSide, movement_allowance};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+};
+use crate::app::App;
+
+/// The symbol drawn for a square: the first letter of its occupant's name, or its terrain
+fn square_symbol(encounter: &Encounter, battlefield: &Battlefield, position: Position) -> (String, Style) {
+    if let Some(occupant) = battlefield.occupant(position) {
+        let letter = encounter.name_of(occupant).chars().next().unwrap_or('?').to_ascii_uppercase();
+        let color = match battlefield.placement(occupant).map(|placement| placement.side) {
+            Some(TurnSide::PC) => Color::Cyan,
+            _ => Color::Magenta,
+        };
+        return (letter.to_string(), Style::default().fg(color).add_modifier(Modifier::BOLD));
+    }
+    match battlefield.grid().terrain(position) {
+        Terrain::Wall => ("#".to_string(), Style::default().fg(Color::Gray)),
+        Terrain::Difficult => ("~".to_string(), Style::default().fg(Color::Yellow)),
+        Terrain::Water => ("w".to_string(), Style::default().fg(Color::Blue)),
+        Terrain::Floor => match battlefield.grid().elevation(position) {
+            0 => (".".to_string(), Style::default().fg(Color::DarkGray)),
+            elevation => (elevation.min(9).to_string(), Style::default().fg(Color::White)),
+        },
+    }
+}
+
+/// Draw the battlefield with the squares the current creature can reach highlighted
+pub fn render_movement_map(app: &App, cursor: Position, kind: MovementKind) -> Paragraph<'static> {
+    let battlefield = match app.encounter.battlefield() {
+        Some(battlefield) => battlefield,
+        None => {
+            return Paragraph::new("No battlefield")
+                .block(Block::default().borders(Borders::ALL).title("Battlefield"));
+        }
+    };
+    let mover = app.encounter.state().and_then(|state| state.current_turn()).map(|turn| turn.entity);
+    let allowance = mover
+        .and_then(|mover| app.encounter.entity(mover))
+        .map(|entity| movement_allowance(entity.definition().speed, kind))
+        .unwrap_or(0);
+    let movement_map: Option<MovementMap> = mover
+        .and_then(|mover| battlefield.movement_map(mover, Some(allowance), kind).ok());
+
+    let mut text = vec![];
+    for y in 0..battlefield.grid().height() {
+        let mut spans = vec![];
+        for x in 0..battlefield.grid().width() {
+            let position = Position::new(x, y);
+            let (symbol, mut style) = square_symbol(&app.encounter, battlefield, position);
+            if movement_map.as_ref().is_some_and(|map| map.can_end_at(position) && map.origin() != position) {
+                style = style.bg(Color::Blue);
+            }
+            if position == cursor {
+                style = style.bg(Color::Yellow).fg(Color::Black);
+            }
+            spans.push(Span::styled(symbol, style));
+            spans.push(Span::raw(" "));
+        }
+        text.push(Line::from(spans));
+    }
+
+    text.push(Line::from(""));
+    text.push(Line::from(vec![
+        Span::styled(format!("{:?}", kind), Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+        Span::raw(format!(" up to {} squares", allowance)),
+    ]));
+    let cursor_info = match movement_map.as_ref().and_then(|map| map.get(cursor)) {
+        Some(square) if square.can_end => {
+            let mut info = format!("({}, {}): costs {}", cursor.x, cursor.y, square.cost);
+            if !square.provokes.is_empty() {
+                info.push_str(&format!(", provokes {}", square.provokes.iter().map(|id| app.encounter.name_of(*id)).collect::<Vec<_>>().join(", ")));
+            }
+            info
+        }
+        _ => format!("({}, {}): out of reach", cursor.x, cursor.y),
+    };
+    text.push(Line::from(cursor_info));
+    text.push(Line::from(""));
+    text.push(Line::from(Span::styled(
+        "Arrows/hjkl move the cursor, 's' toggles shifting, Enter moves, 'x' cancels",
+        Style::default().fg(Color::Yellow),
+    )));
+
+    Paragraph::new(text)
+        .block(Block::default().borders(Borders::ALL).title("Battlefield"))
+}
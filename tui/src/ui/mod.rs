@@ -1,17 +1,20 @@
 mod abilities;
 mod combat;
 mod entities;
+mod map;
+mod montage;
 mod setup;
+mod skill_test;
 mod status;
 
-use odsr_engine::{CombatParameters,  TurnSide};
+use odsr_engine::EncounterPhase;
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     widgets::{Block, Borders, Paragraph},
     Frame,
 };
-use crate::{app::{App, CombatMode, InputMode}, ui::entities::{render_all_entities_setup}};
+use crate::{app::{App, InputMode}, ui::entities::{render_all_entities_setup}};
 
 pub fn render_ui(f: &mut Frame, app: &App) {
     // If log view is expanded, show it as an overlay
@@ -45,34 +48,43 @@ pub fn render_ui(f: &mut Frame, app: &App) {
         .split(chunks[1]);
 
     // Left side: Combat state or creation
-    let left_content = match app.state {
-        Some(CombatMode::Active(ref state)) => combat::render_combat_state(state),
-        Some(CombatMode::Setup(ref params)) => setup::render_creation_ui(app, params),
-        None => {
-            let empty_params = CombatParameters::new(Vec::<String>::new(), Vec::<String>::new(), TurnSide::PC);
-            setup::render_creation_ui(app, &empty_params)
-        },
+    let left_content = match app.encounter.phase() {
+        EncounterPhase::Active(state) => combat::render_combat_state(&app.encounter, state),
+        EncounterPhase::Setup(params) => setup::render_creation_ui(app, params),
     };
     f.render_widget(left_content, main_chunks[0]);
 
     // Right side: Available entities, all entities (for removal), abilities (during turn), or instructions
-    let right_content = match (&app.state, &app.input_mode) {
-        (Some(CombatMode::Active(state)), InputMode::RemovingEntity) => {
-            entities::render_all_entities(state)
+    let right_content = match (app.encounter.phase(), &app.input_mode) {
+        (EncounterPhase::Active(state), InputMode::RemovingEntity) => {
+            entities::render_all_entities(&app.encounter, state, "Select entity to remove:", "Remove Entity")
         }
-        (Some(CombatMode::Active(state)), InputMode::SelectingTarget { .. }) => {
-            entities::render_all_entities_for_target(state)
+        (EncounterPhase::Active(state), InputMode::RenamingEntity) => {
+            entities::render_all_entities(&app.encounter, state, "Select entity to rename:", "Rename Entity")
         }
-        (Some(CombatMode::Active(state)), _) => {
+        (_, InputMode::CallingTest(call)) => skill_test::render_test_call(app, call),
+        (_, InputMode::SettingUpMontage(definition)) => montage::render_montage_setup(definition),
+        (_, InputMode::RunningMontage(step)) if app.montage.is_some() => {
+            montage::render_montage(app, app.montage.as_ref().expect("checked above"), step)
+        }
+        (EncounterPhase::Active(_), InputMode::Moving { cursor, kind }) => {
+            map::render_movement_map(app, *cursor, *kind)
+        }
+        (EncounterPhase::Active(state), InputMode::SelectingTarget { ability_name, surges }) => {
+            entities::render_all_entities_for_target(&app.encounter, state, |target| {
+                abilities::strike_note(app, state, ability_name, *surges, target)
+            })
+        }
+        (EncounterPhase::Active(state), _) => {
             // If a turn is in progress, show abilities; otherwise show available entities
             if state.current_turn().is_some() {
-                abilities::render_abilities(state, &app)
+                abilities::render_abilities(state, app)
             } else {
-                entities::render_available_entities(state)
+                entities::render_available_entities(&app.encounter, state)
             }
         }
-        (Some(CombatMode::Setup(params)), InputMode::RemovingEntity) => {
-            render_all_entities_setup(params)
+        (EncounterPhase::Setup(params), InputMode::RemovingEntity) => {
+            render_all_entities_setup(&app.encounter, params)
         }
         (_, InputMode::SelectingMonsterDefinition) => {
             setup::render_monster_definitions(app)
@@ -80,8 +92,7 @@ pub fn render_ui(f: &mut Frame, app: &App) {
         (_, InputMode::SelectingHeroDefinition) => {
             setup::render_hero_definitions(app)
         }
-        (Some(CombatMode::Setup(_)), _) => setup::render_instructions_setup(),
-        _ => combat::render_instructions_combat(),
+        (EncounterPhase::Setup(_), _) => setup::render_instructions_setup(),
     };
     f.render_widget(right_content, main_chunks[1]);
 
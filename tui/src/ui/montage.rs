@@ -0,0 +1,110 @@
+use odsr_engine::{Characteristic, Montage, MontageDefinition, MontageOutcome};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+
+use crate::app::{App, MontageStep};
+
+fn setting(label: &str, value: String, keys: &str) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(format!("{}: ", label), Style::default().fg(Color::White)),
+        Span::styled(value, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+        Span::styled(format!("  ({})", keys), Style::default().fg(Color::DarkGray)),
+    ])
+}
+
+pub fn render_montage_setup(definition: &MontageDefinition) -> Paragraph<'static> {
+    let text = vec![
+        Line::from(Span::styled("Set up the montage", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        setting("Difficulty", format!("{:?}", definition.difficulty), "e/m/h"),
+        setting("Success limit", definition.success_limit.to_string(), "s/S"),
+        setting("Failure limit", definition.failure_limit.to_string(), "f/F"),
+        setting("Rounds", definition.rounds.to_string(), "r/R"),
+        Line::from(""),
+        Line::from(Span::styled("Every hero in the encounter takes part", Style::default().fg(Color::Gray))),
+        Line::from(Span::styled("Press Enter to begin, 'x' to cancel", Style::default().fg(Color::Yellow))),
+    ];
+    Paragraph::new(text)
+        .block(Block::default().borders(Borders::ALL).title("Montage Test"))
+        .wrap(Wrap { trim: true })
+}
+
+/// Progress toward both limits, who has acted this round, and the choice being made
+pub fn render_montage(app: &App, montage: &Montage, step: &MontageStep) -> Paragraph<'static> {
+    let definition = montage.definition();
+    let mut text = vec![
+        Line::from(Span::styled(
+            format!("{} ({:?})", definition.name, definition.difficulty),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(vec![
+            Span::styled("Round: ", Style::default().fg(Color::White)),
+            Span::styled(format!("{}/{}", montage.round(), definition.rounds), Style::default().fg(Color::Cyan)),
+        ]),
+        Line::from(vec![
+            Span::styled("Successes: ", Style::default().fg(Color::White)),
+            Span::styled(format!("{}/{}", montage.successes(), definition.success_limit), Style::default().fg(Color::Green)),
+            Span::styled("  Failures: ", Style::default().fg(Color::White)),
+            Span::styled(format!("{}/{}", montage.failures(), definition.failure_limit), Style::default().fg(Color::Red)),
+        ]),
+        Line::from(""),
+    ];
+
+    if let Some(outcome) = montage.outcome() {
+        let color = match outcome {
+            MontageOutcome::TotalSuccess => Color::Green,
+            MontageOutcome::PartialSuccess => Color::Yellow,
+            MontageOutcome::Failure => Color::Red,
+        };
+        text.push(Line::from(Span::styled(
+            format!("Montage over: {}", outcome),
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        )));
+        text.push(Line::from(""));
+        text.push(Line::from(Span::styled("Press 'x' to close", Style::default().fg(Color::Yellow))));
+        return Paragraph::new(text)
+            .block(Block::default().borders(Borders::ALL).title("Montage Test"))
+            .wrap(Wrap { trim: true });
+    }
+
+    let waiting: Vec<_> = montage.waiting().collect();
+    for (index, hero) in montage.heroes().iter().enumerate() {
+        let style = if step.hero == Some(*hero) {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else if waiting.contains(hero) {
+            Style::default().fg(Color::White)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        text.push(Line::from(vec![
+            Span::styled(format!("[{}] ", index + 1), Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            Span::styled(app.encounter.name_of(*hero), style),
+        ]));
+    }
+    text.push(Line::from(""));
+
+    let hero = step.hero.and_then(|id| app.encounter.entity(id));
+    let prompt = match (hero, step.characteristic) {
+        (None, _) => "Pick a hero who hasn't acted this round".to_string(),
+        (Some(hero), None) => {
+            let scores: Vec<String> = Characteristic::ALL
+                .iter()
+                .map(|characteristic| format!("{} {:+}", characteristic.letter(), hero.definition().characteristics.get(*characteristic)))
+                .collect();
+            format!("Characteristic: m/a/r/i/p ({}), or 's' to sit this round out", scores.join(", "))
+        }
+        (Some(hero), Some(characteristic)) => {
+            let skills: Vec<String> = hero.definition().skills.iter().enumerate().map(|(index, skill)| format!("[{}] {}", index + 1, skill)).collect();
+            format!("{:?} test. Relevant skill: [0] none {}", characteristic, skills.join(" "))
+        }
+    };
+    text.push(Line::from(Span::styled(prompt, Style::default().fg(Color::Yellow))));
+    text.push(Line::from(Span::styled("Press 'x' to abandon the montage", Style::default().fg(Color::Gray))));
+
+    Paragraph::new(text)
+        .block(Block::default().borders(Borders::ALL).title("Montage Test"))
+        .wrap(Wrap { trim: true })
+}
@@ -1,4 +1,4 @@
-use odsr_engine::CombatParameters;
+use odsr_engine::{CombatParameters, EntityId};
 use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span},
@@ -6,7 +6,7 @@ use ratatui::{
 };
 use crate::app::App;
 
-pub fn render_creation_ui(_app: &App, params: &CombatParameters) -> Paragraph<'static> {
+pub fn render_creation_ui(app: &App, params: &CombatParameters) -> Paragraph<'static> {
     let mut text = vec![
         Line::from(Span::styled(
             "Create a new Combat",
@@ -14,6 +14,19 @@ pub fn render_creation_ui(_app: &App, params: &CombatParameters) -> Paragraph<'s
         )),
         Line::from(""),
     ];
+
+    let map = app.selected_map.as_ref().and_then(|key| app.definitions.maps.get(key));
+    text.push(Line::from(vec![
+        Span::styled("Map: ", Style::default().fg(Color::White)),
+        match map {
+            Some(map) => Span::styled(
+                format!("{} ({}x{})", map.name, map.width, map.height),
+                Style::default().fg(Color::Green),
+            ),
+            None => Span::styled("(none)", Style::default().fg(Color::DarkGray)),
+        },
+    ]));
+    text.push(Line::from(""));
     
     text.push(Line::from(Span::styled(
         "PCs in combat:",
@@ -26,11 +39,11 @@ pub fn render_creation_ui(_app: &App, params: &CombatParameters) -> Paragraph<'s
             Style::default().fg(Color::DarkGray),
         )));
     } else {
-        let pc_vec: Vec<&String> = pcs.iter().collect();
+        let pc_vec: Vec<&EntityId> = pcs.iter().collect();
         
         for pc in pc_vec {
             text.push(Line::from(Span::styled(
-                format!("  • {}", pc),
+                format!("  • {}", app.encounter.name_of(*pc)),
                 Style::default().fg(Color::White),
             )));
         }
@@ -48,10 +61,10 @@ pub fn render_creation_ui(_app: &App, params: &CombatParameters) -> Paragraph<'s
             Style::default().fg(Color::DarkGray),
         )));
     } else {
-        let npc_vec: Vec<&String> = npcs.iter().collect();
+        let npc_vec: Vec<&EntityId> = npcs.iter().collect();
         for npc in npc_vec {
             text.push(Line::from(Span::styled(
-                format!("  • {}", npc),
+                format!("  • {}", app.encounter.name_of(*npc)),
                 Style::default().fg(Color::White),
             )));
         }
@@ -72,7 +85,12 @@ pub fn render_instructions_setup() -> Paragraph<'static> {
         Line::from("• Press 'p' to add a PC"),
         Line::from("• Press 'b' to add an NPC"),
         Line::from("• Press 'x' to remove an PC or NPC"),
+        Line::from("• Press 'm' to change the map"),
         Line::from("• Press 'n' to start combat"),
+        Line::from("• Press 'l' to resume a saved combat"),
+        Line::from("• Press 'd' to roll dice, such as 1d10 + 2"),
+        Line::from("• Press 't' to call for a test, 'g' to run a montage test"),
+        Line::from("• Press F5 to reload the content"),
         Line::from(""),
         Line::from("• Press 'q' to quit"),
     ];
@@ -106,7 +124,7 @@ pub fn render_monster_definitions(app: &App) -> Paragraph<'static> {
                     Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
                 ),
                 Span::styled(
-                    format!("{} (max stamina: {})", def_name, definition.max_stamina),
+                    format!("{} (max stamina: {}, size: {})", def_name, definition.max_stamina, definition.size),
                     Style::default().fg(Color::White),
                 ),
             ]));
@@ -148,7 +166,7 @@ pub fn render_hero_definitions(app: &App) -> Paragraph<'static> {
                     Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
                 ),
                 Span::styled(
-                    format!("{} (max stamina: {})", def_name, definition.max_stamina),
+                    format!("{} (max stamina: {}, size: {})", def_name, definition.max_stamina, definition.size),
                     Style::default().fg(Color::White),
                 ),
             ]));
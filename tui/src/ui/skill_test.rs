@@ -0,0 +1,84 @@
+use odsr_engine::{BaneState, Characteristic, Difficulty, EdgeState, EntityDefinition, SkillTest, TurnSide};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+
+use crate::app::{App, TestCall};
+
+fn option(key: String, label: String, note: String) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(format!("[{}] ", key), Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+        Span::styled(label, Style::default().fg(Color::White)),
+        Span::styled(note, Style::default().fg(Color::DarkGray)),
+    ])
+}
+
+fn chance(test: &SkillTest, hero: &EntityDefinition) -> String {
+    format!("  {:.0}% to succeed", test.success_chance(hero, EdgeState::None, BaneState::None) * 100.0)
+}
+
+/// The choices left in the test being called for, with each one's chance of success once it can be worked out
+pub fn render_test_call(app: &App, call: &TestCall) -> Paragraph<'static> {
+    let mut text = vec![];
+    let hero = call.hero.and_then(|id| app.encounter.entity(id));
+    if let Some(hero) = call.hero {
+        let mut chosen = vec![app.encounter.name_of(hero)];
+        chosen.extend(call.characteristic.map(|characteristic| format!("{:?}", characteristic)));
+        chosen.extend(call.difficulty.map(|difficulty| format!("{:?}", difficulty)));
+        text.push(Line::from(Span::styled(chosen.join(", "), Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))));
+        text.push(Line::from(""));
+    }
+
+    let (prompt, options) = match (hero, call.characteristic, call.difficulty) {
+        (None, _, _) => {
+            let heroes = app.encounter.combatants(TurnSide::PC);
+            let options = heroes
+                .iter()
+                .enumerate()
+                .map(|(index, id)| option((index + 1).to_string(), app.encounter.name_of(*id), String::new()))
+                .collect();
+            ("Which hero makes the test?", options)
+        }
+        (Some(hero), None, _) => {
+            let scores = hero.definition().characteristics;
+            let options = Characteristic::ALL
+                .iter()
+                .map(|characteristic| {
+                    let key = characteristic.letter().to_ascii_lowercase().to_string();
+                    option(key, format!("{:?}", characteristic), format!("  {:+}", scores.get(*characteristic)))
+                })
+                .collect();
+            ("Which characteristic?", options)
+        }
+        (Some(hero), Some(characteristic), None) => {
+            let options = Difficulty::ALL
+                .iter()
+                .map(|difficulty| {
+                    let label = format!("{:?}", difficulty);
+                    let key = label[..1].to_lowercase();
+                    option(key, label, chance(&SkillTest::new(characteristic, *difficulty), hero.definition()))
+                })
+                .collect();
+            ("How hard is it?", options)
+        }
+        (Some(hero), Some(characteristic), Some(difficulty)) => {
+            let test = SkillTest::new(characteristic, difficulty);
+            let mut options = vec![option("0".to_string(), "No relevant skill".to_string(), chance(&test, hero.definition()))];
+            for (index, skill) in hero.definition().skills.iter().enumerate() {
+                let skilled = test.clone().with_skill(skill);
+                options.push(option((index + 1).to_string(), skill.clone(), chance(&skilled, hero.definition())));
+            }
+            ("Does one of their skills apply?", options)
+        }
+    };
+    text.push(Line::from(Span::styled(prompt, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))));
+    text.extend(options);
+    text.push(Line::from(""));
+    text.push(Line::from(Span::styled("Press 'x' to cancel", Style::default().fg(Color::Yellow))));
+
+    Paragraph::new(text)
+        .block(Block::default().borders(Borders::ALL).title("Call for a Test"))
+        .wrap(Wrap { trim: true })
+}
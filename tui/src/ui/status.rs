@@ -10,6 +10,10 @@ pub fn render_status_widget<'a>(app: &'a App) -> Paragraph<'a> {
         let prompt = match text_input.input_type {
             TextInputType::NPCName => "NPC Name: ",
             TextInputType::PCName => "PC Name: ",
+            TextInputType::Rename(_) => "New Name: ",
+            TextInputType::SavePath => "Save to: ",
+            TextInputType::LoadPath => "Resume from: ",
+            TextInputType::Roll => "Roll: ",
         };
         let input_text = format!("{}{}_", prompt, text_input.buffer);
         Paragraph::new(input_text)
@@ -43,6 +47,16 @@ pub fn render_log_view<'a>(app: &'a App) -> Paragraph<'a> {
             lines.push(Line::from(message.as_str()));
         }
     }
+
+    // The engine's event log is the authoritative record of the combat
+    if let Some(state) = app.encounter.state() {
+        lines.push(Line::from(""));
+        lines.push(Line::from("Combat Events (latest first)"));
+        let count = state.events().len();
+        for (index, event) in state.events().iter().enumerate() {
+            lines.push(Line::from(format!("{:>4}. {}", count - index, app.encounter.describe(event))));
+        }
+    }
     
     Paragraph::new(lines)
         .style(Style::default().fg(Color::White))
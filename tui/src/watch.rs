@@ -0,0 +1,52 @@
+use std::{fs, path::{Path, PathBuf}, time::SystemTime};
+
+/// Notices changes to content files by polling their modification times
+pub struct ContentWatcher {
+    roots: Vec<PathBuf>,
+    snapshot: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl ContentWatcher {
+    pub fn new(roots: Vec<PathBuf>) -> Self {
+        let snapshot = snapshot(&roots);
+        Self { roots, snapshot }
+    }
+
+    /// True if any file was added, removed or modified since the last check
+    pub fn changed(&mut self) -> bool {
+        let snapshot = snapshot(&self.roots);
+        if snapshot == self.snapshot {
+            return false;
+        }
+        self.snapshot = snapshot;
+        true
+    }
+}
+
+fn snapshot(roots: &[PathBuf]) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let mut files = vec![];
+    for root in roots {
+        collect(root, &mut files);
+    }
+    files.sort();
+    files
+}
+
+fn collect(dir: &Path, files: &mut Vec<(PathBuf, Option<SystemTime>)>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        // Like the content loader, symlinked directories are skipped so a link cycle can't recurse forever
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            collect(&path, files);
+        } else if path.is_dir() {
+            continue;
+        } else {
+            let modified = entry.metadata().and_then(|metadata| metadata.modified()).ok();
+            files.push((path, modified));
+        }
+    }
+}